@@ -0,0 +1,61 @@
+// Per-file access control lists (acls.json in the AirShare config dir)
+//
+// Maps a shared file name to the peer IDs allowed to download it. Files
+// without an entry are open to every peer. Peers identify themselves with
+// the `X-AirShare-Peer-ID` header, so ACLs only keep out honest clients
+// unless a PIN (see `auth`) is also set.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Request header carrying the requesting peer's device ID
+pub const PEER_ID_HEADER: &str = "X-AirShare-Peer-ID";
+
+const ACLS_FILE: &str = "acls.json";
+
+/// Filename -> peer IDs allowed to download it
+pub type FileAcls = HashMap<String, Vec<String>>;
+
+fn acls_path() -> Option<PathBuf> {
+    crate::config::config_dir().map(|dir| dir.join(ACLS_FILE))
+}
+
+/// Load the saved ACLs, starting empty if the file is missing or invalid
+pub fn load() -> FileAcls {
+    let Some(path) = acls_path() else {
+        return FileAcls::new();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("[ACL] Invalid ACL file {:?}: {}", path, e);
+            FileAcls::new()
+        }),
+        Err(_) => FileAcls::new(),
+    }
+}
+
+/// Write the ACLs, creating the config directory if needed
+pub fn save(acls: &FileAcls) -> Result<(), String> {
+    let path = acls_path().ok_or("Could not find config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(acls)
+        .map_err(|e| format!("Failed to serialize ACLs: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write ACLs: {}", e))
+}
+
+/// Whether the peer sending `peer_id` may download `filename`
+///
+/// A file with a non-empty list refuses requests that carry no peer ID.
+pub fn is_allowed(acls: &FileAcls, filename: &str, peer_id: Option<&str>) -> bool {
+    match acls.get(filename) {
+        Some(allowed) if !allowed.is_empty() => {
+            peer_id.is_some_and(|id| allowed.iter().any(|allowed_id| allowed_id == id))
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,158 @@
+// Folder sharing: zip a folder to hold it, unzip one that was received
+//
+// The zip is written into the shared folder so `/file/{filename}` serves it
+// like any other file. It lives only as long as the grab, and is deleted when
+// AirShare shuts down.
+
+use crate::discovery::SharedDiscoveryState;
+use crate::server::{resolve_unique_path, SharedServerState};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+/// Zip everything under `folder` into `dest`, returning the number of files added
+///
+/// Entries are stored relative to `folder`. Symlinks are skipped so the
+/// archive can't pull in files from outside it.
+pub fn zip_folder(folder: &Path, dest: &Path) -> Result<usize, String> {
+    let file = File::create(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    let mut zip = zip::ZipWriter::new(BufWriter::new(file));
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    let mut files = 0;
+    for entry in walkdir::WalkDir::new(folder).min_depth(1) {
+        let entry = entry.map_err(|e| format!("Failed to read folder: {}", e))?;
+        // Holding the shared folder itself would otherwise zip the zip
+        if entry.path() == dest {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(folder)
+            .map_err(|e| format!("Failed to read folder: {}", e))?;
+        // Zip entry names always use forward slashes
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if entry.file_type().is_dir() {
+            zip.add_directory(name, options)
+                .map_err(|e| format!("Failed to add {}: {}", relative.display(), e))?;
+        } else if entry.file_type().is_file() {
+            zip.start_file(name, options)
+                .map_err(|e| format!("Failed to add {}: {}", relative.display(), e))?;
+            let mut source = File::open(entry.path())
+                .map_err(|e| format!("Failed to open {}: {}", relative.display(), e))?;
+            std::io::copy(&mut source, &mut zip)
+                .map_err(|e| format!("Failed to add {}: {}", relative.display(), e))?;
+            files += 1;
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish zip: {}", e))?
+        .flush()
+        .map_err(|e| format!("Failed to write zip: {}", e))?;
+    Ok(files)
+}
+
+/// Unpack `zip_path` into `dest_dir`, returning the paths of the extracted files
+///
+/// Entries whose names would land outside `dest_dir` (absolute paths, `..`)
+/// are skipped.
+pub fn unzip(zip_path: &Path, dest_dir: &Path) -> Result<Vec<String>, String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open {:?}: {}", zip_path, e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid zip: {}", e))?;
+    std::fs::create_dir_all(dest_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", dest_dir, e))?;
+
+    let mut extracted = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("Invalid zip: {}", e))?;
+        let Some(relative) = entry.enclosed_name() else {
+            eprintln!("[Archive] Skipped unsafe entry: {}", entry.name());
+            continue;
+        };
+        let path = dest_dir.join(relative);
+
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)
+                .map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        let mut out = File::create(&path).map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+        std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {:?}: {}", path, e))?;
+        extracted.push(path.to_string_lossy().to_string());
+    }
+
+    Ok(extracted)
+}
+
+/// Tauri command to hold a whole folder, shared as a zip
+///
+/// The zip is named after the folder (`Photos.zip`, or `Photos (1).zip` if
+/// taken) and deleted once the grab is cleared or replaced, or on shutdown.
+#[tauri::command]
+pub async fn set_grab_folder(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    server: tauri::State<'_, SharedServerState>,
+    folder_path: String,
+) -> Result<(), String> {
+    let folder = PathBuf::from(&folder_path);
+    if !folder.is_dir() {
+        return Err(format!("Not a folder: {}", folder_path));
+    }
+    let name = folder
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid folder path: {}", folder_path))?;
+
+    // Drop the previous zip first so its name can be reused
+    state.write().await.discard_held_archive();
+    let zip_path = resolve_unique_path(&server.shared_dir, &format!("{}.zip", name));
+
+    let (source, dest) = (folder.clone(), zip_path.clone());
+    let zipped = tauri::async_runtime::spawn_blocking(move || zip_folder(&source, &dest))
+        .await
+        .map_err(|e| format!("Zip task failed: {}", e))?;
+    let files = match zipped {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&zip_path).await;
+            return Err(e);
+        }
+    };
+
+    let zip_name = zip_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    println!("[Archive] Zipped {} ({} files) as {}", folder_path, files, zip_name);
+
+    let mut discovery = state.write().await;
+    discovery.set_grab(&zip_name);
+    discovery.held_archive = Some(zip_path);
+    Ok(())
+}
+
+/// Tauri command to unpack a received zip, returning the extracted file paths
+#[tauri::command]
+pub async fn extract_zip(zip_path: String, dest_dir: String) -> Result<Vec<String>, String> {
+    let extracted = tauri::async_runtime::spawn_blocking(move || {
+        unzip(Path::new(&zip_path), Path::new(&dest_dir))
+    })
+    .await
+    .map_err(|e| format!("Unzip task failed: {}", e))??;
+
+    println!("[Archive] Extracted {} files", extracted.len());
+    Ok(extracted)
+}
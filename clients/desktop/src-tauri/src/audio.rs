@@ -0,0 +1,204 @@
+// Audio output devices for gesture media controls
+//
+// Devices are enumerated through cpal everywhere. Changing the system default
+// output has no cross-platform API, so `set_default_output` is implemented per
+// OS: the Sound control panel's policy-config COM interface on Windows,
+// `SwitchAudioSource` on macOS and `pactl` on Linux.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+
+/// Tauri command to list audio output devices as `[{ name, is_default }]`
+#[tauri::command]
+pub fn list_audio_devices() -> Result<Vec<serde_json::Value>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|device| device.name().ok());
+    let devices = host
+        .output_devices()
+        .map_err(|e| format!("Failed to list audio devices: {}", e))?;
+
+    Ok(devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| {
+            serde_json::json!({
+                "is_default": default_name.as_deref() == Some(name.as_str()),
+                "name": name,
+            })
+        })
+        .collect())
+}
+
+/// Tauri command to make `device_name` the system default output
+///
+/// On Linux the name is matched against PulseAudio/PipeWire sink names and
+/// descriptions, since those are what `pactl` can switch between.
+#[tauri::command]
+pub fn set_default_audio_device(device_name: String) -> Result<(), String> {
+    set_default_output(&device_name)?;
+    println!("[Audio] Default output: {}", device_name);
+    Ok(())
+}
+
+/// `IPolicyConfigVista`, the undocumented interface behind the Sound control panel
+#[cfg(target_os = "windows")]
+#[allow(non_snake_case)]
+mod policy_config {
+    use std::ffi::c_void;
+    use windows::core::{GUID, HRESULT, PCWSTR};
+    use windows::Win32::Foundation::PROPERTYKEY;
+    use windows::Win32::Media::Audio::ERole;
+
+    // Only SetDefaultEndpoint is called; the rest keep the vtable layout intact
+    #[windows_core::interface("568b9108-44bf-40b4-9006-86afe5b5a620")]
+    pub unsafe trait IPolicyConfigVista: windows_core::IUnknown {
+        fn GetMixFormat(&self, device_id: PCWSTR, format: *mut *mut c_void) -> HRESULT;
+        fn GetDeviceFormat(&self, device_id: PCWSTR, default: i32, format: *mut *mut c_void) -> HRESULT;
+        fn SetDeviceFormat(&self, device_id: PCWSTR, endpoint_format: *mut c_void, mix_format: *mut c_void) -> HRESULT;
+        fn GetProcessingPeriod(&self, device_id: PCWSTR, default: i32, default_period: *mut i64, min_period: *mut i64) -> HRESULT;
+        fn SetProcessingPeriod(&self, device_id: PCWSTR, period: *mut i64) -> HRESULT;
+        fn GetShareMode(&self, device_id: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn SetShareMode(&self, device_id: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn GetPropertyValue(&self, device_id: PCWSTR, key: *const PROPERTYKEY, value: *mut c_void) -> HRESULT;
+        fn SetPropertyValue(&self, device_id: PCWSTR, key: *const PROPERTYKEY, value: *mut c_void) -> HRESULT;
+        pub fn SetDefaultEndpoint(&self, device_id: PCWSTR, role: ERole) -> HRESULT;
+        fn SetEndpointVisibility(&self, device_id: PCWSTR, visible: i32) -> HRESULT;
+    }
+
+    /// CLSID of `CPolicyConfigVistaClient`
+    pub const POLICY_CONFIG_VISTA_CLIENT: GUID = GUID::from_u128(0x294935ce_f637_4e7c_a41b_ab255460b862);
+}
+
+#[cfg(target_os = "windows")]
+fn set_default_output(device_name: &str) -> Result<(), String> {
+    use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+
+    // Fails harmlessly when the thread already joined an apartment
+    let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
+    // Every COM object is released inside, before the apartment is left
+    let result = unsafe { set_default_endpoint(device_name) };
+    if initialized {
+        unsafe { CoUninitialize() };
+    }
+    result
+}
+
+/// Find the active render endpoint whose friendly name (cpal's device name)
+/// is `device_name` and make it the default for every role
+#[cfg(target_os = "windows")]
+unsafe fn set_default_endpoint(device_name: &str) -> Result<(), String> {
+    use policy_config::{IPolicyConfigVista, POLICY_CONFIG_VISTA_CLIENT};
+    use windows::core::PCWSTR;
+    use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+    use windows::Win32::Media::Audio::{
+        eCommunications, eConsole, eMultimedia, eRender, IMMDevice, IMMDeviceEnumerator,
+        MMDeviceEnumerator, DEVICE_STATE_ACTIVE,
+    };
+    use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PropVariantToStringAlloc};
+    use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_ALL, STGM_READ};
+
+    let friendly_name = |device: &IMMDevice| -> windows::core::Result<String> {
+        let mut value = device.OpenPropertyStore(STGM_READ)?.GetValue(&PKEY_Device_FriendlyName)?;
+        let name = PropVariantToStringAlloc(&value);
+        let _ = PropVariantClear(&mut value);
+        let name = name?;
+        let text = name.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(name.0 as _));
+        Ok(text)
+    };
+
+    let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+        .map_err(|e| format!("Failed to open audio devices: {}", e))?;
+    let devices = enumerator
+        .EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)
+        .map_err(|e| format!("Failed to list audio devices: {}", e))?;
+    let count = devices.GetCount().map_err(|e| format!("Failed to list audio devices: {}", e))?;
+
+    for index in 0..count {
+        let Ok(device) = devices.Item(index) else {
+            continue;
+        };
+        if friendly_name(&device).ok().as_deref() != Some(device_name) {
+            continue;
+        }
+
+        let id = device.GetId().map_err(|e| format!("Failed to read device ID: {}", e))?;
+        let policy: windows::core::Result<IPolicyConfigVista> =
+            CoCreateInstance(&POLICY_CONFIG_VISTA_CLIENT, None, CLSCTX_ALL);
+        // The Sound control panel sets all three roles, so apps that track any of them follow
+        let result = policy.and_then(|policy| {
+            [eConsole, eMultimedia, eCommunications]
+                .into_iter()
+                .try_for_each(|role| policy.SetDefaultEndpoint(PCWSTR(id.0), role).ok())
+        });
+        CoTaskMemFree(Some(id.0 as _));
+        return result.map_err(|e| format!("Failed to set default audio device: {}", e));
+    }
+
+    Err(format!("Audio device not found: {}", device_name))
+}
+
+#[cfg(target_os = "macos")]
+fn set_default_output(device_name: &str) -> Result<(), String> {
+    let output = std::process::Command::new("SwitchAudioSource")
+        .args(["-t", "output", "-s", device_name])
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to run SwitchAudioSource (install it with `brew install switchaudio-osx`): {}",
+                e
+            )
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "SwitchAudioSource failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn set_default_output(device_name: &str) -> Result<(), String> {
+    let output = std::process::Command::new("pactl")
+        .args(["list", "sinks"])
+        .output()
+        .map_err(|e| format!("Failed to run pactl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "pactl list sinks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let sinks = parse_pactl_sinks(&String::from_utf8_lossy(&output.stdout));
+    let sink = sinks
+        .iter()
+        .find(|(name, description)| name == device_name || description == device_name)
+        .map(|(name, _)| name)
+        .ok_or_else(|| format!("Audio device not found: {}", device_name))?;
+
+    let status = std::process::Command::new("pactl")
+        .args(["set-default-sink", sink])
+        .status()
+        .map_err(|e| format!("Failed to run pactl: {}", e))?;
+    if !status.success() {
+        return Err(format!("pactl set-default-sink failed with {}", status));
+    }
+    Ok(())
+}
+
+/// `(name, description)` of every sink in `pactl list sinks` output
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_pactl_sinks(listing: &str) -> Vec<(String, String)> {
+    let mut sinks = Vec::new();
+    let mut name = None;
+    for line in listing.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("Name: ") {
+            name = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("Description: ") {
+            if let Some(name) = name.take() {
+                sinks.push((name, value.to_string()));
+            }
+        }
+    }
+    sinks
+}
@@ -0,0 +1,68 @@
+// Shared-secret PIN authentication between peers
+//
+// Devices configured with the same PIN sign their beacons with
+// HMAC-SHA256(PIN, device ID) and send a PIN-derived token with every file
+// request. The PIN itself never leaves the device, and the request token
+// differs from every beacon token, so sniffing beacons is not enough to
+// download files.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Request header carrying `request_token` on file downloads
+pub const TOKEN_HEADER: &str = "X-AirShare-Token";
+
+/// Message signed for HTTP requests; device IDs are UUIDs, so never equal to this
+const REQUEST_TOKEN_MESSAGE: &str = "airshare-http";
+
+/// Hex HMAC-SHA256 of `message` keyed with `pin`
+fn sign(pin: &str, message: &str) -> String {
+    // HMAC accepts keys of any length, so this cannot fail
+    let mut mac = Hmac::<Sha256>::new_from_slice(pin.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Token a device with `device_id` puts in its beacons
+pub fn beacon_token(device_id: &str, pin: &str) -> String {
+    sign(pin, device_id)
+}
+
+/// Token sent in `TOKEN_HEADER` to download from any device sharing `pin`
+pub fn request_token(pin: &str) -> String {
+    sign(pin, REQUEST_TOKEN_MESSAGE)
+}
+
+/// Compare tokens without leaking how many leading characters matched
+pub fn tokens_match(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// How this device identifies itself when requesting files from peers
+#[derive(Debug, Clone, Default)]
+pub struct PeerCredentials {
+    /// Sent as `X-AirShare-Peer-ID` for per-file ACLs
+    pub device_id: String,
+    /// `request_token` of the PIN, when one is set
+    pub token: Option<String>,
+}
+
+impl PeerCredentials {
+    /// Add the identity headers to an outgoing request
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = request.header(crate::acl::PEER_ID_HEADER, &self.device_id);
+        match &self.token {
+            Some(token) => request.header(TOKEN_HEADER, token),
+            None => request,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Platform clipboard access for Smart Drop
+//
+// Reading copied files and clearing the clipboard work differently on every
+// OS, so the Smart Drop commands go through `ClipboardBackend` and only
+// `platform_backend()` knows which implementation is compiled in.
+
+/// Clipboard operations Smart Drop needs from the OS
+pub trait ClipboardBackend {
+    /// Paths of the files copied in the file manager
+    fn get_files(&self) -> Result<Vec<String>, String>;
+    /// Empty the clipboard
+    fn clear(&self) -> Result<(), String>;
+}
+
+/// The backend for the OS this build targets
+pub fn platform_backend() -> Box<dyn ClipboardBackend> {
+    #[cfg(target_os = "windows")]
+    return Box::new(WindowsClipboard);
+    #[cfg(target_os = "macos")]
+    return Box::new(MacClipboard);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    return Box::new(LinuxClipboard);
+}
+
+/// Explorer file lists via the native clipboard API
+#[cfg(target_os = "windows")]
+pub struct WindowsClipboard;
+
+#[cfg(target_os = "windows")]
+impl ClipboardBackend for WindowsClipboard {
+    fn get_files(&self) -> Result<Vec<String>, String> {
+        let mut clipboard = arboard::Clipboard::new()
+            .map_err(|e| format!("Failed to open clipboard: {}", e))?;
+        let files = clipboard
+            .get()
+            .file_list()
+            .map_err(|e| format!("No files on clipboard: {}", e))?;
+        Ok(files.iter().map(|path| path.to_string_lossy().to_string()).collect())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.clear())
+            .map_err(|e| format!("Failed to clear clipboard: {}", e))
+    }
+}
+
+/// Reads the file URLs Finder puts on the general pasteboard, one path per line
+#[cfg(target_os = "macos")]
+const MACOS_READ_FILES_SCRIPT: &str = r#"
+ObjC.import('AppKit');
+const urls = $.NSPasteboard.generalPasteboard.readObjectsForClassesOptions(
+    $([$.NSURL]),
+    $({ NSPasteboardURLReadingFileURLsOnlyKey: true })
+);
+const paths = [];
+if (!urls.isNil()) {
+    for (let i = 0; i < urls.count; i++) {
+        paths.push(urls.objectAtIndex(i).path.js);
+    }
+}
+paths.join('\n');
+"#;
+
+/// Finder file lists via `osascript`, cleared with `pbcopy`
+#[cfg(target_os = "macos")]
+pub struct MacClipboard;
+
+#[cfg(target_os = "macos")]
+impl ClipboardBackend for MacClipboard {
+    fn get_files(&self) -> Result<Vec<String>, String> {
+        let output = read_command("osascript", &["-l", "JavaScript", "-e", MACOS_READ_FILES_SCRIPT])?;
+        Ok(output
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        // pbcopy with empty input replaces whatever was copied
+        run_command("pbcopy", &[])
+    }
+}
+
+/// File manager copies via `wl-paste`/`wl-copy` on Wayland, `xclip` on X11
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct LinuxClipboard;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl LinuxClipboard {
+    fn on_wayland() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ClipboardBackend for LinuxClipboard {
+    fn get_files(&self) -> Result<Vec<String>, String> {
+        // Nautilus, Dolphin and Thunar all offer copied files as text/uri-list
+        let uri_list = if Self::on_wayland() {
+            read_command("wl-paste", &["--no-newline", "--type", "text/uri-list"])?
+        } else {
+            read_command("xclip", &["-selection", "clipboard", "-target", "text/uri-list", "-out"])?
+        };
+        Ok(parse_uri_list(&uri_list))
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        if Self::on_wayland() {
+            run_command("wl-copy", &["--clear"])
+        } else {
+            run_command("xclip", &["-selection", "clipboard", "-in", "/dev/null"])
+        }
+    }
+}
+
+/// Run a clipboard helper and return its stdout
+#[cfg(unix)]
+fn read_command(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Run a clipboard helper with empty stdin, waiting only for its exit status
+///
+/// xclip forks to keep serving the selection it was given, so its stdout
+/// must not be a pipe we wait on.
+#[cfg(unix)]
+fn run_command(program: &str, args: &[&str]) -> Result<(), String> {
+    use std::process::Stdio;
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} failed with {}", program, status));
+    }
+    Ok(())
+}
+
+/// Local paths from a `text/uri-list` body, skipping comments and non-file URIs
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_uri_list(uri_list: &str) -> Vec<String> {
+    uri_list
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|uri| uri.strip_prefix("file://"))
+        // Drop an optional host such as "localhost" before the absolute path
+        .filter_map(|rest| rest.find('/').map(|slash| &rest[slash..]))
+        .map(percent_decode)
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,247 @@
+// Persistent AirShare configuration (config.json in the OS config dir)
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// User-editable settings loaded once at startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AirShareConfig {
+    /// Origins allowed to call the HTTP server (empty = any origin)
+    pub allowed_origins: Vec<String>,
+    /// Expose debugging endpoints such as `/debug/requests`
+    pub debug_mode: bool,
+    /// Multiplier applied to simulated relative mouse moves and scrolls
+    pub gesture_sensitivity: f64,
+    /// Also serve files over the experimental QUIC transport
+    pub use_quic: bool,
+    /// List and serve symlinks in the shared folder (targets must stay inside it)
+    pub follow_symlinks: bool,
+    /// Name of the demo file created in a new shared folder (`null` = none)
+    pub demo_file_name: Option<String>,
+    /// Contents of the demo file (`null` = none)
+    pub demo_file_content: Option<String>,
+    /// Never create the demo file, whatever the name and content say
+    pub skip_demo_file: bool,
+    /// Discovery backends started at launch ("udp", "mdns", "tcp")
+    pub discovery_backends: Vec<String>,
+    /// UDP port for discovery beacons; peers must agree on it to find each other
+    pub discovery_port: u16,
+    /// TCP port of the HTTP file server, advertised to peers in beacons
+    pub server_port: u16,
+    /// What to do when a received file has the name of an existing one
+    pub file_collision_strategy: CollisionStrategy,
+    /// Show OS notifications for grabbed and received files
+    pub notifications_enabled: bool,
+    /// IPv4 address of the interface discovery uses (`null` = the OS default)
+    pub preferred_interface: Option<String>,
+    /// Largest upload accepted in one request, in bytes
+    pub max_upload_size_bytes: u64,
+    /// Dashboard window geometry from before Phantom Mode was last entered
+    pub dashboard_window: Option<PhantomRestoreState>,
+    /// Lowercase extensions `/upload` accepts, without the dot (empty = any)
+    pub allowed_upload_extensions: Vec<String>,
+    /// Global shortcut toggling Phantom Mode, e.g. "CommandOrControl+Shift+P" (`null` = none)
+    pub phantom_hotkey: Option<String>,
+}
+
+/// Physical size and position of the main window outside Phantom Mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhantomRestoreState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// How to save a received file whose name is already taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionStrategy {
+    /// Replace the existing file
+    Overwrite,
+    /// Save as `name (1).ext`, `name (2).ext`, ...
+    #[default]
+    Rename,
+    /// Refuse the file
+    Reject,
+}
+
+/// Phantom Mode shortcut used unless `phantom_hotkey` is set: Ctrl+Shift+P, Cmd+Shift+P on macOS
+pub const DEFAULT_PHANTOM_HOTKEY: &str = "CommandOrControl+Shift+P";
+
+impl Default for AirShareConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            debug_mode: false,
+            gesture_sensitivity: 1.0,
+            use_quic: false,
+            follow_symlinks: false,
+            demo_file_name: Some("demo.txt".to_string()),
+            demo_file_content: Some("Hello from AirShare!\nThis is a demo file.".to_string()),
+            skip_demo_file: false,
+            discovery_backends: vec!["udp".to_string(), "tcp".to_string()],
+            discovery_port: crate::discovery::DEFAULT_DISCOVERY_PORT,
+            server_port: crate::server::DEFAULT_SERVER_PORT,
+            file_collision_strategy: CollisionStrategy::default(),
+            notifications_enabled: true,
+            preferred_interface: None,
+            max_upload_size_bytes: crate::server::DEFAULT_MAX_UPLOAD_SIZE_BYTES,
+            dashboard_window: None,
+            allowed_upload_extensions: Vec::new(),
+            phantom_hotkey: Some(DEFAULT_PHANTOM_HOTKEY.to_string()),
+        }
+    }
+}
+
+impl AirShareConfig {
+    /// Load the config file, falling back to defaults if it is missing or invalid
+    pub fn load() -> Self {
+        let Some(path) = config_path() else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(config) => {
+                    println!("[Config] Loaded {:?}", path);
+                    config
+                }
+                Err(e) => {
+                    eprintln!("[Config] Invalid config {:?}: {}", path, e);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Write the config file, creating the config directory if needed
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path().ok_or("Could not find config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        std::fs::write(&path, json).map_err(|e| format!("Failed to write config: {}", e))?;
+
+        println!("[Config] Saved {:?}", path);
+        Ok(())
+    }
+
+    /// Apply `change` to the config on disk and persist it
+    pub fn update<F: FnOnce(&mut Self)>(change: F) -> Result<Self, String> {
+        let mut config = Self::load();
+        change(&mut config);
+        config.save()?;
+        Ok(config)
+    }
+}
+
+/// Tauri command to read the config file
+///
+/// Returns what is on disk, so changes saved with `set_config` show up here
+/// even before a restart applies them.
+#[tauri::command]
+pub fn get_config() -> Result<AirShareConfig, String> {
+    Ok(AirShareConfig::load())
+}
+
+/// Tauri command to replace the config file
+///
+/// Ports are bound once at startup, so new ports take effect on the next launch.
+#[tauri::command]
+pub fn set_config(config: AirShareConfig) -> Result<(), String> {
+    if config.discovery_port == 0 || config.server_port == 0 {
+        return Err("Ports must be between 1 and 65535".to_string());
+    }
+    config.save()
+}
+
+/// Directory holding all AirShare config files
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("airshare"))
+}
+
+/// Path of the main config file
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.json"))
+}
+
+/// Path of the file holding this device's persistent ID and name
+fn device_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("device.json"))
+}
+
+/// Longest custom device name, in UTF-8 bytes
+pub const MAX_DEVICE_NAME_BYTES: usize = 64;
+
+/// Contents of device.json
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeviceFile {
+    device_id: String,
+    /// Name set with `set_device_name`; the hostname is used when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    device_name: Option<String>,
+}
+
+fn load_device_file() -> Option<DeviceFile> {
+    device_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<DeviceFile>(&json).ok())
+}
+
+fn save_device_file(file: &DeviceFile) -> Result<(), String> {
+    let path = device_path().ok_or("Could not find config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize device file: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write device file: {}", e))?;
+
+    println!("[Config] Saved device info to {:?}", path);
+    Ok(())
+}
+
+/// Load the persisted device ID, creating and saving a new one on first run
+pub fn load_or_create_device_id() -> String {
+    let existing = load_device_file()
+        .map(|file| file.device_id)
+        .filter(|id| uuid::Uuid::parse_str(id).is_ok());
+    if let Some(id) = existing {
+        return id;
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    if let Err(e) = save_device_id(&id) {
+        eprintln!("[Config] {}", e);
+    }
+    id
+}
+
+/// Persist `device_id` to device.json, keeping any custom name
+pub fn save_device_id(device_id: &str) -> Result<(), String> {
+    let mut file = load_device_file().unwrap_or_default();
+    file.device_id = device_id.to_string();
+    save_device_file(&file)
+}
+
+/// The custom device name, if one was saved
+pub fn load_device_name() -> Option<String> {
+    load_device_file().and_then(|file| file.device_name)
+}
+
+/// Persist a custom device name next to `device_id` in device.json
+pub fn save_device_name(device_id: &str, name: &str) -> Result<(), String> {
+    save_device_file(&DeviceFile {
+        device_id: device_id.to_string(),
+        device_name: Some(name.to_string()),
+    })
+}
@@ -0,0 +1,171 @@
+// TCP connection listing for the AirShare server port
+//
+// Reads /proc/net/tcp{,6} on Linux and falls back to parsing `netstat -an`
+// elsewhere, so users can see who is connected without a terminal.
+
+use crate::server::SharedServerState;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+#[cfg(target_os = "linux")]
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Connection on the server port, as reported by the OS
+#[derive(Debug, Clone)]
+struct Connection {
+    remote_ip: String,
+    remote_port: u16,
+    state: String,
+}
+
+/// Tauri command to list active TCP connections on the AirShare server port
+#[tauri::command]
+pub async fn get_server_connections(
+    state: tauri::State<'_, SharedServerState>,
+) -> Result<Vec<Value>, String> {
+    let port = state.port();
+    let connections = tokio::task::spawn_blocking(move || read_connections(port))
+        .await
+        .map_err(|e| format!("Connection lookup failed: {}", e))??;
+
+    Ok(connections
+        .into_iter()
+        .map(|c| {
+            json!({
+                "remote_ip": c.remote_ip,
+                "remote_port": c.remote_port,
+                "state": c.state,
+            })
+        })
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn read_connections(server_port: u16) -> Result<Vec<Connection>, String> {
+    let mut connections = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        // tcp6 is missing when IPv6 is disabled
+        let Ok(contents) = std::fs::read_to_string(table) else {
+            continue;
+        };
+        connections.extend(contents.lines().skip(1).filter_map(|line| parse_proc_line(line, server_port)));
+    }
+    Ok(connections)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_connections(server_port: u16) -> Result<Vec<Connection>, String> {
+    let output = std::process::Command::new("netstat")
+        .args(["-an", "-p", "tcp"])
+        .output()
+        .map_err(|e| format!("Failed to run netstat: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().filter_map(|line| parse_netstat_line(line, server_port)).collect())
+}
+
+/// Parse one row of /proc/net/tcp: `sl local_address rem_address st ...`
+#[cfg(target_os = "linux")]
+fn parse_proc_line(line: &str, server_port: u16) -> Option<Connection> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (_, local_port) = parse_proc_addr(fields.get(1)?)?;
+    let (remote_ip, remote_port) = parse_proc_addr(fields.get(2)?)?;
+    let state = proc_state_name(fields.get(3)?);
+
+    if local_port != server_port || state == "LISTEN" {
+        return None;
+    }
+
+    Some(Connection {
+        remote_ip: remote_ip.to_string(),
+        remote_port,
+        state: state.to_string(),
+    })
+}
+
+/// Decode `0100007F:1F90`; the kernel prints each 32-bit address word in host order
+#[cfg(target_os = "linux")]
+fn parse_proc_addr(field: &str) -> Option<(IpAddr, u16)> {
+    let (ip_hex, port_hex) = field.split_once(':')?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+
+    let mut bytes = Vec::with_capacity(16);
+    for chunk in ip_hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            let v6 = Ipv6Addr::from(octets);
+            // Show IPv4 clients on a dual-stack socket as plain IPv4
+            v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(v6))
+        }
+        _ => return None,
+    };
+    Some((ip, port))
+}
+
+/// Map the hex `st` column to the usual netstat name
+#[cfg(target_os = "linux")]
+fn proc_state_name(code: &str) -> &'static str {
+    match code {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECV",
+        "04" => "FIN_WAIT1",
+        "05" => "FIN_WAIT2",
+        "06" => "TIME_WAIT",
+        "07" => "CLOSE",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTEN",
+        "0B" => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Parse a netstat row, taking local/remote/state from the last three columns
+///
+/// Windows prints `TCP 10.0.0.2:8080 10.0.0.3:51234 ESTABLISHED`, macOS prints
+/// `tcp4 0 0 10.0.0.2.8080 10.0.0.3.51234 ESTABLISHED`.
+#[cfg(not(target_os = "linux"))]
+fn parse_netstat_line(line: &str, server_port: u16) -> Option<Connection> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if !fields.first()?.to_ascii_lowercase().starts_with("tcp") || fields.len() < 4 {
+        return None;
+    }
+
+    let n = fields.len();
+    let (_, local_port) = split_host_port(fields[n - 3])?;
+    let (remote_ip, remote_port) = split_host_port(fields[n - 2])?;
+    let state = fields[n - 1].to_uppercase();
+
+    if local_port != server_port || state == "LISTEN" || state == "LISTENING" {
+        return None;
+    }
+
+    Some(Connection {
+        remote_ip: remote_ip
+            .parse::<IpAddr>()
+            .map(|ip| ip.to_string())
+            .unwrap_or(remote_ip),
+        remote_port,
+        state,
+    })
+}
+
+/// Split `host:port`, `[v6]:port` or BSD-style `host.port`
+#[cfg(not(target_os = "linux"))]
+fn split_host_port(addr: &str) -> Option<(String, u16)> {
+    let separator = if addr.starts_with('[') || addr.matches(':').count() == 1 {
+        ':'
+    } else {
+        '.'
+    };
+    let (host, port) = addr.rsplit_once(separator)?;
+    let port = port.parse().ok()?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_string(), port))
+}
@@ -1,28 +1,172 @@
 // Native Rust UDP Discovery (replaces Go discovery package)
 
+use crate::server::DEFAULT_SERVER_PORT;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::UdpSocket;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
-const DISCOVERY_PORT: u16 = 9988;
+/// Discovery port used unless `discovery_port` is set in the config
+pub const DEFAULT_DISCOVERY_PORT: u16 = 9988;
+/// Further ports tried, one after another, when the discovery port is taken
+const DISCOVERY_PORT_FALLBACKS: u16 = 10;
 const BEACON_INTERVAL_MS: u64 = 1000;
-const BROADCAST_ADDR: &str = "255.255.255.255:9988";
+/// Slowest beacon rate, reached by doubling while no peers are around
+const MAX_BEACON_INTERVAL_MS: u64 = 10_000;
+/// How long without any peer before beacons start slowing down
+const BEACON_BACKOFF_AFTER: std::time::Duration = std::time::Duration::from_secs(30);
+const BROADCAST_IP: Ipv4Addr = Ipv4Addr::BROADCAST;
 // Multicast address for better hotspot compatibility
-const MULTICAST_ADDR: &str = "224.0.0.251:9988";
+const MULTICAST_IP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+/// IPv6 all-nodes link-local group, standing in for broadcast on IPv6 networks
+const MULTICAST_IP_V6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+const PING_TIMEOUT_MS: u64 = 500;
+/// Window in which an identical packet on another interface counts as a duplicate
+/// (shorter than the beacon interval so repeated beacons still refresh peers)
+const RECENT_PACKET_TTL_MS: u64 = 500;
+/// Peers silent for this long are removed
+const PEER_EXPIRY_MS: u64 = BEACON_INTERVAL_MS * 5;
+/// How often the listener checks for expired peers
+pub const PEER_SWEEP_INTERVAL_MS: u64 = BEACON_INTERVAL_MS;
+/// Default peer lifetime advertised in beacons
+const DEFAULT_BEACON_TTL_MS: u64 = BEACON_INTERVAL_MS * 3;
+/// Files smaller than this are embedded in the beacon instead of fetched over HTTP
+const INLINE_FILE_MAX_BYTES: usize = 100;
+/// Longest text a device can hold and advertise in its beacon (UTF-8 bytes)
+pub const MAX_HELD_TEXT_BYTES: usize = 512;
+/// Longest base64 `inline_data` accepted from a beacon
+const INLINE_DATA_MAX_LEN: usize = 200;
+/// Largest datagram handled by the listener; anything bigger is dropped unparsed.
+/// Beacons are usually a few hundred bytes, but with every string field at its
+/// limit and JSON-escaped (up to 6 bytes per character) they approach 6 KiB.
+const MAX_BEACON_BYTES: usize = 8192;
+/// Beacons above this size are logged at debug level to help diagnose truncation
+const LARGE_BEACON_BYTES: usize = 1024;
+/// Largest UDP payload; the receive buffer holds any datagram whole
+const MAX_UDP_PAYLOAD: usize = 65535;
+/// Longest peer name accepted from a beacon (UTF-8 bytes)
+const MAX_PEER_NAME_BYTES: usize = 128;
+
+/// Beacon format this build sends; bump on breaking changes
+pub const BEACON_VERSION: u8 = 1;
+
+/// Version assumed for beacons without a `version` field (sent before it existed)
+fn default_version() -> u8 {
+    1
+}
 
 /// Beacon packet broadcast over UDP
+///
+/// Every field has a default and unknown fields are ignored, so older clients
+/// can always parse beacons from newer versions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BeaconPacket {
+    /// Format version, so receivers can tell when a beacon has changed shape
+    #[serde(default = "default_version")]
+    pub version: u8,
     pub id: String,
     pub ip: String,
     pub name: String,
-    #[serde(rename = "isHolding", default)]
+    #[serde(rename = "isHolding")]
     pub is_holding: bool,
-    #[serde(rename = "heldFile", default)]
+    #[serde(rename = "heldFile")]
     pub held_file: String,
+    /// How long receivers should keep this peer; 0 means "remove me now"
+    pub ttl_ms: u64,
+    /// Base64 contents of a tiny held file, so peers can skip the HTTP download
+    #[serde(rename = "inlineData", skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<String>,
+    /// Sender can decode zstd-compressed downloads
+    #[serde(rename = "supportsZstd", default)]
+    pub supports_zstd: bool,
+    /// Port the sender's HTTP server listens on
+    #[serde(rename = "serverPort")]
+    pub server_port: u16,
+    /// HMAC-SHA256 of `id` keyed with the sender's PIN, when one is set
+    #[serde(rename = "authToken", default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Text snippet the sender is holding instead of a file
+    #[serde(rename = "heldText", skip_serializing_if = "Option::is_none")]
+    pub held_text: Option<String>,
+}
+
+impl Default for BeaconPacket {
+    fn default() -> Self {
+        Self {
+            version: BEACON_VERSION,
+            id: String::new(),
+            ip: String::new(),
+            name: String::new(),
+            is_holding: false,
+            held_file: String::new(),
+            ttl_ms: DEFAULT_BEACON_TTL_MS,
+            inline_data: None,
+            supports_zstd: false,
+            server_port: DEFAULT_SERVER_PORT,
+            auth_token: None,
+            held_text: None,
+        }
+    }
+}
+
+/// Version 1 beacon layout (the current one); a distinct struct once the format changes
+pub type BeaconPacketV1 = BeaconPacket;
+
+/// Convert a version 1 beacon into the current layout
+pub fn upgrade_from_v1(old: BeaconPacketV1) -> BeaconPacket {
+    old
+}
+
+impl BeaconPacket {
+    /// Build a beacon advertising the current discovery state
+    fn from_state(state: &DiscoveryState, ttl_ms: u64) -> Self {
+        Self {
+            version: BEACON_VERSION,
+            id: state.device_id.clone(),
+            ip: state.local_ip.clone(),
+            name: state.device_name.clone(),
+            is_holding: state.is_holding,
+            held_file: state.held_file.clone(),
+            ttl_ms,
+            inline_data: state.inline_data.clone(),
+            supports_zstd: true,
+            server_port: state.server_port,
+            // Recomputed for every beacon so a PIN change takes effect immediately
+            auth_token: state
+                .pin
+                .as_deref()
+                .map(|pin| crate::auth::beacon_token(&state.device_id, pin)),
+            held_text: state.held_text.clone(),
+        }
+    }
+
+    /// Check the fields a received beacon is trusted with
+    fn validate(&self) -> Result<(), String> {
+        if uuid::Uuid::parse_str(&self.id).is_err() {
+            return Err(format!("invalid id {:?}", self.id));
+        }
+        if self.ip.parse::<IpAddr>().is_err() {
+            return Err(format!("invalid ip {:?}", self.ip));
+        }
+        if self.name.len() > MAX_PEER_NAME_BYTES {
+            return Err(format!("name is {} bytes", self.name.len()));
+        }
+        Ok(())
+    }
+}
+
+/// Ping/pong packets sharing the discovery port with beacons
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ControlPacket {
+    Ping { id: String, nonce: u64 },
+    Pong { nonce: u64 },
 }
 
 /// Discovered peer
@@ -35,6 +179,60 @@ pub struct Peer {
     pub is_holding: bool,
     #[serde(rename = "heldFile")]
     pub held_file: String,
+    /// Download URL for the held file, set only while `is_holding`
+    #[serde(rename = "heldFileUrl", default)]
+    pub held_file_url: Option<String>,
+    /// Base64 contents of the held file when small enough to travel in the beacon
+    #[serde(rename = "inlineData", default)]
+    pub inline_data: Option<String>,
+    /// The peer advertised it can decode zstd-compressed downloads
+    #[serde(rename = "supportsZstd", default)]
+    pub supports_zstd: bool,
+    /// Port of the peer's HTTP server
+    #[serde(rename = "serverPort", default = "default_server_port")]
+    pub server_port: u16,
+    /// Text the peer is holding, when it grabbed text instead of a file
+    #[serde(rename = "heldText", default)]
+    pub held_text: Option<String>,
+}
+
+fn default_server_port() -> u16 {
+    DEFAULT_SERVER_PORT
+}
+
+/// Change in a peer reported to the listener callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerEvent {
+    /// First beacon from this peer
+    Discovered,
+    /// The peer started or stopped holding a file or text
+    GrabUpdate,
+    /// The peer announced it is shutting down
+    Left,
+    /// No beacon within `PEER_EXPIRY_MS`
+    Lost,
+}
+
+/// Whether a held file of `size` bytes should travel inside the beacon
+pub fn should_embed_inline(size: usize) -> bool {
+    size < INLINE_FILE_MAX_BYTES
+}
+
+/// Decode a peer's inline file contents, if any
+pub fn decode_inline_data(peer: &Peer) -> Option<Vec<u8>> {
+    let data = peer.inline_data.as_ref()?;
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+/// Build the URL a peer serves its held file from
+pub fn held_file_url(ip: &str, server_port: u16, is_holding: bool, held_file: &str) -> Option<String> {
+    if !is_holding || held_file.is_empty() {
+        return None;
+    }
+
+    crate::server::peer_file_url(ip, server_port, held_file)
+        .ok()
+        .map(|url| url.to_string())
 }
 
 /// Discovery state shared across async tasks
@@ -44,16 +242,53 @@ pub struct DiscoveryState {
     pub local_ip: String,
     pub is_holding: bool,
     pub held_file: String,
+    /// Base64 contents of the held file, set by `set_inline_data` for tiny files
+    pub inline_data: Option<String>,
+    /// Text held by `set_grab_text`, sent whole in every beacon
+    pub held_text: Option<String>,
     pub peers: HashMap<String, Peer>,
+    /// When each beaconing peer was last heard from; manual peers have no entry
+    pub peer_last_seen: HashMap<String, Instant>,
+    /// UDP port every peer listens on by default (from config)
+    pub default_discovery_port: u16,
+    /// UDP port the listener is bound to (`default_discovery_port` unless it was taken)
+    pub discovery_port: u16,
+    /// Port our HTTP server listens on, advertised in beacons
+    pub server_port: u16,
+    /// Main window opacity set by `set_window_opacity` (0.0-1.0)
+    pub window_opacity: f64,
+    /// Shared secret set by `set_pin`; when set, only peers with the same PIN are accepted
+    pub pin: Option<String>,
+    /// Cancelled by `stop`, ending the beacon and listener loops
+    pub cancellation_token: CancellationToken,
+    /// Interface chosen with `set_preferred_interface`; beacons are sent from it
+    pub preferred_ip: Option<Ipv4Addr>,
+    /// Peers already warned about for sending a newer beacon version
+    pub newer_version_peers: HashSet<String>,
+    /// Temporary zip made by `set_grab_folder`, deleted when the grab ends
+    pub held_archive: Option<std::path::PathBuf>,
+    /// Signalled when a new beaconing peer appears, so a backed-off beacon speeds up again
+    pub peer_found: tokio::sync::watch::Sender<()>,
+    /// Signalled when the grab changes, so the beacon goes out without waiting
+    pub grab_changed: Arc<tokio::sync::Notify>,
 }
 
 impl DiscoveryState {
-    pub fn new() -> Self {
-        let device_id = uuid::Uuid::new_v4().to_string();
-        let device_name = hostname::get()
-            .map(|h| h.to_string_lossy().to_string())
-            .unwrap_or_else(|_| "Unknown".to_string());
+    pub fn new(discovery_port: u16, server_port: u16) -> Self {
+        let device_id = crate::config::load_or_create_device_id();
+        let device_name = crate::config::load_device_name().unwrap_or_else(|| {
+            hostname::get()
+                .map(|h| h.to_string_lossy().to_string())
+                .unwrap_or_else(|_| "Unknown".to_string())
+        });
+        Self::with_identity(device_id, device_name, discovery_port, server_port)
+    }
+
+    /// State for a device whose ID and name are already known
+    fn with_identity(device_id: String, device_name: String, discovery_port: u16, server_port: u16) -> Self {
+        // IPv6-only networks have no IPv4 address to advertise
         let local_ip = local_ip_address::local_ip()
+            .or_else(|_| local_ip_address::local_ipv6())
             .map(|ip| ip.to_string())
             .unwrap_or_else(|_| "127.0.0.1".to_string());
 
@@ -67,132 +302,1035 @@ impl DiscoveryState {
             local_ip,
             is_holding: false,
             held_file: String::new(),
+            inline_data: None,
+            held_text: None,
             peers: HashMap::new(),
+            peer_last_seen: HashMap::new(),
+            default_discovery_port: discovery_port,
+            discovery_port,
+            server_port,
+            window_opacity: 1.0,
+            pin: None,
+            cancellation_token: CancellationToken::new(),
+            preferred_ip: None,
+            newer_version_peers: HashSet::new(),
+            held_archive: None,
+            peer_found: tokio::sync::watch::Sender::new(()),
+            grab_changed: Arc::new(tokio::sync::Notify::new()),
         }
     }
 
+    /// Advertise and send beacons from `ip` instead of the OS default interface
+    pub fn set_preferred_ip(&mut self, ip: Ipv4Addr) {
+        self.preferred_ip = Some(ip);
+        self.local_ip = ip.to_string();
+        println!("[Discovery] Preferred interface: {}", ip);
+    }
+
+    /// Signal the running beacon and listeners to exit
+    ///
+    /// Loops started afterwards watch a fresh token, so discovery can be restarted.
+    pub fn stop(&mut self) {
+        std::mem::take(&mut self.cancellation_token).cancel();
+    }
+
+    /// The known peer at `ip`, if any
+    pub fn peer_by_ip(&self, ip: &str) -> Option<&Peer> {
+        self.peers.values().find(|peer| peer.ip == ip)
+    }
+
+    /// The known peer serving `url`, if any
+    pub fn peer_for_url(&self, url: &str) -> Option<&Peer> {
+        let url = reqwest::Url::parse(url).ok()?;
+        self.peer_by_ip(url.host_str()?)
+    }
+
+    /// HTTP port of the known peer at `ip`, or the default for unknown hosts
+    pub fn peer_server_port(&self, ip: &str) -> u16 {
+        self.peer_by_ip(ip)
+            .map(|peer| peer.server_port)
+            .unwrap_or(DEFAULT_SERVER_PORT)
+    }
+
+    /// Token to send in `X-AirShare-Token` when downloading from peers
+    pub fn request_token(&self) -> Option<String> {
+        self.pin.as_deref().map(crate::auth::request_token)
+    }
+
+    /// Headers to send when downloading from peers
+    pub fn credentials(&self) -> crate::auth::PeerCredentials {
+        crate::auth::PeerCredentials {
+            device_id: self.device_id.clone(),
+            token: self.request_token(),
+        }
+    }
+
+    /// Whether a beacon's `auth_token` proves the sender knows our PIN
+    fn accepts_beacon(&self, packet: &BeaconPacket) -> bool {
+        let Some(pin) = &self.pin else {
+            return true;
+        };
+        let expected = crate::auth::beacon_token(&packet.id, pin);
+        packet
+            .auth_token
+            .as_deref()
+            .is_some_and(|token| crate::auth::tokens_match(&expected, token))
+    }
+
     pub fn set_grab(&mut self, filename: &str) {
+        self.discard_held_archive();
         self.is_holding = true;
         self.held_file = filename.to_string();
+        self.inline_data = None;
+        self.held_text = None;
+        self.grab_changed.notify_one();
         println!("[Discovery] Grab: {}", filename);
     }
 
+    /// Hold a text snippet instead of a file
+    pub fn set_grab_text(&mut self, text: &str) -> Result<(), String> {
+        if text.len() > MAX_HELD_TEXT_BYTES {
+            return Err(format!(
+                "Text is {} bytes; at most {} fit in a beacon, use send_text instead",
+                text.len(),
+                MAX_HELD_TEXT_BYTES
+            ));
+        }
+        self.discard_held_archive();
+        self.is_holding = true;
+        self.held_file.clear();
+        self.inline_data = None;
+        self.held_text = Some(text.to_string());
+        self.grab_changed.notify_one();
+        println!("[Discovery] Grab text ({} bytes)", text.len());
+        Ok(())
+    }
+
+    /// Embed the held file's contents in outgoing beacons
+    pub fn set_inline_data(&mut self, data: &[u8]) {
+        self.inline_data = Some(base64::engine::general_purpose::STANDARD.encode(data));
+    }
+
     pub fn clear_grab(&mut self) {
+        self.discard_held_archive();
         self.is_holding = false;
         self.held_file.clear();
+        self.inline_data = None;
+        self.held_text = None;
+        self.grab_changed.notify_one();
         println!("[Discovery] Release");
     }
+
+    /// Delete the zip of a previously held folder, if any
+    pub fn discard_held_archive(&mut self) {
+        if let Some(path) = self.held_archive.take() {
+            match std::fs::remove_file(&path) {
+                Ok(()) => println!("[Discovery] Deleted {:?}", path),
+                Err(e) => eprintln!("[Discovery] Failed to delete {:?}: {}", path, e),
+            }
+        }
+    }
 }
 
 pub type SharedDiscoveryState = Arc<RwLock<DiscoveryState>>;
 
-/// Start the beacon broadcaster (sends UDP every 1s)
-pub async fn start_beacon(state: SharedDiscoveryState) {
-    let socket = match UdpSocket::bind("0.0.0.0:0").await {
+/// Ports a beacon is sent to
+///
+/// Always includes the default port so peers listening there hear us even
+/// when our own listener had to fall back to another port.
+fn beacon_ports(state: &DiscoveryState) -> Vec<u16> {
+    let mut ports = vec![state.default_discovery_port];
+    if state.discovery_port != state.default_discovery_port {
+        ports.push(state.discovery_port);
+    }
+    ports
+}
+
+/// Whether this machine has a local IPv4 and a local IPv6 address
+fn local_ip_families() -> (bool, bool) {
+    (
+        local_ip_address::local_ip().is_ok(),
+        local_ip_address::local_ipv6().is_ok(),
+    )
+}
+
+/// Indexes of the non-loopback IPv6 interfaces, used as multicast scope IDs
+///
+/// Falls back to `[0]` (the OS default interface) when none are listed.
+fn ipv6_scopes() -> Vec<u32> {
+    let mut scopes: Vec<u32> = if_addrs::get_if_addrs()
+        .map(|interfaces| {
+            interfaces
+                .into_iter()
+                .filter(|iface| !iface.is_loopback() && iface.ip().is_ipv6())
+                .filter_map(|iface| iface.index)
+                .collect()
+        })
+        .unwrap_or_default();
+    scopes.sort_unstable();
+    scopes.dedup();
+    if scopes.is_empty() {
+        scopes.push(0);
+    }
+    scopes
+}
+
+/// A network interface that discovery can use
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub ip: String,
+    /// Whether the OS would pick this interface by default
+    pub is_default: bool,
+}
+
+/// Non-loopback IPv4 interfaces, the ones beacons can be broadcast on
+pub fn network_interfaces() -> Result<Vec<InterfaceInfo>, String> {
+    let default_ip = local_ip_address::local_ip().ok();
+    let interfaces = if_addrs::get_if_addrs()
+        .map_err(|e| format!("Failed to list network interfaces: {}", e))?;
+
+    Ok(interfaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback() && iface.ip().is_ipv4())
+        .map(|iface| InterfaceInfo {
+            is_default: Some(iface.ip()) == default_ip,
+            ip: iface.ip().to_string(),
+            name: iface.name,
+        })
+        .collect())
+}
+
+/// Parse `ip` and check that one of `network_interfaces` has it
+pub fn find_interface(ip: &str) -> Result<Ipv4Addr, String> {
+    let parsed: Ipv4Addr = ip
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid IPv4 address: {}", ip))?;
+    if !network_interfaces()?.iter().any(|iface| iface.ip == parsed.to_string()) {
+        return Err(format!("No network interface has IP {}", parsed));
+    }
+    Ok(parsed)
+}
+
+/// Bind a broadcast-enabled socket for sending beacons
+///
+/// Bound to `interface` when one is preferred, so beacons leave through it
+/// rather than whatever the OS picks (often a VPN).
+async fn bind_beacon_socket(interface: Option<Ipv4Addr>) -> Option<UdpSocket> {
+    let ip = interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+    let socket = match UdpSocket::bind(SocketAddr::from((ip, 0))).await {
         Ok(s) => s,
         Err(e) => {
-            eprintln!("[Discovery] Failed to bind beacon socket: {}", e);
-            return;
+            eprintln!("[Discovery] Failed to bind beacon socket on {}: {}", ip, e);
+            return None;
         }
     };
 
     if let Err(e) = socket.set_broadcast(true) {
         eprintln!("[Discovery] Failed to enable broadcast: {}", e);
-        return;
+        return None;
+    }
+    if let Some(ip) = interface {
+        if let Err(e) = socket2::SockRef::from(&socket).set_multicast_if_v4(&ip) {
+            eprintln!("[Discovery] Failed to send multicast from {}: {}", ip, e);
+        }
+    }
+    Some(socket)
+}
+
+/// Sockets beacons are sent from, one per address family this machine has
+struct BeaconSockets {
+    /// Sends to `BROADCAST_IP` and `MULTICAST_IP`
+    v4: Option<UdpSocket>,
+    /// Sends to `MULTICAST_IP_V6` on each of `v6_scopes`
+    v6: Option<UdpSocket>,
+    v6_scopes: Vec<u32>,
+}
+
+impl BeaconSockets {
+    /// Bind a socket for IPv4, IPv6 or both, depending on which local IPs exist
+    ///
+    /// IPv4 is used whenever a preferred interface is set, or when neither
+    /// family can be detected.
+    async fn bind(interface: Option<Ipv4Addr>) -> Option<Self> {
+        let (has_v4, has_v6) = local_ip_families();
+        let v4 = if has_v4 || !has_v6 || interface.is_some() {
+            bind_beacon_socket(interface).await
+        } else {
+            None
+        };
+        let v6 = if has_v6 { bind_beacon_socket_v6().await } else { None };
+        if v4.is_none() && v6.is_none() {
+            return None;
+        }
+        Some(Self {
+            v4,
+            v6,
+            v6_scopes: ipv6_scopes(),
+        })
     }
 
+    /// Send `data` to the broadcast and multicast groups on each of `ports`
+    async fn send(&self, data: &[u8], ports: &[u16]) {
+        for &port in ports {
+            if let Some(socket) = &self.v4 {
+                let _ = socket.send_to(data, (BROADCAST_IP, port)).await;
+                let _ = socket.send_to(data, (MULTICAST_IP, port)).await;
+            }
+            if let Some(socket) = &self.v6 {
+                for &scope in &self.v6_scopes {
+                    let target = SocketAddrV6::new(MULTICAST_IP_V6, port, 0, scope);
+                    let _ = socket.send_to(data, target).await;
+                }
+            }
+        }
+    }
+}
+
+/// Bind an IPv6 socket for sending beacons to `MULTICAST_IP_V6`
+async fn bind_beacon_socket_v6() -> Option<UdpSocket> {
+    match UdpSocket::bind(SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))).await {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            eprintln!("[Discovery] Failed to bind IPv6 beacon socket: {}", e);
+            None
+        }
+    }
+}
+
+/// Start the beacon broadcaster (sends UDP every 1s)
+///
+/// Sends over IPv4, IPv6 or both, depending on the local IPs. Rebinds the
+/// IPv4 socket when the preferred interface changes.
+pub async fn start_beacon(state: SharedDiscoveryState) {
+    let mut interface = state.read().await.preferred_ip;
+    let Some(mut sockets) = BeaconSockets::bind(interface).await else {
+        return;
+    };
+
     println!("[Discovery] Beacon started, broadcasting every {}ms", BEACON_INTERVAL_MS);
 
+    let (cancellation_token, mut peer_found, grab_changed) = {
+        let s = state.read().await;
+        (s.cancellation_token.clone(), s.peer_found.subscribe(), s.grab_changed.clone())
+    };
+    // Interval before the next beacon, doubled while no peers are around
+    let mut backoff = BEACON_INTERVAL_MS;
+    let mut last_peer_at = Instant::now();
     loop {
-        let packet = {
-            let state = state.read().await;
-            BeaconPacket {
-                id: state.device_id.clone(),
-                ip: state.local_ip.clone(),
-                name: state.device_name.clone(),
-                is_holding: state.is_holding,
-                held_file: state.held_file.clone(),
-            }
+        let (packet, ports, preferred_ip, has_peers) = {
+            let s = state.read().await;
+            (
+                BeaconPacket::from_state(&s, DEFAULT_BEACON_TTL_MS),
+                beacon_ports(&s),
+                s.preferred_ip,
+                // Manual and favorite peers never expire, so they don't count as nearby
+                !s.peer_last_seen.is_empty(),
+            )
         };
 
+        if preferred_ip != interface {
+            if let Some(rebound) = bind_beacon_socket(preferred_ip).await {
+                sockets.v4 = Some(rebound);
+                interface = preferred_ip;
+            }
+        }
+
         if let Ok(json) = serde_json::to_string(&packet) {
             // Send to both broadcast and multicast for better compatibility
-            let _ = socket.send_to(json.as_bytes(), BROADCAST_ADDR).await;
-            let _ = socket.send_to(json.as_bytes(), MULTICAST_ADDR).await;
+            sockets.send(json.as_bytes(), &ports).await;
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(BEACON_INTERVAL_MS)).await;
+        if has_peers {
+            last_peer_at = Instant::now();
+            backoff = BEACON_INTERVAL_MS;
+        } else if last_peer_at.elapsed() >= BEACON_BACKOFF_AFTER && backoff < MAX_BEACON_INTERVAL_MS {
+            backoff = (backoff * 2).min(MAX_BEACON_INTERVAL_MS);
+            println!("[Discovery] No peers nearby, beaconing every {}ms", backoff);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_millis(backoff)) => {}
+            Ok(()) = peer_found.changed() => {
+                if backoff != BEACON_INTERVAL_MS {
+                    println!("[Discovery] Peer found, beaconing every {}ms", BEACON_INTERVAL_MS);
+                }
+                backoff = BEACON_INTERVAL_MS;
+                last_peer_at = Instant::now();
+            }
+            // The grab state goes out now; the interval is left as it is
+            _ = grab_changed.notified() => {}
+            _ = cancellation_token.cancelled() => break,
+        }
+    }
+    println!("[Discovery] Beacon stopped");
+}
+
+/// Broadcast a final `ttl_ms: 0` beacon so peers drop us immediately
+pub async fn send_leaving_beacon(state: &SharedDiscoveryState) {
+    let interface = state.read().await.preferred_ip;
+    let Some(sockets) = BeaconSockets::bind(interface).await else {
+        return;
+    };
+
+    let (packet, ports) = {
+        let s = state.read().await;
+        (BeaconPacket::from_state(&s, 0), beacon_ports(&s))
+    };
+    if let Ok(json) = serde_json::to_string(&packet) {
+        sockets.send(json.as_bytes(), &ports).await;
+        println!("[Discovery] Sent leaving beacon");
     }
 }
 
 /// Start the UDP listener (receives peer beacons)
-pub async fn start_listener<F>(state: SharedDiscoveryState, on_peer: F)
+///
+/// Listens on IPv4 and, when this machine has an IPv6 address, on IPv6 too.
+/// `on_peer` is called with the peer and what happened to it.
+pub async fn start_listener<F, P>(state: SharedDiscoveryState, on_peer: F, on_port_changed: P)
 where
-    F: Fn(Peer, bool) + Send + Sync + 'static,
+    F: Fn(Peer, PeerEvent) + Send + Sync + 'static,
+    P: FnOnce(u16) + Send + 'static,
 {
-    let socket = match UdpSocket::bind(format!("0.0.0.0:{}", DISCOVERY_PORT)).await {
-        Ok(s) => s,
+    let base_port = state.read().await.default_discovery_port;
+    let (_, has_v6) = local_ip_families();
+    let v4 = bind_discovery_socket(IpAddr::V4(Ipv4Addr::UNSPECIFIED), base_port).await;
+    let v6 = if has_v6 {
+        match &v4 {
+            // Beacons advertise a single port, so IPv6 listens on the IPv4 one
+            Some((_, port)) => bind_ipv6_listener(*port).map(|socket| (socket, *port)),
+            None => bind_discovery_socket(IpAddr::V6(Ipv6Addr::UNSPECIFIED), base_port).await,
+        }
+    } else {
+        None
+    };
+    let Some(port) = v4.as_ref().or(v6.as_ref()).map(|(_, port)| *port) else {
+        eprintln!("[Discovery] This may be due to firewall or another process using the port.");
+        return;
+    };
+    record_discovery_port(&state, port, on_port_changed).await;
+
+    let on_peer = Arc::new(on_peer);
+    // A dual-stack peer's beacon arrives on both sockets
+    let recent = Arc::new(RecentPacketCache::new(RECENT_PACKET_TTL_MS));
+    let listen_v4 = async {
+        if let Some((socket, port)) = v4 {
+            join_multicast_group(&socket, None);
+            println!("[Discovery] Listener started on port {}", port);
+            listen_on(socket, state.clone(), on_peer.clone(), Some(recent.clone())).await;
+        }
+    };
+    let listen_v6 = async {
+        if let Some((socket, port)) = v6 {
+            join_multicast_group_v6(&socket);
+            println!("[Discovery] IPv6 listener started on port {}", port);
+            listen_on(socket, state.clone(), on_peer.clone(), Some(recent.clone())).await;
+        }
+    };
+    tokio::join!(listen_v4, listen_v6);
+}
+
+/// Bind the IPv6 discovery socket on exactly `port`
+///
+/// The socket is IPv6-only so it can share the port with the IPv4 listener.
+fn bind_ipv6_listener(port: u16) -> Option<UdpSocket> {
+    match bind_udp(SocketAddr::from((Ipv6Addr::UNSPECIFIED, port))) {
+        Ok(socket) => Some(socket),
         Err(e) => {
-            eprintln!("[Discovery] Failed to bind listener on port {}: {}", DISCOVERY_PORT, e);
-            eprintln!("[Discovery] This may be due to firewall or another process using the port.");
-            return;
+            eprintln!("[Discovery] Failed to bind IPv6 listener on port {}: {}", port, e);
+            None
         }
+    }
+}
+
+/// Bind a UDP socket, making IPv6 sockets IPv6-only
+///
+/// Without that, a wildcard IPv6 socket also claims the IPv4 port on most
+/// systems and can't be bound next to the IPv4 listener.
+fn bind_udp(addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Join `MULTICAST_IP_V6` on every IPv6 interface so multicast beacons reach `socket`
+fn join_multicast_group_v6(socket: &UdpSocket) {
+    let mut joined = 0;
+    for scope in ipv6_scopes() {
+        match socket.join_multicast_v6(&MULTICAST_IP_V6, scope) {
+            Ok(()) => joined += 1,
+            Err(e) => eprintln!("[Discovery] Failed to join {} on interface {}: {}", MULTICAST_IP_V6, scope, e),
+        }
+    }
+    if joined > 0 {
+        println!("[Discovery] Joined {} on {} interface(s)", MULTICAST_IP_V6, joined);
+    }
+}
+
+/// Join `MULTICAST_IP` so multicast beacons reach `socket`
+///
+/// Joins on `interface` only, or on every non-loopback IPv4 interface when
+/// `None`. Interfaces that refuse are skipped; broadcast beacons still arrive
+/// on them.
+fn join_multicast_group(socket: &UdpSocket, interface: Option<Ipv4Addr>) {
+    let interfaces = match interface {
+        Some(ip) => vec![ip],
+        None => match local_ip_address::list_afinet_netifas() {
+            Ok(list) => list
+                .into_iter()
+                .filter_map(|(_, ip)| match ip {
+                    IpAddr::V4(ip) if !ip.is_loopback() => Some(ip),
+                    _ => None,
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("[Discovery] Failed to list interfaces for multicast: {}", e);
+                Vec::new()
+            }
+        },
+    };
+
+    let mut joined = 0;
+    for ip in &interfaces {
+        match socket.join_multicast_v4(MULTICAST_IP, *ip) {
+            Ok(()) => joined += 1,
+            Err(e) => eprintln!("[Discovery] Failed to join {} on {}: {}", MULTICAST_IP, ip, e),
+        }
+    }
+
+    // Let the OS pick an interface when none could be joined explicitly
+    if joined == 0 {
+        match socket.join_multicast_v4(MULTICAST_IP, Ipv4Addr::UNSPECIFIED) {
+            Ok(()) => println!("[Discovery] Joined {} on the default interface", MULTICAST_IP),
+            Err(e) => eprintln!("[Discovery] Multicast disabled, failed to join {}: {}", MULTICAST_IP, e),
+        }
+    } else {
+        println!("[Discovery] Joined {} on {} interface(s)", MULTICAST_IP, joined);
+    }
+}
+
+/// Bind the discovery port on `ip`, trying the next few ports until one is free
+async fn bind_discovery_socket(ip: IpAddr, base_port: u16) -> Option<(UdpSocket, u16)> {
+    for port in base_port..=base_port.saturating_add(DISCOVERY_PORT_FALLBACKS) {
+        match bind_udp(SocketAddr::new(ip, port)) {
+            Ok(socket) => return Some((socket, port)),
+            Err(e) => eprintln!("[Discovery] Failed to bind listener on {}:{}: {}", ip, port, e),
+        }
+    }
+    None
+}
+
+/// Store the bound port, reporting it when it is not the default
+async fn record_discovery_port<P: FnOnce(u16)>(state: &SharedDiscoveryState, port: u16, on_port_changed: P) {
+    let default_port = {
+        let mut state = state.write().await;
+        state.discovery_port = port;
+        state.default_discovery_port
     };
+    if port != default_port {
+        println!("[Discovery] Port {} in use, listening on {} instead", default_port, port);
+        on_port_changed(port);
+    }
+}
 
-    println!("[Discovery] Listener started on port {}", DISCOVERY_PORT);
+/// Start one listener per non-loopback IPv4 interface, bound to that interface's IP
+///
+/// Windows only delivers broadcasts to a wildcard socket from one interface, so
+/// multi-homed machines need a socket per interface. Other platforms already
+/// see every interface on `0.0.0.0` and fall back to `start_listener`.
+pub async fn start_multi_interface_listener<F, P>(
+    state: SharedDiscoveryState,
+    on_peer: F,
+    on_port_changed: P,
+) where
+    F: Fn(Peer, PeerEvent) + Send + Sync + 'static,
+    P: FnOnce(u16) + Send + 'static,
+{
+    if !cfg!(windows) {
+        return start_listener(state, on_peer, on_port_changed).await;
+    }
 
-    let mut buf = [0u8; 4096];
+    let interfaces = match local_ip_address::list_afinet_netifas() {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("[Discovery] Failed to list interfaces: {}", e);
+            return start_listener(state, on_peer, on_port_changed).await;
+        }
+    };
+
+    let base_port = state.read().await.default_discovery_port;
     let on_peer = Arc::new(on_peer);
+    // The same beacon arrives once per interface it was broadcast on
+    let recent = Arc::new(RecentPacketCache::new(RECENT_PACKET_TTL_MS));
+    let mut listeners = tokio::task::JoinSet::new();
+    let mut on_port_changed = Some(on_port_changed);
+
+    for (name, ip) in interfaces {
+        if !ip.is_ipv4() || ip.is_loopback() {
+            continue;
+        }
+
+        let Some((socket, port)) = bind_discovery_socket(ip, base_port).await else {
+            eprintln!("[Discovery] No free discovery port on {} ({})", name, ip);
+            continue;
+        };
+
+        // Beacons advertise a single port, so the first interface decides it
+        if let Some(callback) = on_port_changed.take() {
+            record_discovery_port(&state, port, callback).await;
+        }
+
+        if let IpAddr::V4(ipv4) = ip {
+            join_multicast_group(&socket, Some(ipv4));
+        }
+        println!("[Discovery] Listener started on {} ({}:{})", name, ip, port);
+        listeners.spawn(listen_on(socket, state.clone(), on_peer.clone(), Some(recent.clone())));
+    }
+
+    if listeners.is_empty() {
+        eprintln!("[Discovery] No interface listeners started, using 0.0.0.0");
+        let Some((socket, port)) = bind_discovery_socket(IpAddr::V4(Ipv4Addr::UNSPECIFIED), base_port).await else {
+            return;
+        };
+        if let Some(callback) = on_port_changed.take() {
+            record_discovery_port(&state, port, callback).await;
+        }
+        join_multicast_group(&socket, None);
+        return listen_on(socket, state, on_peer, None).await;
+    }
+
+    if local_ip_families().1 {
+        let port = state.read().await.discovery_port;
+        if let Some(socket) = bind_ipv6_listener(port) {
+            join_multicast_group_v6(&socket);
+            println!("[Discovery] IPv6 listener started on port {}", port);
+            listeners.spawn(listen_on(socket, state.clone(), on_peer.clone(), Some(recent.clone())));
+        }
+    }
+
+    while listeners.join_next().await.is_some() {}
+}
+
+/// Hashes of recently seen packets, used to drop duplicates received on several sockets
+pub struct RecentPacketCache {
+    ttl: std::time::Duration,
+    seen: std::sync::Mutex<HashMap<u64, std::time::Instant>>,
+}
+
+impl RecentPacketCache {
+    pub fn new(ttl_ms: u64) -> Self {
+        Self {
+            ttl: std::time::Duration::from_millis(ttl_ms),
+            seen: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a packet, returning false if it was already seen within the TTL
+    pub fn insert(&self, packet: &[u8]) -> bool {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        packet.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let now = std::time::Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, at| now.duration_since(*at) < self.ttl);
+        if seen.contains_key(&key) {
+            return false;
+        }
+        seen.insert(key, now);
+        true
+    }
+}
+
+/// Receive loop shared by all listener sockets
+///
+/// Also sweeps out peers that have stopped beaconing, in the same task.
+async fn listen_on<F>(
+    socket: UdpSocket,
+    state: SharedDiscoveryState,
+    on_peer: Arc<F>,
+    recent: Option<Arc<RecentPacketCache>>,
+) where
+    F: Fn(Peer, PeerEvent) + Send + Sync + 'static,
+{
+    let mut buf = vec![0u8; MAX_UDP_PAYLOAD];
+    let mut sweep = tokio::time::interval(tokio::time::Duration::from_millis(PEER_SWEEP_INTERVAL_MS));
+    let cancellation_token = state.read().await.cancellation_token.clone();
 
     loop {
-        match socket.recv_from(&mut buf).await {
-            Ok((len, _addr)) => {
-                if let Ok(json_str) = std::str::from_utf8(&buf[..len]) {
-                    if let Ok(packet) = serde_json::from_str::<BeaconPacket>(json_str) {
-                        // Ignore our own broadcasts
-                        let our_id = {
-                            let state = state.read().await;
-                            state.device_id.clone()
-                        };
-
-                        if packet.id == our_id {
+        tokio::select! {
+            result = socket.recv_from(&mut buf) => match result {
+                Ok((len, addr)) => {
+                    if let Some(recent) = &recent {
+                        if !recent.insert(&buf[..len]) {
                             continue;
                         }
-
-                        let peer = Peer {
-                            id: packet.id.clone(),
-                            ip: packet.ip.clone(),
-                            name: packet.name.clone(),
-                            is_holding: packet.is_holding,
-                            held_file: packet.held_file.clone(),
-                        };
-
-                        // Check if this is a new peer or grab update
-                        let (is_new, is_grab_update) = {
-                            let mut state = state.write().await;
-                            let existing = state.peers.get(&peer.id);
-                            let is_new = existing.is_none();
-                            let is_grab_update = existing
-                                .map(|p| p.is_holding != peer.is_holding || p.held_file != peer.held_file)
-                                .unwrap_or(false);
-                            
-                            state.peers.insert(peer.id.clone(), peer.clone());
-                            (is_new, is_grab_update)
-                        };
-
-                        if is_new {
-                            println!("[Discovery] New peer: {} at {}", peer.name, peer.ip);
-                            on_peer(peer, false);
-                        } else if is_grab_update {
-                            println!("[Discovery] Grab update from {}: holding={}", peer.name, peer.is_holding);
-                            on_peer(peer, true);
-                        }
                     }
+                    handle_packet(&socket, &state, &*on_peer, &buf[..len], addr).await;
+                }
+                Err(e) => {
+                    eprintln!("[Discovery] Receive error: {}", e);
                 }
+            },
+            _ = sweep.tick() => sweep_expired_peers(&state, &*on_peer).await,
+            _ = cancellation_token.cancelled() => break,
+        }
+    }
+    println!("[Discovery] Listener stopped");
+}
+
+/// Handle one datagram: answer control packets, record beacons
+async fn handle_packet<F>(
+    socket: &UdpSocket,
+    state: &SharedDiscoveryState,
+    on_peer: &F,
+    data: &[u8],
+    addr: SocketAddr,
+) where
+    F: Fn(Peer, PeerEvent),
+{
+    if data.len() > MAX_BEACON_BYTES {
+        eprintln!("[Discovery] Dropped {}-byte packet from {}", data.len(), addr);
+        return;
+    }
+    if data.len() > LARGE_BEACON_BYTES {
+        tracing::debug!(size = data.len(), %addr, "Large discovery packet");
+    }
+
+    let Ok(json_str) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(control) = serde_json::from_str::<ControlPacket>(json_str) {
+        handle_control_packet(socket, control, addr).await;
+        return;
+    }
+
+    let Ok(packet) = serde_json::from_str::<BeaconPacketV1>(json_str) else {
+        return;
+    };
+    let packet = upgrade_from_v1(packet);
+    if packet.id.is_empty() {
+        return;
+    }
+    if let Err(e) = packet.validate() {
+        eprintln!("[Discovery] Ignored beacon from {}: {}", addr, e);
+        return;
+    }
+    // Newer beacons are read as far as our schema goes; fields we don't know are dropped
+    if packet.version > BEACON_VERSION
+        && state.write().await.newer_version_peers.insert(packet.id.clone())
+    {
+        eprintln!(
+            "[Discovery] {} sends beacon version {} (we know {}), reading known fields only",
+            addr, packet.version, BEACON_VERSION
+        );
+    }
+
+    // Ignore our own broadcasts, and peers that don't share our PIN
+    let (our_id, accepted) = {
+        let state = state.read().await;
+        (state.device_id.clone(), state.accepts_beacon(&packet))
+    };
+
+    if packet.id == our_id {
+        return;
+    }
+    // Dropped silently: a mismatched peer keeps beaconing every second
+    if !accepted {
+        return;
+    }
+
+    let peer = Peer {
+        id: packet.id.clone(),
+        ip: packet.ip.clone(),
+        name: packet.name.clone(),
+        is_holding: packet.is_holding,
+        held_file: packet.held_file.clone(),
+        held_file_url: held_file_url(&packet.ip, packet.server_port, packet.is_holding, &packet.held_file),
+        inline_data: packet
+            .inline_data
+            .clone()
+            .filter(|data| data.len() <= INLINE_DATA_MAX_LEN),
+        supports_zstd: packet.supports_zstd,
+        server_port: packet.server_port,
+        held_text: packet
+            .held_text
+            .clone()
+            .filter(|text| text.len() <= MAX_HELD_TEXT_BYTES),
+    };
+
+    if packet.ttl_ms == 0 {
+        let removed = {
+            let mut state = state.write().await;
+            state.peer_last_seen.remove(&peer.id);
+            state.peers.remove(&peer.id)
+        };
+        if removed.is_some() {
+            println!("[Discovery] Peer left: {} at {}", peer.name, peer.ip);
+            on_peer(peer, PeerEvent::Left);
+        }
+        return;
+    }
+
+    record_peer(state, peer, on_peer, true).await;
+}
+
+/// Store a peer reported by any discovery backend and report what changed
+///
+/// Peers recorded with `expires` are dropped by `sweep_expired_peers` once
+/// they go quiet; the others stay until removed explicitly.
+pub async fn record_peer<F>(state: &SharedDiscoveryState, peer: Peer, on_peer: &F, expires: bool)
+where
+    F: Fn(Peer, PeerEvent) + ?Sized,
+{
+    // Check if this is a new peer or grab update
+    let (is_new, is_grab_update) = {
+        let mut state = state.write().await;
+        let existing = state.peers.get(&peer.id);
+        let is_new = existing.is_none();
+        let is_grab_update = existing
+            .map(|p| {
+                p.is_holding != peer.is_holding
+                    || p.held_file != peer.held_file
+                    || p.held_text != peer.held_text
+            })
+            .unwrap_or(false);
+
+        state.peers.insert(peer.id.clone(), peer.clone());
+        if expires {
+            state.peer_last_seen.insert(peer.id.clone(), Instant::now());
+        }
+        if is_new && expires {
+            state.peer_found.send_replace(());
+        }
+        (is_new, is_grab_update)
+    };
+
+    if is_new {
+        println!("[Discovery] New peer: {} at {}", peer.name, peer.ip);
+        on_peer(peer, PeerEvent::Discovered);
+    } else if is_grab_update {
+        println!("[Discovery] Grab update from {}: holding={}", peer.name, peer.is_holding);
+        on_peer(peer, PeerEvent::GrabUpdate);
+    }
+}
+
+/// Remove peers whose beacons have not been heard within `PEER_EXPIRY_MS`
+pub async fn sweep_expired_peers<F>(state: &SharedDiscoveryState, on_peer: &F)
+where
+    F: Fn(Peer, PeerEvent) + ?Sized,
+{
+    let expiry = tokio::time::Duration::from_millis(PEER_EXPIRY_MS);
+    let lost: Vec<Peer> = {
+        let mut state = state.write().await;
+        let expired: Vec<String> = state
+            .peer_last_seen
+            .iter()
+            .filter(|(_, seen)| seen.elapsed() > expiry)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        expired
+            .iter()
+            .filter_map(|id| {
+                state.peer_last_seen.remove(id);
+                state.peers.remove(id)
+            })
+            .collect()
+    };
+
+    for peer in lost {
+        println!("[Discovery] Peer lost: {} at {}", peer.name, peer.ip);
+        on_peer(peer, PeerEvent::Lost);
+    }
+}
+
+/// Answer pings received on the discovery port
+async fn handle_control_packet(socket: &UdpSocket, packet: ControlPacket, addr: SocketAddr) {
+    match packet {
+        ControlPacket::Ping { nonce, .. } => {
+            if let Ok(json) = serde_json::to_string(&ControlPacket::Pong { nonce }) {
+                let _ = socket.send_to(json.as_bytes(), addr).await;
             }
-            Err(e) => {
-                eprintln!("[Discovery] Receive error: {}", e);
+        }
+        // Pongs go to the pinging socket, never to the listener
+        ControlPacket::Pong { .. } => {}
+    }
+}
+
+/// Ping a peer's discovery `port` and return the round-trip time in microseconds
+pub async fn ping_peer(our_id: &str, peer_ip: &str, port: u16) -> Result<u64, String> {
+    let ip: IpAddr = peer_ip
+        .parse()
+        .map_err(|_| format!("Invalid peer IP: {}", peer_ip))?;
+    let target = SocketAddr::new(ip, port);
+
+    let unspecified = match ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0))
+        .await
+        .map_err(|e| format!("Failed to bind ping socket: {}", e))?;
+
+    let nonce = uuid::Uuid::new_v4().as_u128() as u64;
+    let ping = ControlPacket::Ping {
+        id: our_id.to_string(),
+        nonce,
+    };
+    let json = serde_json::to_string(&ping).map_err(|e| e.to_string())?;
+
+    let started = tokio::time::Instant::now();
+    socket
+        .send_to(json.as_bytes(), target)
+        .await
+        .map_err(|e| format!("Failed to send ping: {}", e))?;
+
+    let wait_for_pong = async {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, _) = socket.recv_from(&mut buf).await?;
+            if let Ok(ControlPacket::Pong { nonce: reply }) =
+                serde_json::from_slice::<ControlPacket>(&buf[..len])
+            {
+                if reply == nonce {
+                    return Ok::<_, std::io::Error>(started.elapsed());
+                }
             }
         }
+    };
+
+    match tokio::time::timeout(
+        tokio::time::Duration::from_millis(PING_TIMEOUT_MS),
+        wait_for_pong,
+    )
+    .await
+    {
+        Ok(Ok(rtt)) => Ok(rtt.as_micros() as u64),
+        Ok(Err(e)) => Err(format!("Ping failed: {}", e)),
+        Err(_) => Err(format!("Ping to {} timed out", peer_ip)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn multicast_beacon_round_trips_on_loopback() {
+        let loopback = Ipv4Addr::LOCALHOST;
+        let (receiver, port) = bind_discovery_socket(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 29988)
+            .await
+            .expect("no free receive port");
+        receiver.join_multicast_v4(MULTICAST_IP, loopback).unwrap();
+
+        let (sender, _) = bind_discovery_socket(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port + 1)
+            .await
+            .expect("no free send port");
+        socket2::SockRef::from(&sender).set_multicast_if_v4(&loopback).unwrap();
+        sender.set_multicast_loop_v4(true).unwrap();
+
+        let beacon = serde_json::to_vec(&BeaconPacket {
+            id: uuid::Uuid::new_v4().to_string(),
+            ip: loopback.to_string(),
+            ..BeaconPacket::default()
+        })
+        .unwrap();
+        sender.send_to(&beacon, (MULTICAST_IP, port)).await.unwrap();
+
+        let mut buf = vec![0u8; MAX_UDP_PAYLOAD];
+        let (len, _) = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv_from(&mut buf))
+            .await
+            .expect("multicast beacon not received")
+            .unwrap();
+        assert_eq!(&buf[..len], &beacon[..]);
+    }
+
+    #[tokio::test]
+    async fn max_length_beacon_round_trips() {
+        // Longest file name most file systems allow
+        const MAX_FILENAME_BYTES: usize = 255;
+        // Control characters are the worst case: JSON escapes each as `\u00XX`
+        let escaped = |len: usize| "\u{1}".repeat(len);
+        let packet = BeaconPacket {
+            version: u8::MAX,
+            id: uuid::Uuid::new_v4().to_string(),
+            ip: "ffff:ffff:ffff:ffff:ffff:ffff:255.255.255.255".to_string(),
+            name: escaped(MAX_PEER_NAME_BYTES),
+            is_holding: true,
+            held_file: escaped(MAX_FILENAME_BYTES),
+            ttl_ms: u64::MAX,
+            inline_data: Some("A".repeat(INLINE_DATA_MAX_LEN)),
+            supports_zstd: true,
+            server_port: u16::MAX,
+            auth_token: Some("f".repeat(64)),
+            held_text: Some(escaped(MAX_HELD_TEXT_BYTES)),
+        };
+        let serialized = serde_json::to_vec(&packet).unwrap();
+        assert!(
+            serialized.len() <= MAX_BEACON_BYTES,
+            "{}-byte beacon exceeds MAX_BEACON_BYTES",
+            serialized.len()
+        );
+
+        let state: SharedDiscoveryState = Arc::new(RwLock::new(DiscoveryState::with_identity(
+            uuid::Uuid::new_v4().to_string(),
+            "Test".to_string(),
+            DEFAULT_DISCOVERY_PORT,
+            DEFAULT_SERVER_PORT,
+        )));
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let received = std::sync::Mutex::new(None);
+        let on_peer = |peer: Peer, _: PeerEvent| *received.lock().unwrap() = Some(peer);
+        let from = SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_DISCOVERY_PORT));
+        handle_packet(&socket, &state, &on_peer, &serialized, from).await;
+
+        let peer = received.into_inner().unwrap().expect("beacon was not recorded");
+        assert_eq!(peer.id, packet.id);
+        assert_eq!(peer.ip, packet.ip);
+        assert_eq!(peer.name, packet.name);
+        assert_eq!(peer.held_file, packet.held_file);
+        assert_eq!(peer.inline_data, packet.inline_data);
+        assert_eq!(peer.held_text, packet.held_text);
+        assert_eq!(peer.server_port, packet.server_port);
+        assert!(peer.is_holding && peer.supports_zstd);
+    }
+
+    #[test]
+    fn beacon_with_unknown_fields_parses() {
+        let json = r#"{
+            "version": 2,
+            "id": "3f1c2a9e-5b7d-4e8f-9a0b-1c2d3e4f5a6b",
+            "ip": "192.168.1.20",
+            "name": "Future Phone",
+            "isHolding": true,
+            "heldFile": "photo.jpg",
+            "serverPort": 8081,
+            "futureField": { "nested": [1, 2, 3] },
+            "anotherNewFlag": true
+        }"#;
+        let packet = upgrade_from_v1(serde_json::from_str::<BeaconPacketV1>(json).unwrap());
+        assert_eq!(packet.version, 2);
+        assert_eq!(packet.id, "3f1c2a9e-5b7d-4e8f-9a0b-1c2d3e4f5a6b");
+        assert_eq!(packet.name, "Future Phone");
+        assert!(packet.is_holding);
+        assert_eq!(packet.held_file, "photo.jpg");
+        assert_eq!(packet.server_port, 8081);
+        // Fields the sender left out fall back to their defaults
+        assert_eq!(packet.ttl_ms, DEFAULT_BEACON_TTL_MS);
+        assert!(packet.held_text.is_none());
+        assert!(packet.validate().is_ok());
     }
 }
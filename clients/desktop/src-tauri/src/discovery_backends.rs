@@ -0,0 +1,496 @@
+// Pluggable discovery backends
+//
+// Each backend advertises this device and reports peers through the shared
+// `on_peer` callback. `DiscoveryRegistry` runs the enabled backends and can
+// switch them on and off at runtime:
+// - "udp":  JSON beacons over broadcast/multicast (the original discovery)
+// - "mdns": DNS-SD style announcements of `_airshare._tcp.local`
+// - "tcp":  hotspot QR page plus HTTP polling of the hotspot subnet
+
+use crate::discovery::{self, Peer, PeerEvent, SharedDiscoveryState};
+use crate::hotspot;
+use crate::server::DEFAULT_SERVER_PORT;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tokio::net::UdpSocket;
+
+/// Callback shared by every backend
+pub type PeerCallback = Arc<dyn Fn(Peer, PeerEvent) + Send + Sync>;
+
+/// A way of finding peers and being found by them
+#[async_trait]
+pub trait DiscoveryBackend: Send + Sync {
+    /// Short name used by `register_discovery_backend` and the config file
+    fn name(&self) -> &'static str;
+    /// Make this device visible to peers; runs until aborted
+    async fn start_advertising(&self, state: SharedDiscoveryState);
+    /// Look for peers and report them through `on_peer`; runs until aborted
+    async fn start_discovering(&self, state: SharedDiscoveryState, on_peer: PeerCallback);
+}
+
+/// UDP broadcast/multicast beacons on the discovery port
+pub struct UdpBroadcastBackend {
+    on_port_changed: Arc<dyn Fn(u16) + Send + Sync>,
+}
+
+impl UdpBroadcastBackend {
+    pub fn new(on_port_changed: Arc<dyn Fn(u16) + Send + Sync>) -> Self {
+        Self { on_port_changed }
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for UdpBroadcastBackend {
+    fn name(&self) -> &'static str {
+        "udp"
+    }
+
+    async fn start_advertising(&self, state: SharedDiscoveryState) {
+        discovery::start_beacon(state).await;
+    }
+
+    async fn start_discovering(&self, state: SharedDiscoveryState, on_peer: PeerCallback) {
+        let on_port_changed = self.on_port_changed.clone();
+        discovery::start_multi_interface_listener(
+            state,
+            move |peer, event| on_peer(peer, event),
+            move |port| on_port_changed(port),
+        )
+        .await;
+    }
+}
+
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+const MDNS_SERVICE: &str = "_airshare._tcp.local";
+/// Announcements must come faster than the peer expiry window
+const MDNS_ANNOUNCE_INTERVAL_MS: u64 = 2000;
+/// TTL of announced records, in seconds
+const MDNS_RECORD_TTL: u32 = 120;
+
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_PTR: u16 = 12;
+const DNS_TYPE_TXT: u16 = 16;
+const DNS_TYPE_SRV: u16 = 33;
+const DNS_CLASS_IN: u16 = 1;
+/// Class IN with the mDNS cache-flush bit set
+const DNS_CLASS_IN_FLUSH: u16 = 0x8001;
+
+/// Multicast DNS announcements of the `_airshare._tcp` service
+///
+/// Each device periodically multicasts unsolicited PTR/SRV/TXT/A records;
+/// the TXT record carries the same fields as a UDP beacon. Queries are not
+/// answered, so only AirShare devices running this backend see each other.
+pub struct MdnsBackend;
+
+#[async_trait]
+impl DiscoveryBackend for MdnsBackend {
+    fn name(&self) -> &'static str {
+        "mdns"
+    }
+
+    async fn start_advertising(&self, state: SharedDiscoveryState) {
+        let socket = match UdpSocket::bind("0.0.0.0:0").await {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("[mDNS] Failed to bind announce socket: {}", e);
+                return;
+            }
+        };
+
+        println!("[mDNS] Announcing {} every {}ms", MDNS_SERVICE, MDNS_ANNOUNCE_INTERVAL_MS);
+        let target = SocketAddrV4::new(MDNS_ADDR, MDNS_PORT);
+        loop {
+            let packet = {
+                let state = state.read().await;
+                build_announcement(&state)
+            };
+            if let Err(e) = socket.send_to(&packet, target).await {
+                eprintln!("[mDNS] Failed to send announcement: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(MDNS_ANNOUNCE_INTERVAL_MS)).await;
+        }
+    }
+
+    async fn start_discovering(&self, state: SharedDiscoveryState, on_peer: PeerCallback) {
+        let socket = match bind_mdns_socket() {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("[mDNS] {}", e);
+                return;
+            }
+        };
+
+        println!("[mDNS] Listening on {}:{}", MDNS_ADDR, MDNS_PORT);
+        let mut buf = [0u8; 9000];
+        let mut sweep = tokio::time::interval(tokio::time::Duration::from_millis(
+            discovery::PEER_SWEEP_INTERVAL_MS,
+        ));
+
+        loop {
+            tokio::select! {
+                result = socket.recv_from(&mut buf) => match result {
+                    Ok((len, _)) => {
+                        let our_id = state.read().await.device_id.clone();
+                        for peer in parse_announcement(&buf[..len]) {
+                            if peer.id != our_id {
+                                discovery::record_peer(&state, peer, &*on_peer, true).await;
+                            }
+                        }
+                    }
+                    Err(e) => eprintln!("[mDNS] Receive error: {}", e),
+                },
+                _ = sweep.tick() => discovery::sweep_expired_peers(&state, &*on_peer).await,
+            }
+        }
+    }
+}
+
+/// Bind 5353 with address reuse so we can share it with the system responder
+fn bind_mdns_socket() -> Result<UdpSocket, String> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to create socket: {}", e))?;
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Failed to set SO_REUSEADDR: {}", e))?;
+    #[cfg(unix)]
+    socket
+        .set_reuse_port(true)
+        .map_err(|e| format!("Failed to set SO_REUSEPORT: {}", e))?;
+    socket
+        .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())
+        .map_err(|e| format!("Failed to bind port {}: {}", MDNS_PORT, e))?;
+    socket
+        .join_multicast_v4(&MDNS_ADDR, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| format!("Failed to join {}: {}", MDNS_ADDR, e))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to set non-blocking: {}", e))?;
+
+    UdpSocket::from_std(socket.into()).map_err(|e| format!("Failed to register socket: {}", e))
+}
+
+/// Unsolicited mDNS response advertising this device
+fn build_announcement(state: &discovery::DiscoveryState) -> Vec<u8> {
+    let instance = format!("{}.{}", state.device_id, MDNS_SERVICE);
+    let host = format!("{}.local", state.device_id);
+
+    let mut packet = Vec::with_capacity(512);
+    // ID 0, flags: response + authoritative, 0 questions, 4 answers
+    for field in [0u16, 0x8400, 0, 4, 0, 0] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+
+    let mut ptr = Vec::new();
+    write_name(&mut ptr, &instance);
+    write_record(&mut packet, MDNS_SERVICE, DNS_TYPE_PTR, DNS_CLASS_IN, &ptr);
+
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&0u16.to_be_bytes()); // priority
+    srv.extend_from_slice(&0u16.to_be_bytes()); // weight
+    srv.extend_from_slice(&state.server_port.to_be_bytes());
+    write_name(&mut srv, &host);
+    write_record(&mut packet, &instance, DNS_TYPE_SRV, DNS_CLASS_IN_FLUSH, &srv);
+
+    let mut txt = Vec::new();
+    for entry in [
+        format!("id={}", state.device_id),
+        format!("name={}", state.device_name),
+        format!("ip={}", state.local_ip),
+        format!("holding={}", state.is_holding),
+        format!("file={}", state.held_file),
+        format!("port={}", state.server_port),
+        "zstd=true".to_string(),
+    ] {
+        // TXT strings are length-prefixed and at most 255 bytes
+        let bytes = &entry.as_bytes()[..entry.len().min(255)];
+        txt.push(bytes.len() as u8);
+        txt.extend_from_slice(bytes);
+    }
+    write_record(&mut packet, &instance, DNS_TYPE_TXT, DNS_CLASS_IN_FLUSH, &txt);
+
+    let ip: Ipv4Addr = state.local_ip.parse().unwrap_or(Ipv4Addr::LOCALHOST);
+    write_record(&mut packet, &host, DNS_TYPE_A, DNS_CLASS_IN_FLUSH, &ip.octets());
+
+    packet
+}
+
+/// Append a dotted name as DNS labels (no compression)
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        let bytes = &label.as_bytes()[..label.len().min(63)];
+        out.push(bytes.len() as u8);
+        out.extend_from_slice(bytes);
+    }
+    out.push(0);
+}
+
+fn write_record(out: &mut Vec<u8>, name: &str, rtype: u16, class: u16, rdata: &[u8]) {
+    write_name(out, name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&MDNS_RECORD_TTL.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+}
+
+/// Read a possibly compressed name at `pos`, returning it and the offset after it
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bound pointer chasing so a malicious loop cannot spin forever
+    for _ in 0..128 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let target = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = target;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+    None
+}
+
+/// Peers described by AirShare TXT records in an mDNS packet
+fn parse_announcement(packet: &[u8]) -> Vec<Peer> {
+    let field = |i: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*packet.get(i)?, *packet.get(i + 1)?]))
+    };
+    let (Some(questions), Some(answers), Some(authority), Some(additional)) =
+        (field(4), field(6), field(8), field(10))
+    else {
+        return Vec::new();
+    };
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        let Some((_, next)) = read_name(packet, pos) else {
+            return Vec::new();
+        };
+        pos = next + 4;
+    }
+
+    let mut peers = Vec::new();
+    for _ in 0..(answers as usize + authority as usize + additional as usize) {
+        let Some((name, next)) = read_name(packet, pos) else {
+            break;
+        };
+        let (Some(rtype), Some(rdlen)) = (field(next), field(next + 8)) else {
+            break;
+        };
+        let start = next + 10;
+        let Some(rdata) = packet.get(start..start + rdlen as usize) else {
+            break;
+        };
+        pos = start + rdlen as usize;
+
+        if rtype == DNS_TYPE_TXT && name.ends_with(&format!(".{}", MDNS_SERVICE)) {
+            if let Some(peer) = peer_from_txt(rdata) {
+                peers.push(peer);
+            }
+        }
+    }
+    peers
+}
+
+fn peer_from_txt(rdata: &[u8]) -> Option<Peer> {
+    let mut fields = HashMap::new();
+    let mut pos = 0;
+    while let Some(&len) = rdata.get(pos) {
+        let entry = rdata.get(pos + 1..pos + 1 + len as usize)?;
+        let entry = String::from_utf8_lossy(entry);
+        if let Some((key, value)) = entry.split_once('=') {
+            fields.insert(key.to_string(), value.to_string());
+        }
+        pos += 1 + len as usize;
+    }
+
+    let id = fields.remove("id").filter(|id| !id.is_empty())?;
+    let ip = fields.remove("ip").unwrap_or_default();
+    let is_holding = fields.get("holding").map(|v| v == "true").unwrap_or(false);
+    let held_file = fields.remove("file").unwrap_or_default();
+    let server_port = fields
+        .get("port")
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_SERVER_PORT);
+    Some(Peer {
+        id,
+        name: fields.remove("name").unwrap_or_else(|| format!("Device at {}", ip)),
+        held_file_url: discovery::held_file_url(&ip, server_port, is_holding, &held_file),
+        ip,
+        is_holding,
+        held_file,
+        inline_data: None,
+        supports_zstd: fields.get("zstd").map(|v| v == "true").unwrap_or(false),
+        server_port,
+        // TXT strings are too short to carry held text
+        held_text: None,
+    })
+}
+
+/// How often the hotspot subnet is polled
+const TCP_SCAN_INTERVAL_MS: u64 = 10_000;
+
+/// Hotspot fallback for networks that drop UDP broadcast
+///
+/// Advertises through the QR page and finds peers by probing the hotspot
+/// subnet over HTTP. Probing only runs while we are on that subnet.
+pub struct TcpFallbackBackend;
+
+#[async_trait]
+impl DiscoveryBackend for TcpFallbackBackend {
+    fn name(&self) -> &'static str {
+        "tcp"
+    }
+
+    async fn start_advertising(&self, state: SharedDiscoveryState) {
+        if let Err(e) = hotspot::start_qr_broadcast_server(state).await {
+            eprintln!("[Hotspot] {}", e);
+        }
+    }
+
+    async fn start_discovering(&self, state: SharedDiscoveryState, on_peer: PeerCallback) {
+        loop {
+            let (our_ip, server_port) = {
+                let state = state.read().await;
+                (state.local_ip.clone(), state.server_port)
+            };
+            if hotspot::is_on_hotspot_subnet(&our_ip) {
+                // Hotspot peers don't beacon, so assume they use our server port
+                for found in hotspot::scan_hotspot_subnet(&our_ip, server_port).await {
+                    let peer = Peer {
+                        id: format!("hotspot-{}", found.ip),
+                        name: format!("Device at {}", found.ip),
+                        ip: found.ip,
+                        is_holding: false,
+                        held_file: String::new(),
+                        held_file_url: None,
+                        inline_data: None,
+                        supports_zstd: false,
+                        server_port,
+                        held_text: None,
+                    };
+                    // Polling is slower than the expiry sweep, so keep these like manual peers
+                    discovery::record_peer(&state, peer, &*on_peer, false).await;
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(TCP_SCAN_INTERVAL_MS)).await;
+        }
+    }
+}
+
+struct RegisteredBackend {
+    backend: Arc<dyn DiscoveryBackend>,
+    /// Advertising and discovering tasks while enabled
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// The available discovery backends and which of them are running
+pub struct DiscoveryRegistry {
+    state: SharedDiscoveryState,
+    on_peer: PeerCallback,
+    backends: Mutex<Vec<RegisteredBackend>>,
+}
+
+pub type SharedDiscoveryRegistry = Arc<DiscoveryRegistry>;
+
+impl DiscoveryRegistry {
+    pub fn new(state: SharedDiscoveryState, on_peer: PeerCallback) -> Self {
+        Self {
+            state,
+            on_peer,
+            backends: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add a backend, starting it right away if `enabled`
+    pub fn register(&self, backend: Arc<dyn DiscoveryBackend>, enabled: bool) {
+        let tasks = if enabled { self.spawn(&backend) } else { Vec::new() };
+        self.backends
+            .lock()
+            .unwrap()
+            .push(RegisteredBackend { backend, tasks });
+    }
+
+    /// Start or stop a backend by name, returning whether it is now running
+    pub fn toggle(&self, name: &str) -> Result<bool, String> {
+        let mut backends = self.backends.lock().unwrap();
+        let entry = backends
+            .iter_mut()
+            .find(|b| b.backend.name() == name)
+            .ok_or_else(|| format!("Unknown discovery backend: {}", name))?;
+
+        if entry.tasks.is_empty() {
+            entry.tasks = self.spawn(&entry.backend);
+            println!("[Discovery] Started backend {}", name);
+            Ok(true)
+        } else {
+            for task in entry.tasks.drain(..) {
+                task.abort();
+            }
+            println!("[Discovery] Stopped backend {}", name);
+            Ok(false)
+        }
+    }
+
+    /// Stop every running backend; `toggle` can start them again
+    pub fn stop_all(&self) {
+        for entry in self.backends.lock().unwrap().iter_mut() {
+            for task in entry.tasks.drain(..) {
+                task.abort();
+            }
+        }
+    }
+
+    /// Names of the backends currently running
+    pub fn enabled(&self) -> Vec<String> {
+        self.backends
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|b| !b.tasks.is_empty())
+            .map(|b| b.backend.name().to_string())
+            .collect()
+    }
+
+    fn spawn(&self, backend: &Arc<dyn DiscoveryBackend>) -> Vec<JoinHandle<()>> {
+        let advertiser = backend.clone();
+        let state = self.state.clone();
+        let advertising = tauri::async_runtime::spawn(async move { advertiser.start_advertising(state).await });
+
+        let discoverer = backend.clone();
+        let state = self.state.clone();
+        let on_peer = self.on_peer.clone();
+        let discovering = tauri::async_runtime::spawn(async move {
+            discoverer.start_discovering(state, on_peer).await
+        });
+
+        vec![advertising, discovering]
+    }
+}
+
+/// Tauri command to switch a discovery backend ("udp", "mdns", "tcp") on or off
+///
+/// Returns whether the backend is running afterwards; the choice is saved
+/// to the config file.
+#[tauri::command]
+pub fn register_discovery_backend(
+    registry: tauri::State<'_, SharedDiscoveryRegistry>,
+    name: String,
+) -> Result<bool, String> {
+    let running = registry.toggle(&name)?;
+    let enabled = registry.enabled();
+    crate::config::AirShareConfig::update(|config| config.discovery_backends = enabled)?;
+    Ok(running)
+}
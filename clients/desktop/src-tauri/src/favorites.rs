@@ -0,0 +1,109 @@
+// Favorite peers (favorites.json in the local data dir)
+//
+// Devices discovery can't find on its own, such as a phone running a
+// hotspot, are saved here by IP and manually connected at every launch.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const FAVORITES_FILE: &str = "favorites.json";
+
+/// A peer connected by IP at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FavoritePeer {
+    pub name: String,
+    pub ip: String,
+}
+
+fn favorites_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("airshare").join(FAVORITES_FILE))
+}
+
+/// Load the saved favorites, starting empty if the file is missing or invalid
+pub fn load() -> Vec<FavoritePeer> {
+    let Some(path) = favorites_path() else {
+        return Vec::new();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("[Favorites] Invalid favorites file {:?}: {}", path, e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Write the favorites, creating the data directory if needed
+fn save(favorites: &[FavoritePeer]) -> Result<(), String> {
+    let path = favorites_path().ok_or("Could not find data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(favorites)
+        .map_err(|e| format!("Failed to serialize favorites: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write favorites: {}", e))
+}
+
+/// Canonical form of `ip`, so the same address can't be saved twice
+pub fn normalize_ip(ip: &str) -> Result<String, String> {
+    ip.trim()
+        .parse::<std::net::IpAddr>()
+        .map(|ip| ip.to_string())
+        .map_err(|_| "Invalid IP address".to_string())
+}
+
+/// Tauri command to save a peer to connect to at every launch
+///
+/// Saving an IP that is already a favorite renames it.
+#[tauri::command]
+pub fn add_favorite(name: String, ip: String) -> Result<(), String> {
+    let ip = normalize_ip(&ip)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Favorite name cannot be empty".to_string());
+    }
+    if name.len() > crate::config::MAX_DEVICE_NAME_BYTES {
+        return Err(format!(
+            "Favorite name is longer than {} bytes",
+            crate::config::MAX_DEVICE_NAME_BYTES
+        ));
+    }
+
+    let mut favorites = load();
+    match favorites.iter_mut().find(|favorite| favorite.ip == ip) {
+        Some(favorite) => favorite.name = name.to_string(),
+        None => favorites.push(FavoritePeer {
+            name: name.to_string(),
+            ip: ip.clone(),
+        }),
+    }
+    save(&favorites)?;
+
+    println!("[Favorites] Saved {} at {}", name, ip);
+    Ok(())
+}
+
+/// Tauri command to stop connecting to a favorite at launch
+#[tauri::command]
+pub fn remove_favorite(ip: String) -> Result<(), String> {
+    let ip = normalize_ip(&ip)?;
+    let mut favorites = load();
+    let count = favorites.len();
+    favorites.retain(|favorite| favorite.ip != ip);
+    if favorites.len() == count {
+        return Err(format!("No favorite at {}", ip));
+    }
+    save(&favorites)?;
+
+    println!("[Favorites] Removed {}", ip);
+    Ok(())
+}
+
+/// Tauri command to list the saved favorites
+#[tauri::command]
+pub fn get_favorites() -> Result<Vec<FavoritePeer>, String> {
+    Ok(load())
+}
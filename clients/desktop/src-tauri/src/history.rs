@@ -0,0 +1,247 @@
+// Transfer history (transfers.json in the local data dir)
+//
+// A JSON array of records. Downloads, uploads and received files are all
+// logged, whether they completed or failed. Tags let users group files
+// across sessions.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "transfers.json";
+/// One-record-per-line history kept in the config dir by earlier versions
+const LEGACY_HISTORY_FILE: &str = "transfer_history.jsonl";
+
+/// Serialises access so concurrent rewrites never lose a record
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Which way a file went
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Sent,
+    /// Also assumed for records written before directions were logged
+    #[default]
+    Received,
+}
+
+/// How a transfer ended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferStatus {
+    /// Also assumed for records written before failures were logged
+    #[default]
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TransferStatus {
+    /// Status of a transfer that ended with `result`
+    pub fn of<T>(result: &Result<T, String>) -> Self {
+        match result {
+            Ok(_) => Self::Completed,
+            Err(e) if e == crate::server::DOWNLOAD_CANCELLED => Self::Cancelled,
+            Err(_) => Self::Failed,
+        }
+    }
+}
+
+/// A finished transfer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferRecord {
+    pub id: String,
+    pub filename: String,
+    pub size_bytes: u64,
+    /// Name of the other device, when known
+    pub peer_name: Option<String>,
+    /// Device ID of the other device, when known
+    pub peer_id: Option<String>,
+    pub direction: Direction,
+    /// Unix timestamp (ms) when the transfer started
+    pub started_at: u64,
+    /// Unix timestamp (ms) when the transfer finished
+    pub finished_at: u64,
+    pub status: TransferStatus,
+    pub tags: Vec<String>,
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("airshare").join(HISTORY_FILE))
+}
+
+/// Path of the history file, moving the legacy file there first if needed
+///
+/// Callers hold `HISTORY_LOCK`.
+fn history_file() -> Result<PathBuf, String> {
+    let path = history_path().ok_or("Could not find data directory")?;
+    let Some(legacy) = crate::config::config_dir().map(|dir| dir.join(LEGACY_HISTORY_FILE)) else {
+        return Ok(path);
+    };
+    if path.exists() || !legacy.exists() {
+        return Ok(path);
+    }
+
+    let Ok(contents) = std::fs::read_to_string(&legacy) else {
+        return Ok(path);
+    };
+    let records: Vec<TransferRecord> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    write_records(&path, &records)?;
+    if let Err(e) = std::fs::remove_file(&legacy) {
+        eprintln!("[History] Failed to remove {:?}: {}", legacy, e);
+    }
+    println!("[History] Moved {} record(s) from {:?} to {:?}", records.len(), legacy, path);
+    Ok(path)
+}
+
+/// Current Unix time in ms, for `started_at`
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Append a record for a transfer that just finished and return it
+///
+/// `id` and `finished_at` are filled in here.
+pub fn record(record: TransferRecord) -> Result<TransferRecord, String> {
+    let record = TransferRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        finished_at: now_ms(),
+        tags: normalize_tags(record.tags),
+        ..record
+    };
+
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    let path = history_file()?;
+    let mut records = read_records(&path);
+    records.push(record.clone());
+    write_records(&path, &records)?;
+
+    Ok(record)
+}
+
+/// Read every record, skipping ones that fail to parse
+pub fn load() -> Vec<TransferRecord> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    match history_file() {
+        Ok(path) => read_records(&path),
+        Err(e) => {
+            eprintln!("[History] {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn read_records(path: &std::path::Path) -> Vec<TransferRecord> {
+    let Ok(json) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let values: Vec<serde_json::Value> = match serde_json::from_str(&json) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("[History] Invalid history file {:?}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    values
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value(value) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                eprintln!("[History] Skipping invalid record: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Write the whole history, creating the data directory if needed
+fn write_records(path: &std::path::Path, records: &[TransferRecord]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(records)
+        .map_err(|e| format!("Failed to serialize transfer history: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write transfer history: {}", e))
+}
+
+/// Apply `change` to the record with `id` and rewrite the history file
+fn update_record<F: FnOnce(&mut TransferRecord)>(id: &str, change: F) -> Result<(), String> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    let path = history_file()?;
+    let mut records = read_records(&path);
+    let record = records
+        .iter_mut()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("Transfer not found: {}", id))?;
+    change(record);
+    write_records(&path, &records)
+}
+
+/// Trim tags and drop empty or duplicate ones
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Tauri command to tag a transfer in the history
+#[tauri::command]
+pub fn add_transfer_tag(transfer_id: String, tag: String) -> Result<(), String> {
+    let tag = tag.trim().to_string();
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+
+    update_record(&transfer_id, |record| {
+        if !record.tags.contains(&tag) {
+            record.tags.push(tag.clone());
+        }
+    })?;
+    println!("[History] Tagged {} with '{}'", transfer_id, tag);
+    Ok(())
+}
+
+/// Tauri command to list transfers carrying a tag
+#[tauri::command]
+pub fn get_transfers_by_tag(tag: String) -> Result<Vec<TransferRecord>, String> {
+    let tag = tag.trim();
+    Ok(load()
+        .into_iter()
+        .filter(|record| record.tags.iter().any(|t| t == tag))
+        .collect())
+}
+
+/// Tauri command to list every logged transfer, oldest first
+#[tauri::command]
+pub fn get_transfer_history() -> Result<Vec<TransferRecord>, String> {
+    Ok(load())
+}
+
+/// Tauri command to delete the transfer history
+#[tauri::command]
+pub fn clear_transfer_history() -> Result<(), String> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    let path = history_file()?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to clear transfer history: {}", e)),
+    }
+    println!("[History] Cleared");
+    Ok(())
+}
@@ -0,0 +1,138 @@
+// Hotspot fallback discovery (QR page + HTTP subnet polling)
+//
+// Some phone hotspots drop UDP broadcast entirely. This serves a small page
+// on HOTSPOT_PORT with a QR code of our connect address, and lets peers poll
+// the typical Android hotspot subnet for other AirShare servers.
+
+use crate::discovery::SharedDiscoveryState;
+use axum::{extract::State, response::Html, routing::get, Json, Router};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Serialize;
+use std::time::Duration;
+
+const HOTSPOT_PORT: u16 = 9989;
+/// Default gateway subnet used by Android hotspots
+const HOTSPOT_SUBNET: &str = "192.168.43";
+const PROBE_TIMEOUT_MS: u64 = 500;
+
+/// Peer found by polling the hotspot subnet
+#[derive(Debug, Clone, Serialize)]
+pub struct HotspotPeer {
+    pub ip: String,
+    pub url: String,
+}
+
+/// Start the QR broadcast page on all interfaces
+pub async fn start_qr_broadcast_server(state: SharedDiscoveryState) -> Result<(), String> {
+    let app = Router::new()
+        .route("/", get(qr_page))
+        .route("/hotspot-peers", get(hotspot_peers))
+        .with_state(state);
+
+    let addr = format!("0.0.0.0:{}", HOTSPOT_PORT);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+
+    println!("[Hotspot] QR page on port {}", HOTSPOT_PORT);
+
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| format!("Hotspot server error: {}", e))
+}
+
+/// Connect URI encoded in the QR code, parsed by scanning peers
+fn connect_uri(ip: &str) -> String {
+    format!("airshare://connect?ip={}", ip)
+}
+
+/// Escape text for inclusion in the HTML page
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Minimal HTML page with the device's QR code inlined as SVG
+async fn qr_page(State(state): State<SharedDiscoveryState>) -> Html<String> {
+    let (ip, name) = {
+        let state = state.read().await;
+        (state.local_ip.clone(), escape_html(&state.device_name))
+    };
+
+    let uri = connect_uri(&ip);
+    let qr_svg = match QrCode::new(uri.as_bytes()) {
+        Ok(code) => code.render::<svg::Color>().min_dimensions(256, 256).build(),
+        Err(e) => {
+            eprintln!("[Hotspot] Failed to encode QR: {}", e);
+            String::new()
+        }
+    };
+
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>AirShare - {name}</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center\">\
+         <h2>{name}</h2>{qr_svg}<p>Connect to <b>{ip}</b></p></body></html>"
+    ))
+}
+
+/// Return AirShare servers found on the hotspot subnet
+async fn hotspot_peers(State(state): State<SharedDiscoveryState>) -> Json<Vec<HotspotPeer>> {
+    let (our_ip, server_port) = {
+        let state = state.read().await;
+        (state.local_ip.clone(), state.server_port)
+    };
+    Json(scan_hotspot_subnet(&our_ip, server_port).await)
+}
+
+/// True if `ip` is on the typical Android hotspot subnet
+pub fn is_on_hotspot_subnet(ip: &str) -> bool {
+    ip.strip_prefix(HOTSPOT_SUBNET)
+        .map(|rest| rest.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// Probe every host in the hotspot /24 for an AirShare server on `server_port`
+pub async fn scan_hotspot_subnet(our_ip: &str, server_port: u16) -> Vec<HotspotPeer> {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(PROBE_TIMEOUT_MS))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("[Hotspot] Failed to build HTTP client: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut probes = tokio::task::JoinSet::new();
+    for host in 1..=254u8 {
+        let ip = format!("{}.{}", HOTSPOT_SUBNET, host);
+        if ip == our_ip {
+            continue;
+        }
+        let client = client.clone();
+        probes.spawn(async move {
+            let url = crate::server::peer_base_url(&ip, server_port);
+            match client.get(format!("{}/health", url)).send().await {
+                Ok(resp) if resp.status().is_success() => Some(HotspotPeer { ip, url }),
+                _ => None,
+            }
+        });
+    }
+
+    let mut peers = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(Some(peer)) = result {
+            peers.push(peer);
+        }
+    }
+
+    peers.sort_by(|a, b| a.ip.cmp(&b.ip));
+    println!("[Hotspot] Found {} peer(s) on {}.0/24", peers.len(), HOTSPOT_SUBNET);
+    peers
+}
@@ -0,0 +1,264 @@
+// Local IPC transfer (Unix domain socket / Windows named pipe)
+//
+// Transfers between processes on the same machine skip the HTTP stack:
+// the client writes one JSON `TransferRequest` line, the server answers
+// with one JSON `TransferResponse` line followed by the raw file bytes.
+
+use crate::server::{is_plain_filename, SharedServerState};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Request frame sent by the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub filename: String,
+}
+
+/// Response frame sent by the server before the file bytes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub error: String,
+}
+
+/// Default socket path used by `start_ipc_server` at startup
+pub fn default_socket_path() -> String {
+    #[cfg(unix)]
+    {
+        std::env::temp_dir()
+            .join("airshare.sock")
+            .to_string_lossy()
+            .to_string()
+    }
+    #[cfg(windows)]
+    {
+        r"\\.\pipe\airshare".to_string()
+    }
+}
+
+/// Start the IPC server on a Unix domain socket
+#[cfg(unix)]
+pub async fn start_ipc_server(state: SharedServerState, socket_path: &str) {
+    use tokio::net::UnixListener;
+
+    // A stale socket file from a previous run would make bind fail
+    let _ = std::fs::remove_file(socket_path);
+
+    let listener = match UnixListener::bind(socket_path) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("[IPC] Failed to bind {}: {}", socket_path, e);
+            return;
+        }
+    };
+
+    println!("[IPC] Listening on {}", socket_path);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _addr)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    handle_connection(state, stream).await;
+                });
+            }
+            Err(e) => {
+                eprintln!("[IPC] Accept error: {}", e);
+            }
+        }
+    }
+}
+
+/// Start the IPC server on a Windows named pipe
+#[cfg(windows)]
+pub async fn start_ipc_server(state: SharedServerState, socket_path: &str) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = match ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(socket_path)
+    {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[IPC] Failed to create pipe {}: {}", socket_path, e);
+            return;
+        }
+    };
+
+    println!("[IPC] Listening on {}", socket_path);
+
+    loop {
+        if let Err(e) = server.connect().await {
+            eprintln!("[IPC] Connect error: {}", e);
+            continue;
+        }
+
+        // Hand the connected instance off and create the next one
+        let connected = server;
+        server = match ServerOptions::new().create(socket_path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("[IPC] Failed to create pipe {}: {}", socket_path, e);
+                return;
+            }
+        };
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            handle_connection(state, connected).await;
+        });
+    }
+}
+
+/// Serve a single request on an accepted connection
+async fn handle_connection<S>(state: SharedServerState, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    if let Err(e) = reader.read_line(&mut line).await {
+        eprintln!("[IPC] Failed to read request: {}", e);
+        return;
+    }
+
+    let response = match serde_json::from_str::<TransferRequest>(line.trim()) {
+        Ok(request) => open_shared_file(&state, &request.filename).await,
+        Err(e) => Err(format!("Invalid request: {}", e)),
+    };
+
+    let mut stream = reader.into_inner();
+
+    match response {
+        Ok((mut file, size)) => {
+            let header = TransferResponse {
+                ok: true,
+                size,
+                error: String::new(),
+            };
+            if write_frame(&mut stream, &header).await.is_err() {
+                return;
+            }
+            match tokio::io::copy(&mut file, &mut stream).await {
+                Ok(bytes) => println!("[IPC] Sent {} bytes", bytes),
+                Err(e) => eprintln!("[IPC] Transfer failed: {}", e),
+            }
+        }
+        Err(error) => {
+            eprintln!("[IPC] {}", error);
+            let header = TransferResponse {
+                ok: false,
+                size: 0,
+                error,
+            };
+            let _ = write_frame(&mut stream, &header).await;
+        }
+    }
+
+    let _ = stream.shutdown().await;
+}
+
+/// Open a file from the shared directory, refusing anything but a bare filename
+pub async fn open_shared_file(
+    state: &SharedServerState,
+    filename: &str,
+) -> Result<(fs::File, u64), String> {
+    if !is_plain_filename(filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+
+    let file_path = state.shared_dir.join(filename);
+    let file = fs::File::open(&file_path)
+        .await
+        .map_err(|_| format!("File not found: {}", filename))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+
+    Ok((file, size))
+}
+
+/// Write a JSON frame terminated by a newline
+pub async fn write_frame<S, T>(stream: &mut S, frame: &T) -> std::io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut json = serde_json::to_vec(frame)?;
+    json.push(b'\n');
+    stream.write_all(&json).await
+}
+
+/// Request `filename` over the local socket and write it to `dest`
+async fn receive_file<S>(stream: S, filename: &str, dest: &str) -> Result<u64, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+
+    let request = TransferRequest {
+        filename: filename.to_string(),
+    };
+    write_frame(reader.get_mut(), &request)
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let response: TransferResponse = serde_json::from_str(line.trim())
+        .map_err(|e| format!("Invalid response: {}", e))?;
+
+    if !response.ok {
+        return Err(response.error);
+    }
+
+    let mut file = fs::File::create(dest)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let bytes = tokio::io::copy(&mut reader, &mut file)
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    if bytes != response.size {
+        return Err(format!(
+            "Transfer truncated: got {} of {} bytes",
+            bytes, response.size
+        ));
+    }
+
+    Ok(bytes)
+}
+
+/// Tauri command to fetch a file from a local AirShare process over IPC
+#[tauri::command]
+pub async fn transfer_via_ipc(
+    socket_path: String,
+    filename: String,
+    dest: String,
+) -> Result<String, String> {
+    println!("[IPC] Requesting {} from {}", filename, socket_path);
+
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(&socket_path)
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(&socket_path)
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+
+    let bytes = receive_file(stream, &filename, &dest).await?;
+    println!("[IPC] Received {} ({} bytes) -> {}", filename, bytes, dest);
+    Ok(dest)
+}
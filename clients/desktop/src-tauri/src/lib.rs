@@ -1,193 +1,1661 @@
 // AirShare - Native Rust Application with Phantom UI
 
+mod acl;
+mod archive;
+mod audio;
+mod auth;
+mod clipboard;
+mod config;
+mod connections;
 mod discovery;
+mod discovery_backends;
+mod favorites;
+mod history;
+mod hotspot;
+mod ipc;
+mod mobile;
+mod notifications;
+mod processors;
+mod rooms;
+#[cfg(feature = "webrtc")]
+mod rtc_transfer;
 mod server;
 mod smart_drop;
+mod transfer_requests;
+mod transfers;
 
-use discovery::{start_beacon, start_listener, DiscoveryState, Peer, SharedDiscoveryState};
+use config::AirShareConfig;
+use discovery::{DiscoveryState, Peer, PeerEvent, SharedDiscoveryState};
+use discovery_backends::{
+    DiscoveryBackend, DiscoveryRegistry, MdnsBackend, PeerCallback, SharedDiscoveryRegistry,
+    TcpFallbackBackend, UdpBroadcastBackend,
+};
 use server::{start_server, ServerState, SharedServerState};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Emitter, Manager,
+    Emitter, Listener, Manager, WebviewUrl, WebviewWindowBuilder,
 };
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 use tokio::sync::RwLock;
+use transfers::{
+    SharedTransferQueue, SharedTransferState, TransferProgress, TransferQueue, TransferState,
+};
+
+/// Tauri command to set grab state
+#[tauri::command]
+async fn set_grab(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    server: tauri::State<'_, SharedServerState>,
+    filename: String,
+) -> Result<(), String> {
+    // Tiny files ride along in the beacon so peers can skip the HTTP download
+    let mut inline = None;
+    if server::is_plain_filename(&filename) {
+        let path = server.shared_dir.join(&filename);
+        if let Ok(meta) = tokio::fs::metadata(&path).await {
+            if meta.is_file() && discovery::should_embed_inline(meta.len() as usize) {
+                inline = tokio::fs::read(&path).await.ok();
+            }
+        }
+    }
+
+    let mut discovery = state.write().await;
+    discovery.set_grab(&filename);
+    if let Some(data) = inline {
+        discovery.set_inline_data(&data);
+    }
+    Ok(())
+}
+
+/// Tauri command to hold a text snippet, advertised to peers in the beacon
+#[tauri::command]
+async fn set_grab_text(state: tauri::State<'_, SharedDiscoveryState>, text: String) -> Result<(), String> {
+    state.write().await.set_grab_text(&text)
+}
+
+/// Tauri command to send a text snippet to a peer
+#[tauri::command]
+async fn send_text(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    text: String,
+) -> Result<(), String> {
+    let (port, credentials) = {
+        let state = state.read().await;
+        (state.peer_server_port(&peer_ip), state.credentials())
+    };
+    server::send_text(&peer_ip, port, &text, &credentials).await?;
+    println!("[Text] Sent {} bytes to {}", text.len(), peer_ip);
+    Ok(())
+}
+
+/// Tauri command to get the latest text a peer sent us
+#[tauri::command]
+async fn get_received_text(
+    server: tauri::State<'_, SharedServerState>,
+) -> Result<Option<String>, String> {
+    Ok(server.received_text.read().await.clone())
+}
+
+/// Tauri command to clear grab state
+#[tauri::command]
+async fn clear_grab(state: tauri::State<'_, SharedDiscoveryState>) -> Result<(), String> {
+    let mut discovery = state.write().await;
+    discovery.clear_grab();
+    Ok(())
+}
+
+/// Tauri command to download a file
+///
+/// `transfer_id` is chosen by the caller and can be passed to
+/// `cancel_transfer` while the download runs. Emits `transfer-progress`
+/// after every chunk, then `transfer-complete` or `transfer-error`.
+#[tauri::command]
+async fn download_file(
+    app_handle: tauri::AppHandle,
+    transfers: tauri::State<'_, SharedTransferState>,
+    transfer_id: String,
+    url: String,
+    dest_path: String,
+    tags: Option<Vec<String>>,
+    expected_sha256: Option<String>,
+) -> Result<String, String> {
+    let (credentials, peer) = {
+        let discovery = app_handle.state::<SharedDiscoveryState>();
+        let discovery = discovery.read().await;
+        (discovery.credentials(), discovery.peer_for_url(&url).cloned())
+    };
+    let cancel = transfers.start(&transfer_id)?;
+    let started_at = history::now_ms();
+    let result = server::download_file(
+        &url,
+        &dest_path,
+        expected_sha256.as_deref(),
+        Some(&credentials),
+        Some(&cancel),
+        |progress| {
+            let _ = app_handle.emit("transfer-progress", TransferProgress::new(&transfer_id, &progress));
+        },
+    )
+    .await;
+    transfers.finish(&transfer_id);
+    match &result {
+        Ok(()) => {
+            let _ = app_handle.emit(
+                "transfer-complete",
+                serde_json::json!({ "transfer_id": transfer_id, "path": dest_path }),
+            );
+        }
+        Err(e) => {
+            let _ = app_handle.emit(
+                "transfer-error",
+                serde_json::json!({ "transfer_id": transfer_id, "error": e }),
+            );
+        }
+    }
+
+    let path = std::path::Path::new(&dest_path);
+    let filename = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let size = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+    let record = history::TransferRecord {
+        filename,
+        size_bytes: size,
+        peer_name: peer.as_ref().map(|p| p.name.clone()),
+        peer_id: peer.map(|p| p.id),
+        direction: history::Direction::Received,
+        started_at,
+        status: history::TransferStatus::of(&result),
+        tags: tags.unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Err(e) = history::record(record) {
+        eprintln!("[History] {}", e);
+    }
+    result?;
+    Ok(dest_path)
+}
+
+/// Tauri command to download a file from a peer's QUIC server into AirShare_Downloads
+#[tauri::command]
+async fn transfer_via_quic(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    port: u16,
+    filename: String,
+) -> Result<String, String> {
+    if !server::is_plain_filename(&filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+
+    let dest = std::path::Path::new(&get_airshare_downloads()?).join(&filename);
+    println!("[QUIC] Requesting {} from {}:{}", filename, peer_ip, port);
+    let credentials = state.read().await.credentials();
+    let bytes = server::quic_download(&peer_ip, port, &filename, &dest, &credentials).await?;
+    println!("[QUIC] Received {} ({} bytes)", filename, bytes);
+
+    dest.to_str()
+        .map(|s| s.to_string())
+        .ok_or("Invalid path".to_string())
+}
+
+/// Tauri command to check whether a peer is sharing a given file
+#[tauri::command]
+async fn check_peer_has_file(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    filename: String,
+) -> Result<bool, String> {
+    let port = state.read().await.peer_server_port(&peer_ip);
+    server::peer_has_file(&peer_ip, port, &filename).await
+}
+
+/// Tauri command to get a peer's SHA-256 for a file without downloading it
+#[tauri::command]
+async fn check_peer_file_hash(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    filename: String,
+) -> Result<Option<String>, String> {
+    let (port, credentials) = {
+        let state = state.read().await;
+        (state.peer_server_port(&peer_ip), state.credentials())
+    };
+    server::peer_file_hash(&peer_ip, port, &filename, &credentials).await
+}
+
+/// Tauri command to upload a local file to a peer, tagged with sender metadata,
+/// once the user there accepts it. Emits `transfer-send-progress` as the file
+/// is streamed.
+#[tauri::command]
+async fn upload_file_to_peer(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    local_path: String,
+    tags: Option<Vec<String>>,
+) -> Result<(), String> {
+    let tags = tags.unwrap_or_default();
+    transfers::send_file_to_peer(&app_handle, &state, &peer_ip, &local_path, tags).await
+}
+
+/// Tauri command to overwrite part of a file on a peer
+#[tauri::command]
+async fn patch_file_range(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+    filename: String,
+    offset: u64,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    let (port, credentials) = {
+        let state = state.read().await;
+        (state.peer_server_port(&peer_ip), state.credentials())
+    };
+    server::patch_file_range(&peer_ip, port, &filename, offset, data, &credentials).await
+}
+
+/// Tauri command to read the metadata stored with an uploaded file
+#[tauri::command]
+async fn get_file_metadata(
+    state: tauri::State<'_, SharedServerState>,
+    filename: String,
+) -> Result<serde_json::Value, String> {
+    server::read_file_meta(&state.shared_dir, &filename).await
+}
+
+/// Tauri command to limit downloads of a shared file to the given peer IDs
+///
+/// An empty list removes the restriction, like `clear_file_acl`.
+#[tauri::command]
+async fn set_file_acl(
+    state: tauri::State<'_, SharedServerState>,
+    filename: String,
+    peer_ids: Vec<String>,
+) -> Result<(), String> {
+    if !server::is_plain_filename(&filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+
+    let mut acls = state.file_acls.write().await;
+    if peer_ids.is_empty() {
+        acls.remove(&filename);
+        println!("[ACL] Cleared ACL for {}", filename);
+    } else {
+        println!("[ACL] {} restricted to {} peer(s)", filename, peer_ids.len());
+        acls.insert(filename, peer_ids);
+    }
+    acl::save(&acls)
+}
+
+/// Tauri command to let every peer download a shared file again
+#[tauri::command]
+async fn clear_file_acl(
+    state: tauri::State<'_, SharedServerState>,
+    filename: String,
+) -> Result<(), String> {
+    let mut acls = state.file_acls.write().await;
+    if acls.remove(&filename).is_some() {
+        acl::save(&acls)?;
+        println!("[ACL] Cleared ACL for {}", filename);
+    }
+    Ok(())
+}
+
+/// Tauri command to duplicate a shared file, returning the sanitized name of the copy
+///
+/// Fails if `dest_name` already exists.
+#[tauri::command]
+async fn copy_shared_file(
+    state: tauri::State<'_, SharedServerState>,
+    src: String,
+    dest_name: String,
+) -> Result<String, String> {
+    let copied_to = server::copy_shared_file(&state, &src, &dest_name, false, None)
+        .await
+        .map_err(|(_, message)| message)?;
+    println!("[Server] Copied {} to {}", src, copied_to);
+    Ok(copied_to)
+}
+
+/// Tauri command to compare serving a shared file plain against zstd
+#[tauri::command]
+async fn benchmark_zstd_vs_plain(
+    state: tauri::State<'_, SharedServerState>,
+    filename: String,
+) -> Result<server::ZstdBenchmark, String> {
+    if !server::is_plain_filename(&filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+    let result = server::benchmark_zstd(&state.shared_dir.join(&filename)).await?;
+    println!(
+        "[Server] zstd benchmark for {}: {} -> {} bytes ({}ms vs {}ms plain)",
+        filename, result.plain_bytes, result.zstd_bytes, result.zstd_ms, result.plain_ms
+    );
+    Ok(result)
+}
+
+/// Tauri command to list the shared folder including subdirectories
+#[tauri::command]
+async fn list_files_recursive(
+    state: tauri::State<'_, SharedServerState>,
+    depth: u32,
+) -> Result<Vec<serde_json::Value>, String> {
+    server::list_dir_recursive(&state.shared_dir, depth, false, state.config.follow_symlinks).await
+}
+
+/// Tauri command to get the UDP port the discovery listener is bound to
+#[tauri::command]
+async fn get_discovery_port(state: tauri::State<'_, SharedDiscoveryState>) -> Result<u16, String> {
+    Ok(state.read().await.discovery_port)
+}
+
+/// Send a leaving beacon and end every discovery task
+async fn shutdown_discovery(state: &SharedDiscoveryState, registry: &DiscoveryRegistry) {
+    discovery::send_leaving_beacon(state).await;
+    state.write().await.stop();
+    registry.stop_all();
+    println!("[Discovery] Stopped");
+}
+
+/// Tauri command to stop advertising this device and listening for peers
+///
+/// Backends can be started again with `register_discovery_backend`.
+#[tauri::command]
+async fn stop_discovery(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    registry: tauri::State<'_, SharedDiscoveryRegistry>,
+) -> Result<(), String> {
+    shutdown_discovery(&state, &registry).await;
+    Ok(())
+}
+
+/// Tauri command to replace this device's persistent ID with a new one
+///
+/// Peers drop the old ID on the leaving beacon and pick up the new one
+/// from the next regular beacon.
+#[tauri::command]
+async fn reset_device_id(state: tauri::State<'_, SharedDiscoveryState>) -> Result<String, String> {
+    let new_id = uuid::Uuid::new_v4().to_string();
+    config::save_device_id(&new_id)?;
+
+    discovery::send_leaving_beacon(&state).await;
+    state.write().await.device_id = new_id.clone();
+
+    println!("[Discovery] Device ID reset: {}", new_id);
+    Ok(new_id)
+}
+
+/// Tauri command to get the name this device advertises to peers
+#[tauri::command]
+async fn get_device_name(state: tauri::State<'_, SharedDiscoveryState>) -> Result<String, String> {
+    Ok(state.read().await.device_name.clone())
+}
+
+/// Tauri command to set a friendly device name, persisted in device.json
+///
+/// Peers see it from the next beacon on.
+#[tauri::command]
+async fn set_device_name(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    name: String,
+) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Device name cannot be empty".to_string());
+    }
+    if name.len() > config::MAX_DEVICE_NAME_BYTES {
+        return Err(format!(
+            "Device name is {} bytes; the limit is {}",
+            name.len(),
+            config::MAX_DEVICE_NAME_BYTES
+        ));
+    }
+
+    let mut discovery = state.write().await;
+    config::save_device_name(&discovery.device_id, name)?;
+    discovery.device_name = name.to_string();
+    println!("[Discovery] Device name: {}", name);
+    Ok(())
+}
+
+/// Tauri command to get local device info
+#[tauri::command]
+async fn get_device_info(
+    state: tauri::State<'_, SharedDiscoveryState>,
+) -> Result<serde_json::Value, String> {
+    let discovery = state.read().await;
+    Ok(serde_json::json!({
+        "id": discovery.device_id,
+        "name": discovery.device_name,
+        "ip": discovery.local_ip,
+        "server_port": discovery.server_port
+    }))
+}
+
+/// Tauri command to manually connect to a peer by IP (for hotspot fallback)
+#[tauri::command]
+async fn manual_connect(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    ip: String,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    connect_manual_peer(&state, &app_handle, &ip, None).await
+}
+
+/// Add a peer by IP, named `name` or after its address, and emit `peer-discovered`
+async fn connect_manual_peer(
+    state: &SharedDiscoveryState,
+    app_handle: &tauri::AppHandle,
+    ip: &str,
+    name: Option<&str>,
+) -> Result<String, String> {
+    // Store the canonical form so the same address can't be added twice
+    let ip = favorites::normalize_ip(ip)?;
+    let peer = Peer {
+        id: format!("manual-{}", ip),
+        ip: ip.clone(),
+        name: name.map_or_else(|| format!("Device at {}", ip), str::to_string),
+        is_holding: false,
+        held_file: String::new(),
+        held_file_url: None,
+        inline_data: None,
+        supports_zstd: false,
+        server_port: server::DEFAULT_SERVER_PORT,
+        held_text: None,
+    };
+    
+    {
+        let mut discovery = state.write().await;
+        discovery.peers.insert(peer.id.clone(), peer.clone());
+    }
+    
+    let _ = app_handle.emit("peer-discovered", &peer);
+    println!("[Discovery] Manual connect: {}", ip);
+    Ok(ip)
+}
+
+/// Tauri command to list the IPv4 interfaces discovery can use
+#[tauri::command]
+fn list_network_interfaces() -> Result<Vec<discovery::InterfaceInfo>, String> {
+    discovery::network_interfaces()
+}
+
+/// Tauri command to send beacons from the interface with `ip`, persisted in the config
+///
+/// Peers are told the new address from the next beacon on.
+#[tauri::command]
+async fn set_preferred_interface(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    ip: String,
+) -> Result<(), String> {
+    let ip = discovery::find_interface(&ip)?;
+    AirShareConfig::update(|config| config.preferred_interface = Some(ip.to_string()))?;
+    state.write().await.set_preferred_ip(ip);
+    Ok(())
+}
+
+/// Tauri command to list known peers, sorted by name
+///
+/// Lets the frontend rebuild its peer list after a reload instead of relying
+/// on the events it has seen.
+#[tauri::command]
+async fn get_peers(state: tauri::State<'_, SharedDiscoveryState>) -> Result<Vec<Peer>, String> {
+    let mut peers: Vec<Peer> = state.read().await.peers.values().cloned().collect();
+    peers.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    Ok(peers)
+}
+
+/// Tauri command to forget a peer, e.g. a manual peer that is no longer reachable
+///
+/// Emits `peer-lost` like a peer that stopped beaconing. A peer that is still
+/// beaconing will be discovered again.
+#[tauri::command]
+async fn remove_peer(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    app_handle: tauri::AppHandle,
+    peer_id: String,
+) -> Result<(), String> {
+    let removed = {
+        let mut discovery = state.write().await;
+        discovery.peer_last_seen.remove(&peer_id);
+        discovery.peers.remove(&peer_id)
+    };
+    let peer = removed.ok_or_else(|| format!("Unknown peer: {}", peer_id))?;
+
+    println!("[Discovery] Removed peer: {} at {}", peer.name, peer.ip);
+    let _ = app_handle.emit("peer-lost", &peer_id);
+    Ok(())
+}
+
+/// Tauri command to measure round-trip time to a peer over UDP (microseconds)
+#[tauri::command]
+async fn ping_peer_udp(
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+) -> Result<u64, String> {
+    let (our_id, port) = {
+        let state = state.read().await;
+        (state.device_id.clone(), state.default_discovery_port)
+    };
+    discovery::ping_peer(&our_id, &peer_ip, port).await
+}
+
+/// Tauri command to measure upload/download speed and latency to a peer
+///
+/// Takes a few seconds on slow links; the result is also emitted as
+/// `bandwidth-test-complete` with the peer's IP added.
+#[tauri::command]
+async fn measure_bandwidth_to_peer(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SharedDiscoveryState>,
+    peer_ip: String,
+) -> Result<serde_json::Value, String> {
+    let port = state.read().await.peer_server_port(&peer_ip);
+    let result = server::measure_bandwidth(&peer_ip, port).await?;
+    println!(
+        "[Bandwidth] {}: up {:.0} kbps, down {:.0} kbps, {} ms",
+        peer_ip, result.upload_kbps, result.download_kbps, result.latency_ms
+    );
+
+    let value = serde_json::to_value(&result).map_err(|e| e.to_string())?;
+    let mut event = value.clone();
+    event["peer_ip"] = serde_json::Value::String(peer_ip);
+    let _ = app_handle.emit("bandwidth-test-complete", event);
+    Ok(value)
+}
+
+/// Label of the Phantom overlay window declared in tauri.conf.json
+const MAIN_WINDOW: &str = "main";
+/// Label of the settings dashboard window
+const SETTINGS_WINDOW: &str = "settings";
+
+/// The Phantom overlay window
+///
+/// Phantom commands look the window up by label rather than using the
+/// invoking window, so calls from the settings window never make it
+/// transparent or click-through.
+fn main_window(app: &tauri::AppHandle) -> Result<tauri::WebviewWindow, String> {
+    app.get_webview_window(MAIN_WINDOW)
+        .ok_or_else(|| "Main window not found".to_string())
+}
+
+/// Shortest PIN `set_pin` accepts
+const MIN_PIN_LENGTH: usize = 4;
+
+/// Tauri command to set the shared-secret PIN; an empty PIN turns authentication off
+///
+/// Once set, beacons are signed with it, peers without the same PIN are
+/// ignored, and file downloads require the PIN's token.
+#[tauri::command]
+async fn set_pin(state: tauri::State<'_, SharedDiscoveryState>, pin: String) -> Result<(), String> {
+    let pin = pin.trim();
+    if !pin.is_empty() && pin.chars().count() < MIN_PIN_LENGTH {
+        return Err(format!("PIN must be at least {} characters", MIN_PIN_LENGTH));
+    }
+
+    let mut state = state.write().await;
+    if pin.is_empty() {
+        state.pin = None;
+        println!("[Auth] PIN cleared, accepting all peers");
+    } else {
+        state.pin = Some(pin.to_string());
+        println!("[Auth] PIN set, accepting only peers with the same PIN");
+    }
+    Ok(())
+}
+
+/// Tauri command to toggle click-through mode
+#[tauri::command]
+async fn set_click_through(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let window = main_window(&app)?;
+    window
+        .set_ignore_cursor_events(enabled)
+        .map_err(|e| e.to_string())?;
+    println!("[Phantom] Click-through: {}", enabled);
+    Ok(())
+}
+
+/// Dashboard geometry to restore when Phantom Mode ends (seeded from config)
+type SharedPhantomRestore = Arc<std::sync::Mutex<Option<config::PhantomRestoreState>>>;
+
+/// Turn the main window into the Phantom Mode overlay
+///
+/// Remembers the dashboard's size and position first, in memory and in the
+/// config, so `exit_phantom` can put the window back where it was.
+fn enter_phantom(app: &tauri::AppHandle, restore: &SharedPhantomRestore) -> Result<(), String> {
+    let window = main_window(app)?;
+    println!("[Phantom] Entering Phantom Mode...");
+
+    // Entering twice would record the fullscreen geometry
+    if !window.is_fullscreen().map_err(|e| e.to_string())? {
+        let size = window.inner_size().map_err(|e| e.to_string())?;
+        let position = window.outer_position().map_err(|e| e.to_string())?;
+        let geometry = config::PhantomRestoreState {
+            width: size.width,
+            height: size.height,
+            x: position.x,
+            y: position.y,
+        };
+        *restore.lock().unwrap() = Some(geometry);
+        if let Err(e) = AirShareConfig::update(|config| config.dashboard_window = Some(geometry)) {
+            eprintln!("[Phantom] Failed to save window geometry: {}", e);
+        }
+    }
+    
+    // Set fullscreen
+    window.set_fullscreen(true).map_err(|e| e.to_string())?;
+    
+    // Remove decorations
+    window.set_decorations(false).map_err(|e| e.to_string())?;
+    
+    // Always on top
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    
+    // Enable click-through
+    window.set_ignore_cursor_events(true).map_err(|e| e.to_string())?;
+    
+    println!("[Phantom] Mode activated!");
+    Ok(())
+}
+
+/// Turn the Phantom Mode overlay back into the dashboard
+///
+/// Restores the geometry saved by `enter_phantom`, or centers a
+/// 1000×700 dashboard if none was saved.
+fn exit_phantom(app: &tauri::AppHandle, restore: &SharedPhantomRestore) -> Result<(), String> {
+    let window = main_window(app)?;
+    println!("[Phantom] Exiting Phantom Mode...");
+    
+    // Disable click-through first
+    window.set_ignore_cursor_events(false).map_err(|e| e.to_string())?;
+    
+    // Exit fullscreen
+    window.set_fullscreen(false).map_err(|e| e.to_string())?;
+    
+    // Restore decorations
+    window.set_decorations(true).map_err(|e| e.to_string())?;
+    
+    // Not always on top
+    window.set_always_on_top(false).map_err(|e| e.to_string())?;
+    
+    // Back to the dashboard's previous size, or the default one
+    let geometry = *restore.lock().unwrap();
+    match geometry {
+        Some(geometry) => {
+            let _ = window.set_size(tauri::PhysicalSize::new(geometry.width, geometry.height));
+            let _ = window.set_position(tauri::PhysicalPosition::new(geometry.x, geometry.y));
+        }
+        None => {
+            let _ = window.set_size(tauri::LogicalSize::new(1000.0, 700.0));
+            let _ = window.center();
+        }
+    }
+    
+    println!("[Phantom] Back to Dashboard mode");
+    Ok(())
+}
+
+/// Tauri command to enter Phantom Mode (transparent overlay)
+#[tauri::command]
+async fn enter_phantom_mode(
+    app: tauri::AppHandle,
+    restore: tauri::State<'_, SharedPhantomRestore>,
+) -> Result<(), String> {
+    enter_phantom(&app, &restore)
+}
+
+/// Tauri command to exit Phantom Mode (back to windowed)
+#[tauri::command]
+async fn exit_phantom_mode(
+    app: tauri::AppHandle,
+    restore: tauri::State<'_, SharedPhantomRestore>,
+) -> Result<(), String> {
+    exit_phantom(&app, &restore)
+}
+
+/// Enter Phantom Mode from the dashboard or leave it from the overlay
+///
+/// The dashboard is the only decorated state of the main window.
+fn toggle_phantom_mode(app: &tauri::AppHandle) -> Result<(), String> {
+    let restore = app.state::<SharedPhantomRestore>();
+    if main_window(app)?.is_decorated().map_err(|e| e.to_string())? {
+        enter_phantom(app, &restore)
+    } else {
+        exit_phantom(app, &restore)
+    }
+}
+
+/// Global shortcut currently bound to `toggle_phantom_mode`, if any
+type SharedPhantomHotkey = Arc<std::sync::Mutex<Option<Shortcut>>>;
+
+/// Parse a shortcut such as "CommandOrControl+Shift+P"
+fn parse_hotkey(shortcut: &str) -> Result<Shortcut, String> {
+    shortcut
+        .parse::<Shortcut>()
+        .map_err(|e| format!("Invalid shortcut {:?}: {}", shortcut, e))
+}
+
+/// Tauri command to change the global shortcut toggling Phantom Mode
+///
+/// An empty shortcut removes it. The old shortcut is released before the
+/// new one is bound, and kept if the new one can't be registered (e.g.
+/// because another app owns it). Persisted in the config.
+#[tauri::command]
+fn set_phantom_hotkey(
+    app: tauri::AppHandle,
+    hotkey: tauri::State<'_, SharedPhantomHotkey>,
+    shortcut: String,
+) -> Result<(), String> {
+    let shortcut = shortcut.trim().to_string();
+    let new = if shortcut.is_empty() { None } else { Some(parse_hotkey(&shortcut)?) };
+
+    let mut current = hotkey.lock().unwrap();
+    if *current != new {
+        let global_shortcut = app.global_shortcut();
+        let old = *current;
+        if let Some(old) = old {
+            global_shortcut
+                .unregister(old)
+                .map_err(|e| format!("Failed to release the old shortcut: {}", e))?;
+            *current = None;
+        }
+        if let Some(new) = new {
+            if let Err(e) = global_shortcut.register(new) {
+                if let Some(old) = old.filter(|old| global_shortcut.register(*old).is_ok()) {
+                    *current = Some(old);
+                }
+                return Err(format!("Failed to register {}: {}", shortcut, e));
+            }
+            *current = Some(new);
+        }
+    }
+    drop(current);
+
+    let saved = Some(shortcut.clone()).filter(|s| !s.is_empty());
+    AirShareConfig::update(|config| config.phantom_hotkey = saved)?;
+    if shortcut.is_empty() {
+        println!("[Phantom] Hotkey removed");
+    } else {
+        println!("[Phantom] Hotkey: {}", shortcut);
+    }
+    Ok(())
+}
+
+/// Physical `(x, y, width, height)` of a snap `position` on `monitor`
+fn snap_rect(monitor: &tauri::Monitor, position: &str) -> Result<(i32, i32, u32, u32), String> {
+    let origin = monitor.position();
+    let (width, height) = (monitor.size().width, monitor.size().height);
+    let (half_width, half_height) = (width / 2, height / 2);
+
+    // The right/bottom halves take the odd pixel so the two halves tile exactly
+    let (x, y, w, h) = match position {
+        "left" => (0, 0, half_width, height),
+        "right" => (half_width, 0, width - half_width, height),
+        "top-left" => (0, 0, half_width, half_height),
+        "top-right" => (half_width, 0, width - half_width, half_height),
+        "bottom-left" => (0, half_height, half_width, height - half_height),
+        "bottom-right" => (half_width, half_height, width - half_width, height - half_height),
+        "center" => (width / 4, height / 4, half_width, half_height),
+        other => return Err(format!("Unknown snap position: {}", other)),
+    };
+    Ok((origin.x + x as i32, origin.y + y as i32, w, h))
+}
+
+/// Move and resize `window` into `position` on `monitor`
+fn snap_window_to(window: &tauri::Window, monitor: &tauri::Monitor, position: &str) -> Result<(), String> {
+    let (x, y, width, height) = snap_rect(monitor, position)?;
+
+    // Fullscreen and maximized windows ignore size changes
+    window.set_fullscreen(false).map_err(|e| e.to_string())?;
+    window.unmaximize().map_err(|e| e.to_string())?;
+
+    window
+        .set_size(tauri::PhysicalSize::new(width, height))
+        .map_err(|e| e.to_string())?;
+    window
+        .set_position(tauri::PhysicalPosition::new(x, y))
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Tauri command to snap the window to part of the monitor it is on
+///
+/// `position` is "left", "right", "top-left", "top-right", "bottom-left",
+/// "bottom-right" or "center".
+#[tauri::command]
+async fn snap_window(window: tauri::Window, position: String) -> Result<(), String> {
+    let monitor = match window.current_monitor().map_err(|e| e.to_string())? {
+        Some(monitor) => monitor,
+        None => window
+            .primary_monitor()
+            .map_err(|e| e.to_string())?
+            .ok_or("No monitor found")?,
+    };
+
+    snap_window_to(&window, &monitor, &position)?;
+    println!("[Phantom] Snapped window: {}", position);
+    Ok(())
+}
+
+/// Tauri command to snap the window to part of a specific monitor
+#[tauri::command]
+async fn snap_to_monitor(
+    window: tauri::Window,
+    monitor_index: u32,
+    position: String,
+) -> Result<(), String> {
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors.get(monitor_index as usize).ok_or_else(|| {
+        format!("No monitor {} ({} available)", monitor_index, monitors.len())
+    })?;
+
+    snap_window_to(&window, monitor, &position)?;
+    println!("[Phantom] Snapped window: {} on monitor {}", position, monitor_index);
+    Ok(())
+}
+
+/// Apply `opacity` to the native window (layered window alpha on Windows,
+/// `NSWindow.alphaValue` on macOS)
+#[cfg(target_os = "windows")]
+fn apply_window_opacity(window: &tauri::WebviewWindow, opacity: f64) -> Result<(), String> {
+    use windows::Win32::Foundation::{COLORREF, HWND};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongW, SetLayeredWindowAttributes, SetWindowLongW, GWL_EXSTYLE, LWA_ALPHA,
+        WS_EX_LAYERED,
+    };
+
+    // Tauri may link a different `windows` version, so rebuild the handle from its raw pointer
+    let hwnd = HWND(window.hwnd().map_err(|e| e.to_string())?.0);
+    let alpha = (opacity * 255.0).round() as u8;
+    unsafe {
+        // Alpha only applies to layered windows
+        let style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        SetWindowLongW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED.0 as i32);
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA)
+            .map_err(|e| format!("Failed to set window opacity: {}", e))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_window_opacity(window: &tauri::WebviewWindow, opacity: f64) -> Result<(), String> {
+    // AppKit objects may only be touched on the main thread
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || match target.ns_window() {
+            Ok(ns_window) => {
+                let ns_window = unsafe { &*(ns_window as *const objc2_app_kit::NSWindow) };
+                ns_window.setAlphaValue(opacity);
+            }
+            Err(e) => eprintln!("[Phantom] Failed to get NSWindow: {}", e),
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn apply_window_opacity(_window: &tauri::WebviewWindow, _opacity: f64) -> Result<(), String> {
+    Err("Window opacity is not supported on this platform".to_string())
+}
+
+/// Tauri command to set the window opacity (0.0 fully transparent, 1.0 opaque)
+#[tauri::command]
+async fn set_window_opacity(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SharedDiscoveryState>,
+    opacity: f64,
+) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("Opacity must be between 0.0 and 1.0, got {}", opacity));
+    }
+
+    apply_window_opacity(&main_window(&app)?, opacity)?;
+    state.write().await.window_opacity = opacity;
+    println!("[Phantom] Opacity: {:.2}", opacity);
+    Ok(())
+}
+
+/// Tauri command to get the opacity last set with `set_window_opacity`
+#[tauri::command]
+async fn get_window_opacity(state: tauri::State<'_, SharedDiscoveryState>) -> Result<f64, String> {
+    Ok(state.read().await.window_opacity)
+}
+
+/// Open the settings window, or focus it if it is already open
+fn open_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(SETTINGS_WINDOW) {
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    // A regular decorated window: not transparent, and never click-through
+    WebviewWindowBuilder::new(app, SETTINGS_WINDOW, WebviewUrl::App("settings".into()))
+        .title("AirShare - Settings")
+        .inner_size(400.0, 500.0)
+        .decorations(true)
+        .resizable(false)
+        .center()
+        .build()
+        .map_err(|e| format!("Failed to open settings window: {}", e))?;
+
+    println!("[Settings] Window opened");
+    Ok(())
+}
+
+/// Close the settings window if it is open
+fn close_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(SETTINGS_WINDOW) {
+        window.close().map_err(|e| e.to_string())?;
+        println!("[Settings] Window closed");
+    }
+    Ok(())
+}
+
+fn toggle_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    if app.get_webview_window(SETTINGS_WINDOW).is_some() {
+        close_settings(app)
+    } else {
+        open_settings(app)
+    }
+}
+
+/// Tray "Status" menu item, relabelled as peers come and go
+type TrayStatusItem = Arc<std::sync::Mutex<MenuItem>>;
+
+/// Tray status label shown while no peers are visible
+const TRAY_STATUS_SCANNING: &str = "Status: Scanning…";
+
+/// Relabel the tray status item with the current number of peers
+fn refresh_tray_status(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let count = app.state::<SharedDiscoveryState>().read().await.peers.len();
+        let label = match count {
+            0 => TRAY_STATUS_SCANNING.to_string(),
+            1 => "1 device nearby".to_string(),
+            n => format!("{} devices nearby", n),
+        };
+        let Some(item) = app.try_state::<TrayStatusItem>() else {
+            return;
+        };
+        let result = item.lock().unwrap().set_text(label);
+        if let Err(e) = result {
+            eprintln!("[Tray] Failed to update status: {}", e);
+        }
+    });
+}
+
+/// Tauri command to open the settings dashboard window
+#[tauri::command]
+async fn open_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+    open_settings(&app)
+}
+
+/// Tauri command to close the settings dashboard window
+#[tauri::command]
+async fn close_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+    close_settings(&app)
+}
+
+/// Tauri command to open the settings window, or close it if already open
+#[tauri::command]
+async fn toggle_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+    toggle_settings(&app)
+}
+
+/// Tauri command to simulate a mouse click at current cursor position
+#[tauri::command]
+fn simulate_click() -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings, Button};
+    
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.button(Button::Left, enigo::Direction::Click).map_err(|e| e.to_string())?;
+    
+    println!("[Gesture] Simulated click");
+    Ok(())
+}
+
+/// Upper bound for the pause between the two clicks of a double click
+const MAX_DOUBLE_CLICK_DELAY_MS: u64 = 500;
+
+/// Move the cursor to (x, y) and click there using a single Enigo instance,
+/// so no other simulated input can land between the move and the click
+///
+/// The point must lie on one of the connected monitors.
+fn click_at(
+    window: &tauri::Window,
+    x: i32,
+    y: i32,
+    button: enigo::Button,
+    clicks: u32,
+    delay_ms: u64,
+) -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings, Coordinate};
+
+    ensure_on_monitor(window, x, y)?;
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
+
+    for i in 0..clicks {
+        if i > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+        }
+        enigo.button(button, enigo::Direction::Click).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Tauri command to simulate a left click at specific screen coordinates
+#[tauri::command]
+fn simulate_click_at(window: tauri::Window, x: i32, y: i32) -> Result<(), String> {
+    click_at(&window, x, y, enigo::Button::Left, 1, 0)?;
+    println!("[Gesture] Simulated click at ({}, {})", x, y);
+    Ok(())
+}
+
+/// Longest hold accepted by `simulate_long_press`
+const MAX_LONG_PRESS_MS: u64 = 5000;
+
+/// Set while `simulate_long_press` is holding the left button down
+static IS_PRESSING: AtomicBool = AtomicBool::new(false);
+
+/// Tauri command to press and hold the left button at (x, y), returning immediately
+#[tauri::command]
+fn simulate_long_press(window: tauri::Window, x: i32, y: i32, hold_ms: u64) -> Result<(), String> {
+    use enigo::{Button, Coordinate, Direction, Enigo, Mouse, Settings};
+
+    let hold_ms = hold_ms.min(MAX_LONG_PRESS_MS);
+    ensure_on_monitor(&window, x, y)?;
+
+    if IS_PRESSING
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("A long press is already in progress".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = (|| -> Result<(), String> {
+            let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+            enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
+            enigo.button(Button::Left, Direction::Press).map_err(|e| e.to_string())?;
+            std::thread::sleep(std::time::Duration::from_millis(hold_ms));
+            enigo.button(Button::Left, Direction::Release).map_err(|e| e.to_string())
+        })();
+
+        IS_PRESSING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(()) => println!("[Gesture] Long press at ({}, {}) for {}ms", x, y, hold_ms),
+            Err(e) => eprintln!("[Gesture] Long press failed: {}", e),
+        }
+    });
+
+    Ok(())
+}
+
+/// Tauri command to check whether a simulated long press is in progress
+#[tauri::command]
+fn is_pressing() -> Result<bool, String> {
+    Ok(IS_PRESSING.load(Ordering::SeqCst))
+}
+
+/// Tauri command to simulate a right click at specific screen coordinates
+#[tauri::command]
+fn simulate_right_click_at(window: tauri::Window, x: i32, y: i32) -> Result<(), String> {
+    click_at(&window, x, y, enigo::Button::Right, 1, 0)?;
+    println!("[Gesture] Simulated right click at ({}, {})", x, y);
+    Ok(())
+}
+
+/// Tauri command to simulate a double click at specific screen coordinates
+#[tauri::command]
+fn simulate_double_click_at(window: tauri::Window, x: i32, y: i32, delay_ms: u64) -> Result<(), String> {
+    let delay_ms = delay_ms.min(MAX_DOUBLE_CLICK_DELAY_MS);
+    click_at(&window, x, y, enigo::Button::Left, 2, delay_ms)?;
+    println!("[Gesture] Simulated double click at ({}, {})", x, y);
+    Ok(())
+}
+
+/// Allowed range for the gesture sensitivity multiplier
+const MIN_GESTURE_SENSITIVITY: f64 = 0.1;
+const MAX_GESTURE_SENSITIVITY: f64 = 5.0;
+
+/// Gesture sensitivity multiplier, stored as f64 bits (1.0 until loaded from config)
+static GESTURE_SENSITIVITY: AtomicU64 = AtomicU64::new(0x3FF0_0000_0000_0000);
+
+fn gesture_sensitivity() -> f64 {
+    f64::from_bits(GESTURE_SENSITIVITY.load(Ordering::Relaxed))
+}
+
+/// Scale a gesture delta by the current sensitivity
+fn scale_by_sensitivity(value: i32) -> i32 {
+    (value as f64 * gesture_sensitivity()).round() as i32
+}
+
+/// Tauri command to set the gesture sensitivity multiplier (0.1–5.0)
+#[tauri::command]
+fn set_gesture_sensitivity(sensitivity: f64) -> Result<(), String> {
+    if !(MIN_GESTURE_SENSITIVITY..=MAX_GESTURE_SENSITIVITY).contains(&sensitivity) {
+        return Err(format!(
+            "Sensitivity must be between {} and {}",
+            MIN_GESTURE_SENSITIVITY, MAX_GESTURE_SENSITIVITY
+        ));
+    }
+
+    GESTURE_SENSITIVITY.store(sensitivity.to_bits(), Ordering::Relaxed);
+    AirShareConfig::update(|config| config.gesture_sensitivity = sensitivity)?;
+
+    println!("[Gesture] Sensitivity: {}", sensitivity);
+    Ok(())
+}
+
+/// Tauri command to get the gesture sensitivity multiplier
+#[tauri::command]
+fn get_gesture_sensitivity() -> Result<f64, String> {
+    Ok(gesture_sensitivity())
+}
+
+/// Largest scroll per call, in wheel ticks, so a bad value can't scroll wildly
+const MAX_SCROLL_TICKS: i32 = 20;
+
+/// Scroll `amount` ticks on `axis`, first moving the cursor to (`x`, `y`) if given
+///
+/// The move and the scroll go through one Enigo instance back to back, so the
+/// scroll lands on the target even if the user moves the mouse meanwhile.
+/// Returns the number of ticks actually scrolled.
+fn scroll_at(
+    window: &tauri::Window,
+    axis: enigo::Axis,
+    amount: i32,
+    x: Option<i32>,
+    y: Option<i32>,
+) -> Result<i32, String> {
+    use enigo::{Coordinate, Enigo, Mouse, Settings};
+
+    let target = match (x, y) {
+        (Some(x), Some(y)) => Some((x, y)),
+        (None, None) => None,
+        _ => return Err("x and y must be given together".to_string()),
+    };
+    if let Some((x, y)) = target {
+        ensure_on_monitor(window, x, y)?;
+    }
+
+    let amount = scale_by_sensitivity(amount).clamp(-MAX_SCROLL_TICKS, MAX_SCROLL_TICKS);
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    if let Some((x, y)) = target {
+        enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
+    }
+    enigo.scroll(amount, axis).map_err(|e| e.to_string())?;
+    Ok(amount)
+}
+
+/// Tauri command to simulate scroll, optionally at screen coordinates (`x`, `y`)
+#[tauri::command]
+fn simulate_scroll(
+    window: tauri::Window,
+    direction: i32,
+    x: Option<i32>,
+    y: Option<i32>,
+) -> Result<(), String> {
+    // Positive = scroll up, Negative = scroll down
+    let direction = scroll_at(&window, enigo::Axis::Vertical, direction, x, y)?;
+
+    println!("[Gesture] Simulated scroll: {}", direction);
+    Ok(())
+}
+
+/// Tauri command to scroll horizontally, optionally at screen coordinates (`x`, `y`)
+#[tauri::command]
+fn simulate_scroll_horizontal(
+    window: tauri::Window,
+    amount: i32,
+    x: Option<i32>,
+    y: Option<i32>,
+) -> Result<(), String> {
+    // Positive = scroll right, Negative = scroll left
+    let amount = scroll_at(&window, enigo::Axis::Horizontal, amount, x, y)?;
+
+    println!("[Gesture] Simulated horizontal scroll: {}", amount);
+    Ok(())
+}
+
+/// Inertia scroll tuning: 60 fps steps with 20% decay per step
+const INERTIA_FRAME_MS: u64 = 16;
+const INERTIA_DECAY: f64 = 0.8;
+const INERTIA_MIN_MAGNITUDE: f64 = 0.5;
+const MAX_INERTIA_VELOCITY: f64 = 50.0;
+
+/// Bumped whenever an inertia scroll starts or is stopped; a running
+/// scroll exits as soon as the generation no longer matches its own
+static INERTIA_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Tauri command to scroll with exponentially decaying velocity (trackpad-style)
+#[tauri::command]
+fn simulate_inertia_scroll(velocity: f64, axis: String) -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings, Axis};
+
+    let axis = match axis.as_str() {
+        "vertical" => Axis::Vertical,
+        "horizontal" => Axis::Horizontal,
+        _ => return Err(format!("Invalid axis: {} (expected vertical or horizontal)", axis)),
+    };
+    if !velocity.is_finite() {
+        return Err("Velocity must be a finite number".to_string());
+    }
+
+    let velocity = (velocity * gesture_sensitivity()).clamp(-MAX_INERTIA_VELOCITY, MAX_INERTIA_VELOCITY);
+    let generation = INERTIA_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut enigo = match Enigo::new(&Settings::default()) {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("[Gesture] Inertia scroll failed: {}", e);
+                return;
+            }
+        };
+
+        let sign = velocity.signum() as i32;
+        let mut magnitude = velocity.abs();
+        while magnitude >= INERTIA_MIN_MAGNITUDE
+            && INERTIA_GENERATION.load(Ordering::SeqCst) == generation
+        {
+            if let Err(e) = enigo.scroll(sign * magnitude.round() as i32, axis) {
+                eprintln!("[Gesture] Inertia scroll failed: {}", e);
+                return;
+            }
+            magnitude *= INERTIA_DECAY;
+            std::thread::sleep(std::time::Duration::from_millis(INERTIA_FRAME_MS));
+        }
+    });
+
+    println!("[Gesture] Inertia scroll: {}", velocity);
+    Ok(())
+}
+
+/// Tauri command to stop a running inertia scroll
+#[tauri::command]
+fn stop_inertia_scroll() -> Result<(), String> {
+    INERTIA_GENERATION.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Hold Ctrl while scrolling, which most applications treat as zoom
+fn ctrl_scroll(enigo: &mut enigo::Enigo, direction: i32) -> Result<(), String> {
+    use enigo::{Keyboard, Mouse, Key, Axis, Direction};
+
+    enigo.key(Key::Control, Direction::Press).map_err(|e| e.to_string())?;
+    let result = enigo.scroll(direction, Axis::Vertical).map_err(|e| e.to_string());
+    // Release Ctrl even if the scroll failed so it doesn't stay stuck
+    let _ = enigo.key(Key::Control, Direction::Release);
+    result
+}
+
+/// Tauri command to simulate pinch-to-zoom via Ctrl+scroll
+#[tauri::command]
+fn simulate_zoom(direction: i32) -> Result<(), String> {
+    use enigo::{Enigo, Settings};
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    ctrl_scroll(&mut enigo, direction)?;
+
+    println!("[Gesture] Simulated zoom: {}", direction);
+    Ok(())
+}
+
+/// Tauri command to switch to the previous window (the browser) with Alt+Tab, then zoom
+#[tauri::command]
+fn simulate_browser_zoom(direction: i32) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Settings, Key, Direction};
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+
+    enigo.key(Key::Alt, Direction::Press).map_err(|e| e.to_string())?;
+    let result = enigo.key(Key::Tab, Direction::Click).map_err(|e| e.to_string());
+    let _ = enigo.key(Key::Alt, Direction::Release);
+    result?;
+
+    // Give the window manager a moment to move focus
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    ctrl_scroll(&mut enigo, direction)?;
+
+    println!("[Gesture] Simulated browser zoom: {}", direction);
+    Ok(())
+}
+
+/// Tauri command to simulate media play/pause
+#[tauri::command]
+fn simulate_media_toggle() -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Settings, Key};
+    
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.key(Key::MediaPlayPause, enigo::Direction::Click).map_err(|e| e.to_string())?;
+    
+    println!("[Gesture] Simulated Play/Pause");
+    Ok(())
+}
+
+/// Press `modifiers` in order, click `key`, then release the modifiers in reverse
+fn send_key_combo(modifiers: &[enigo::Key], key: enigo::Key) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Settings, Direction};
+
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+
+    for modifier in modifiers {
+        enigo.key(*modifier, Direction::Press).map_err(|e| e.to_string())?;
+    }
+    let result = enigo.key(key, Direction::Click).map_err(|e| e.to_string());
+    // Always release modifiers, even if the main key failed, so none stay stuck
+    for modifier in modifiers.iter().rev() {
+        let _ = enigo.key(*modifier, Direction::Release);
+    }
+
+    result
+}
+
+/// Map a modifier name from a key combo to an enigo key
+fn parse_modifier(name: &str) -> Option<enigo::Key> {
+    use enigo::Key;
+
+    match name {
+        "ctrl" | "control" => Some(Key::Control),
+        "shift" => Some(Key::Shift),
+        "alt" | "option" => Some(Key::Alt),
+        "super" | "meta" | "cmd" | "command" | "win" => Some(Key::Meta),
+        _ => None,
+    }
+}
+
+/// Map a key name from a key combo to an enigo key
+fn parse_key(name: &str) -> Result<enigo::Key, String> {
+    use enigo::Key;
+
+    let key = match name {
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "escape" | "esc" => Key::Escape,
+        "delete" | "del" => Key::Delete,
+        "backspace" => Key::Backspace,
+        "space" => Key::Space,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => Key::Unicode(c),
+                // A lone modifier, e.g. tapping "super" to open the start menu
+                _ => parse_modifier(name).ok_or_else(|| {
+                    format!(
+                        "Unknown key: {} (expected a-z, 0-9, f1-f12, enter, tab, escape, delete, \
+                         backspace, space, home, end, pageup, pagedown or an arrow)",
+                        name
+                    )
+                })?,
+            }
+        }
+    };
+    Ok(key)
+}
 
-/// Tauri command to set grab state
-#[tauri::command]
-async fn set_grab(
-    state: tauri::State<'_, SharedDiscoveryState>,
-    filename: String,
-) -> Result<(), String> {
-    let mut discovery = state.write().await;
-    discovery.set_grab(&filename);
-    Ok(())
+/// Split a combo such as "ctrl+shift+t" into its modifiers and final key
+fn parse_key_combo(combo: &str) -> Result<(Vec<enigo::Key>, enigo::Key), String> {
+    let parts: Vec<String> = combo.split('+').map(|part| part.trim().to_ascii_lowercase()).collect();
+    let Some((key, modifiers)) = parts.split_last().filter(|(key, _)| !key.is_empty()) else {
+        return Err(format!("Missing key in combo: {:?}", combo));
+    };
+
+    let modifiers = modifiers
+        .iter()
+        .map(|name| {
+            parse_modifier(name).ok_or_else(|| {
+                format!("Unknown modifier: {} (expected ctrl, shift, alt, super, meta or cmd)", name)
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((modifiers, parse_key(key)?))
 }
 
-/// Tauri command to clear grab state
+/// Tauri command to press a key combo such as "ctrl+shift+t" or "super+l"
 #[tauri::command]
-async fn clear_grab(state: tauri::State<'_, SharedDiscoveryState>) -> Result<(), String> {
-    let mut discovery = state.write().await;
-    discovery.clear_grab();
+fn simulate_keypress(key_combo: String) -> Result<(), String> {
+    let (modifiers, key) = parse_key_combo(&key_combo)?;
+    send_key_combo(&modifiers, key)?;
+
+    println!("[Gesture] Simulated keypress: {}", key_combo);
     Ok(())
 }
 
-/// Tauri command to download a file
+/// Tauri command to switch to the virtual desktop on the left or right
 #[tauri::command]
-async fn download_file(url: String, dest_path: String) -> Result<String, String> {
-    server::download_file(&url, &dest_path).await?;
-    Ok(dest_path)
+fn simulate_switch_virtual_desktop(direction: String) -> Result<(), String> {
+    use enigo::Key;
+
+    let left = match direction.as_str() {
+        "left" => true,
+        "right" => false,
+        _ => return Err(format!("Invalid direction: {} (expected left or right)", direction)),
+    };
+
+    #[cfg(target_os = "windows")]
+    let (modifiers, key) = (
+        [Key::Control, Key::Meta],
+        if left { Key::LeftArrow } else { Key::RightArrow },
+    );
+    #[cfg(target_os = "macos")]
+    let (modifiers, key) = (
+        [Key::Control],
+        if left { Key::LeftArrow } else { Key::RightArrow },
+    );
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let (modifiers, key) = (
+        [Key::Meta],
+        if left { Key::PageUp } else { Key::PageDown },
+    );
+
+    send_key_combo(&modifiers, key)?;
+
+    println!("[Gesture] Switched virtual desktop {}", direction);
+    Ok(())
 }
 
-/// Tauri command to get local device info
+/// Tauri command to show all windows (Mission Control / Task View / Activities)
 #[tauri::command]
-async fn get_device_info(
-    state: tauri::State<'_, SharedDiscoveryState>,
-) -> Result<serde_json::Value, String> {
-    let discovery = state.read().await;
-    Ok(serde_json::json!({
-        "id": discovery.device_id,
-        "name": discovery.device_name,
-        "ip": discovery.local_ip
-    }))
+fn simulate_expose() -> Result<(), String> {
+    use enigo::Key;
+
+    #[cfg(target_os = "macos")]
+    send_key_combo(&[], Key::MissionControl)?;
+    #[cfg(target_os = "windows")]
+    send_key_combo(&[Key::Meta], Key::Tab)?;
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    send_key_combo(&[], Key::Meta)?;
+
+    println!("[Gesture] Opened window overview");
+    Ok(())
 }
 
-/// Tauri command to manually connect to a peer by IP (for hotspot fallback)
+/// Tauri command to open Task View (Win+Tab); other platforms use their window overview
 #[tauri::command]
-async fn manual_connect(
-    state: tauri::State<'_, SharedDiscoveryState>,
-    ip: String,
-    app_handle: tauri::AppHandle,
-) -> Result<String, String> {
-    let peer = Peer {
-        id: format!("manual-{}", ip),
-        ip: ip.clone(),
-        name: format!("Device at {}", ip),
-        is_holding: false,
-        held_file: String::new(),
-    };
-    
+fn simulate_task_view() -> Result<(), String> {
+    #[cfg(target_os = "windows")]
     {
-        let mut discovery = state.write().await;
-        discovery.peers.insert(peer.id.clone(), peer.clone());
+        send_key_combo(&[enigo::Key::Meta], enigo::Key::Tab)?;
+        println!("[Gesture] Opened Task View");
+        Ok(())
     }
-    
-    let _ = app_handle.emit("peer-discovered", &peer);
-    println!("[Discovery] Manual connect: {}", ip);
-    Ok(ip)
+    #[cfg(not(target_os = "windows"))]
+    simulate_expose()
 }
 
-/// Tauri command to toggle click-through mode
+/// Tauri command to hide all windows and show the desktop
 #[tauri::command]
-async fn set_click_through(window: tauri::Window, enabled: bool) -> Result<(), String> {
-    window
-        .set_ignore_cursor_events(enabled)
-        .map_err(|e| e.to_string())?;
-    println!("[Phantom] Click-through: {}", enabled);
+fn simulate_show_desktop() -> Result<(), String> {
+    use enigo::Key;
+
+    #[cfg(target_os = "macos")]
+    send_key_combo(&[Key::Function], Key::F11)?;
+    #[cfg(not(target_os = "macos"))]
+    send_key_combo(&[Key::Meta], Key::Unicode('d'))?;
+
+    println!("[Gesture] Showed desktop");
     Ok(())
 }
 
-/// Tauri command to enter Phantom Mode (transparent overlay)
+/// Tauri command to move the real OS cursor to screen coordinates
+///
+/// The point must lie on one of the connected monitors.
 #[tauri::command]
-async fn enter_phantom_mode(window: tauri::Window) -> Result<(), String> {
-    println!("[Phantom] Entering Phantom Mode...");
-    
-    // Set fullscreen
-    window.set_fullscreen(true).map_err(|e| e.to_string())?;
-    
-    // Remove decorations
-    window.set_decorations(false).map_err(|e| e.to_string())?;
-    
-    // Always on top
-    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+fn simulate_mouse_move(window: tauri::Window, x: i32, y: i32) -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings, Coordinate};
+
+    ensure_on_monitor(&window, x, y)?;
     
-    // Enable click-through
-    window.set_ignore_cursor_events(true).map_err(|e| e.to_string())?;
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
     
-    println!("[Phantom] Mode activated!");
     Ok(())
 }
 
-/// Tauri command to exit Phantom Mode (back to windowed)
-#[tauri::command]
-async fn exit_phantom_mode(window: tauri::Window) -> Result<(), String> {
-    println!("[Phantom] Exiting Phantom Mode...");
-    
-    // Disable click-through first
-    window.set_ignore_cursor_events(false).map_err(|e| e.to_string())?;
-    
-    // Exit fullscreen
-    window.set_fullscreen(false).map_err(|e| e.to_string())?;
-    
-    // Restore decorations
-    window.set_decorations(true).map_err(|e| e.to_string())?;
-    
-    // Not always on top
-    window.set_always_on_top(false).map_err(|e| e.to_string())?;
-    
-    // Resize to dashboard size
-    let _ = window.set_size(tauri::LogicalSize::new(1000.0, 700.0));
-    let _ = window.center();
-    
-    println!("[Phantom] Back to Dashboard mode");
+/// Reject screen coordinates outside every connected monitor
+fn ensure_on_monitor(window: &tauri::Window, x: i32, y: i32) -> Result<(), String> {
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    // Some platforms can't enumerate monitors; don't block the cursor there
+    if !monitors.is_empty() && !monitors.iter().any(|monitor| monitor_contains(monitor, x, y)) {
+        return Err(format!("({}, {}) is not on any monitor", x, y));
+    }
     Ok(())
 }
 
-/// Tauri command to simulate a mouse click at current cursor position
+/// Largest relative cursor step accepted per call, to prevent runaway movement
+const MAX_RELATIVE_MOVE: i32 = 4000;
+
+/// Tauri command to move the real OS cursor by a relative offset
 #[tauri::command]
-fn simulate_click() -> Result<(), String> {
-    use enigo::{Enigo, Mouse, Settings, Button};
-    
+fn simulate_mouse_move_relative(dx: i32, dy: i32) -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings, Coordinate};
+
+    // The limit applies to the step actually taken, after sensitivity scaling
+    let (dx, dy) = (scale_by_sensitivity(dx), scale_by_sensitivity(dy));
+    // `unsigned_abs` because `i32::MIN.abs()` overflows
+    if dx.unsigned_abs() > MAX_RELATIVE_MOVE as u32 || dy.unsigned_abs() > MAX_RELATIVE_MOVE as u32 {
+        return Err(format!(
+            "Relative move ({}, {}) exceeds ±{} pixels",
+            dx, dy, MAX_RELATIVE_MOVE
+        ));
+    }
+
     let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    enigo.button(Button::Left, enigo::Direction::Click).map_err(|e| e.to_string())?;
-    
-    println!("[Gesture] Simulated click");
+    enigo.move_mouse(dx, dy, Coordinate::Rel).map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
-/// Tauri command to simulate scroll
-#[tauri::command]
-fn simulate_scroll(direction: i32) -> Result<(), String> {
-    use enigo::{Enigo, Mouse, Settings, Axis};
-    
+/// Map "left", "right" or "middle" to an enigo button
+fn parse_mouse_button(button: &str) -> Result<enigo::Button, String> {
+    match button.to_ascii_lowercase().as_str() {
+        "left" => Ok(enigo::Button::Left),
+        "right" => Ok(enigo::Button::Right),
+        "middle" => Ok(enigo::Button::Middle),
+        _ => Err(format!("Unknown mouse button: {} (expected left, right or middle)", button)),
+    }
+}
+
+/// Press or release `button` at the current cursor position
+fn set_mouse_button(button: &str, direction: enigo::Direction) -> Result<(), String> {
+    use enigo::{Enigo, Mouse, Settings};
+
+    let button = parse_mouse_button(button)?;
     let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    // Positive = scroll up, Negative = scroll down
-    enigo.scroll(direction, Axis::Vertical).map_err(|e| e.to_string())?;
-    
-    println!("[Gesture] Simulated scroll: {}", direction);
-    Ok(())
+    enigo.button(button, direction).map_err(|e| e.to_string())
 }
 
-/// Tauri command to simulate media play/pause
+/// Tauri command to press and hold a mouse button ("left", "right", "middle")
+///
+/// Drags are composed as `simulate_mouse_down`, relative moves, then
+/// `simulate_mouse_up`; the button stays down until released.
 #[tauri::command]
-fn simulate_media_toggle() -> Result<(), String> {
-    use enigo::{Enigo, Keyboard, Settings, Key};
-    
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    enigo.key(Key::MediaPlayPause, enigo::Direction::Click).map_err(|e| e.to_string())?;
-    
-    println!("[Gesture] Simulated Play/Pause");
+fn simulate_mouse_down(button: String) -> Result<(), String> {
+    set_mouse_button(&button, enigo::Direction::Press)?;
+    println!("[Gesture] Mouse {} down", button);
     Ok(())
 }
 
-/// Tauri command to move the real OS cursor to screen coordinates
+/// Tauri command to release a mouse button pressed by `simulate_mouse_down`
 #[tauri::command]
-fn simulate_mouse_move(x: i32, y: i32) -> Result<(), String> {
-    use enigo::{Enigo, Mouse, Settings, Coordinate};
-    
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-    enigo.move_mouse(x, y, Coordinate::Abs).map_err(|e| e.to_string())?;
-    
+fn simulate_mouse_up(button: String) -> Result<(), String> {
+    set_mouse_button(&button, enigo::Direction::Release)?;
+    println!("[Gesture] Mouse {} up", button);
     Ok(())
 }
 
-/// Tauri command to get screen dimensions
+/// Geometry of one monitor, in physical pixels on the virtual desktop
+#[derive(Debug, Clone, serde::Serialize)]
+struct MonitorInfo {
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    scale_factor: f64,
+    is_primary: bool,
+}
+
+/// Whether the physical point (x, y) lies on `monitor`
+fn monitor_contains(monitor: &tauri::Monitor, x: i32, y: i32) -> bool {
+    let (position, size) = (monitor.position(), monitor.size());
+    let (dx, dy) = (x as i64 - position.x as i64, y as i64 - position.y as i64);
+    (0..size.width as i64).contains(&dx) && (0..size.height as i64).contains(&dy)
+}
+
+/// Tauri command to describe every connected monitor
 #[tauri::command]
-fn get_screen_size() -> Result<(i32, i32), String> {
-    // Return typical screen size - enigo doesn't have screen size API
-    // Frontend will use window.screen.width/height instead
-    Ok((1920, 1080))
+fn get_screen_size(window: tauri::Window) -> Result<Vec<MonitorInfo>, String> {
+    let primary = window.primary_monitor().map_err(|e| e.to_string())?;
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+
+    Ok(monitors
+        .iter()
+        .map(|monitor| MonitorInfo {
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            // Monitors have no ID, but no two share an origin on the virtual desktop
+            is_primary: primary
+                .as_ref()
+                .is_some_and(|primary| primary.position() == monitor.position()),
+        })
+        .collect())
+}
+
+/// Tauri command to get the primary monitor's size in physical pixels
+#[tauri::command]
+fn get_primary_screen_size(window: tauri::Window) -> Result<(u32, u32), String> {
+    let monitor = window
+        .primary_monitor()
+        .map_err(|e| e.to_string())?
+        .ok_or("No primary monitor found")?;
+    Ok((monitor.size().width, monitor.size().height))
 }
 
 /// Tauri command to get AirShare Downloads folder path
@@ -210,60 +1678,262 @@ fn get_airshare_downloads() -> Result<String, String> {
         .ok_or("Invalid path".to_string())
 }
 
-/// Tauri command to save received file bytes to disk
+/// Tauri command to list the files in the AirShare Downloads folder
 #[tauri::command]
-fn save_received_file(filename: String, data: Vec<u8>) -> Result<String, String> {
-    let downloads_dir = dirs::download_dir()
-        .ok_or("Could not find Downloads directory")?;
-    
-    let airshare_dir = downloads_dir.join("AirShare_Downloads");
-    
-    // Create directory if it doesn't exist
-    if !airshare_dir.exists() {
-        std::fs::create_dir_all(&airshare_dir)
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
+async fn list_airshare_downloads() -> Result<Vec<server::FileInfo>, String> {
+    let dir = get_airshare_downloads()?;
+    server::list_dir_files(std::path::Path::new(&dir), false)
+        .await
+        .map_err(|e| format!("Failed to list downloads: {}", e))
+}
+
+/// Tauri command to save an image on the clipboard (e.g. a PrintScreen
+/// capture) as a PNG in AirShare_Downloads
+///
+/// Returns the path of the PNG, or `None` if the clipboard holds no image.
+#[tauri::command]
+async fn get_clipboard_image() -> Result<Option<String>, String> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = std::path::Path::new(&get_airshare_downloads()?)
+        .join(format!("clipboard_{}.png", timestamp));
+
+    let dest = path.clone();
+    let saved = tokio::task::spawn_blocking(move || smart_drop::save_clipboard_image(&dest))
+        .await
+        .map_err(|e| format!("Clipboard task failed: {}", e))??;
+    if !saved {
+        return Ok(None);
     }
-    
-    let file_path = airshare_dir.join(&filename);
-    
-    std::fs::write(&file_path, &data)
+
+    println!("[SmartDrop] 📋 Clipboard image saved to {}", path.display());
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Resolve `path`, following symlinks, and refuse it unless it lies inside
+/// AirShare_Downloads
+///
+/// A path that doesn't exist yet is resolved through its parent directory.
+/// A symlink that can't be resolved (e.g. dangling) is refused, since writing
+/// to it would create its target wherever that is.
+async fn resolve_in_downloads(path: &std::path::Path) -> Result<std::path::PathBuf, String> {
+    let downloads = tokio::fs::canonicalize(get_airshare_downloads()?)
+        .await
+        .map_err(|e| format!("Failed to resolve AirShare_Downloads: {}", e))?;
+
+    let resolved = match tokio::fs::canonicalize(path).await {
+        Ok(resolved) => resolved,
+        Err(_) if tokio::fs::symlink_metadata(path).await.is_err() => {
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(format!("Invalid path: {:?}", path));
+            };
+            tokio::fs::canonicalize(parent)
+                .await
+                .map_err(|e| format!("Failed to resolve {:?}: {}", parent, e))?
+                .join(name)
+        }
+        Err(e) => return Err(format!("Failed to resolve {:?}: {}", path, e)),
+    };
+
+    if !resolved.starts_with(&downloads) {
+        return Err(format!("{:?} is outside AirShare_Downloads", path));
+    }
+    Ok(resolved)
+}
+
+/// Write received bytes into AirShare_Downloads, returning the path used
+async fn write_received_file(
+    strategy: config::CollisionStrategy,
+    filename: &str,
+    data: &[u8],
+) -> Result<std::path::PathBuf, String> {
+    if !server::is_plain_filename(filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+
+    let airshare_dir = std::path::PathBuf::from(get_airshare_downloads()?);
+    let file_path = server::resolve_collision(&airshare_dir, filename, strategy)
+        .ok_or_else(|| format!("File already exists: {}", filename))?;
+    let file_path = resolve_in_downloads(&file_path).await?;
+
+    tokio::fs::write(&file_path, data)
+        .await
         .map_err(|e| format!("Failed to write file: {}", e))?;
-    
+
     println!("[Files] Saved: {:?} ({} bytes)", file_path, data.len());
-    
-    file_path.to_str()
-        .map(|s| s.to_string())
-        .ok_or("Invalid path".to_string())
+    Ok(file_path)
+}
+
+/// Tauri command to save received file bytes to disk
+///
+/// Name clashes follow the configured `file_collision_strategy`; returns the
+/// path actually written. The sender, when given, is logged in the history.
+#[tauri::command]
+async fn save_received_file(
+    state: tauri::State<'_, SharedServerState>,
+    filename: String,
+    data: Vec<u8>,
+    peer_name: Option<String>,
+    peer_id: Option<String>,
+) -> Result<String, String> {
+    let started_at = history::now_ms();
+    let result = write_received_file(state.config.file_collision_strategy, &filename, &data)
+        .await
+        .and_then(|path| path.to_str().map(|s| s.to_string()).ok_or("Invalid path".to_string()));
+
+    let filename = std::path::Path::new(result.as_deref().unwrap_or(&filename))
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or(filename);
+    if result.is_ok() {
+        state.emit(
+            "file-received",
+            serde_json::json!({
+                "filename": filename,
+                "size": data.len(),
+                "sender_name": peer_name,
+            }),
+        );
+    }
+
+    let record = history::TransferRecord {
+        filename,
+        size_bytes: data.len() as u64,
+        peer_name,
+        peer_id,
+        direction: history::Direction::Received,
+        started_at,
+        status: history::TransferStatus::of(&result),
+        ..Default::default()
+    };
+    if let Err(e) = history::record(record) {
+        eprintln!("[History] {}", e);
+    }
+    result
+}
+
+/// Tauri command to set the largest upload the server accepts in one request
+///
+/// Applies to the next upload and is persisted in the config.
+#[tauri::command]
+fn set_max_upload_size(
+    state: tauri::State<'_, SharedServerState>,
+    bytes: u64,
+) -> Result<(), String> {
+    if bytes == 0 {
+        return Err("Upload size limit must be greater than 0".to_string());
+    }
+
+    AirShareConfig::update(|config| config.max_upload_size_bytes = bytes)?;
+    state.max_upload_size_bytes.store(bytes, Ordering::Relaxed);
+
+    println!("[Server] Max upload size: {} bytes", bytes);
+    Ok(())
+}
+
+/// Tauri command to restrict uploads to the given file extensions
+///
+/// An empty list accepts any extension. Persisted in the config.
+#[tauri::command]
+async fn set_upload_allow_list(
+    state: tauri::State<'_, SharedServerState>,
+    extensions: Vec<String>,
+) -> Result<(), String> {
+    let extensions = server::normalize_extensions(&extensions);
+    AirShareConfig::update(|config| config.allowed_upload_extensions = extensions.clone())?;
+
+    if extensions.is_empty() {
+        println!("[Server] Upload allow-list cleared, any file type accepted");
+    } else {
+        println!("[Server] Upload allow-list: {}", extensions.join(", "));
+    }
+    *state.allowed_extensions.write().await = extensions;
+    Ok(())
 }
 
-/// Tauri command to read a file from disk
+/// Tauri command to get the upload allow-list (empty = any extension)
 #[tauri::command]
-fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
-    std::fs::read(&path)
+async fn get_upload_allow_list(
+    state: tauri::State<'_, SharedServerState>,
+) -> Result<Vec<String>, String> {
+    Ok(state.allowed_extensions.read().await.clone())
+}
+
+/// Tauri command to read a file from AirShare_Downloads
+#[tauri::command]
+async fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
+    let path = resolve_in_downloads(std::path::Path::new(&path)).await?;
+    tokio::fs::read(&path)
+        .await
         .map_err(|e| format!("Failed to read file: {}", e))
 }
 
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let discovery_state: SharedDiscoveryState = Arc::new(RwLock::new(DiscoveryState::new()));
-    let server_state: SharedServerState = Arc::new(ServerState::new());
+    // Shows request spans from the server, e.g. with RUST_LOG=tauri_app_lib=debug
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .try_init();
+
+    let config = AirShareConfig::load();
+    let mut discovery = DiscoveryState::new(config.discovery_port, config.server_port);
+    if let Some(ip) = &config.preferred_interface {
+        // The interface may be gone (e.g. a different network); keep the default then
+        match discovery::find_interface(ip) {
+            Ok(ip) => discovery.set_preferred_ip(ip),
+            Err(e) => eprintln!("[Discovery] Ignoring preferred interface: {}", e),
+        }
+    }
+    let discovery_state: SharedDiscoveryState = Arc::new(RwLock::new(discovery));
+    let sensitivity = config
+        .gesture_sensitivity
+        .clamp(MIN_GESTURE_SENSITIVITY, MAX_GESTURE_SENSITIVITY);
+    GESTURE_SENSITIVITY.store(sensitivity.to_bits(), Ordering::Relaxed);
+    notifications::init(config.notifications_enabled);
+    let phantom_restore: SharedPhantomRestore =
+        Arc::new(std::sync::Mutex::new(config.dashboard_window));
+    let phantom_hotkey = config.phantom_hotkey.clone();
+    let server_state: SharedServerState =
+        Arc::new(ServerState::new(config, discovery_state.clone()));
+    let transfer_queue: SharedTransferQueue = Arc::new(TransferQueue::new());
+    let transfer_state: SharedTransferState = Arc::new(TransferState::new());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, _shortcut, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        if let Err(e) = toggle_phantom_mode(app) {
+                            eprintln!("[Phantom] Hotkey failed: {}", e);
+                        }
+                    }
+                })
+                .build(),
+        )
         .manage(discovery_state.clone())
         .manage(server_state.clone())
+        .manage(transfer_queue.clone())
+        .manage(transfer_state)
+        .manage(smart_drop::ClipboardAutoUpload::default())
+        .manage(phantom_restore)
         .setup(move |app| {
             let app_handle = app.handle().clone();
-            let discovery_for_beacon = discovery_state.clone();
-            let discovery_for_listener = discovery_state.clone();
 
             // === SYSTEM TRAY ===
             let quit_item = MenuItem::with_id(app, "quit", "Quit AirShare", true, None::<&str>)?;
-            let status_item = MenuItem::with_id(app, "status", "Status: Scanning...", false, None::<&str>)?;
+            let status_item = MenuItem::with_id(app, "status", TRAY_STATUS_SCANNING, false, None::<&str>)?;
             let toggle_item = MenuItem::with_id(app, "toggle", "Toggle Click-Through", true, None::<&str>)?;
+            let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, None::<&str>)?;
             
-            let menu = Menu::with_items(app, &[&status_item, &toggle_item, &quit_item])?;
+            let menu = Menu::with_items(app, &[&status_item, &toggle_item, &settings_item, &quit_item])?;
 
             let _tray = TrayIconBuilder::new()
                 .icon(app.default_window_icon().unwrap().clone())
@@ -273,6 +1943,8 @@ pub fn run() {
                     match event.id.as_ref() {
                         "quit" => {
                             println!("[Tray] Quit requested");
+                            let server = app.state::<SharedServerState>().inner().clone();
+                            tauri::async_runtime::block_on(server::shutdown_server(&server));
                             app.exit(0);
                         }
                         "toggle" => {
@@ -281,6 +1953,11 @@ pub fn run() {
                                 let _ = window.set_ignore_cursor_events(true);
                             }
                         }
+                        "settings" => {
+                            if let Err(e) = toggle_settings(app) {
+                                eprintln!("[Tray] {}", e);
+                            }
+                        }
                         _ => {}
                     }
                 })
@@ -302,6 +1979,34 @@ pub fn run() {
 
             println!("[Phantom] System tray created");
 
+            app.manage::<TrayStatusItem>(Arc::new(std::sync::Mutex::new(status_item)));
+            // Every way a peer can appear or go away ends in one of these events
+            for event in ["peer-discovered", "peer-lost"] {
+                let app_handle = app_handle.clone();
+                app.listen_any(event, move |_| refresh_tray_status(&app_handle));
+            }
+
+            // === Phantom Mode hotkey ===
+            let hotkey = phantom_hotkey.as_deref().and_then(|shortcut| {
+                let parsed = parse_hotkey(shortcut).and_then(|parsed| {
+                    app.global_shortcut()
+                        .register(parsed)
+                        .map(|_| parsed)
+                        .map_err(|e| format!("Failed to register {}: {}", shortcut, e))
+                });
+                match parsed {
+                    Ok(parsed) => {
+                        println!("[Phantom] Hotkey: {}", shortcut);
+                        Some(parsed)
+                    }
+                    Err(e) => {
+                        eprintln!("[Phantom] {}", e);
+                        None
+                    }
+                }
+            });
+            app.manage::<SharedPhantomHotkey>(Arc::new(std::sync::Mutex::new(hotkey)));
+
             // === Enable click-through by default ===
             if let Some(window) = app.get_webview_window("main") {
                 // Start with click-through DISABLED so user can interact initially
@@ -310,49 +2015,257 @@ pub fn run() {
             }
 
             // === Background Services ===
-            tauri::async_runtime::spawn(async move {
-                start_beacon(discovery_for_beacon).await;
+            let app_handle_clone = app_handle.clone();
+            let on_peer: PeerCallback = Arc::new(move |peer: Peer, event: PeerEvent| {
+                match event {
+                    // A leaving beacon removes the card just like a peer going quiet
+                    PeerEvent::Left | PeerEvent::Lost => {
+                        let _ = app_handle_clone.emit("peer-lost", &peer.id);
+                        return;
+                    }
+                    PeerEvent::Discovered | PeerEvent::GrabUpdate => {}
+                }
+
+                if peer.is_holding && server::is_plain_filename(&peer.held_file) {
+                    if let Some(data) = discovery::decode_inline_data(&peer) {
+                        let app_handle = app_handle_clone.clone();
+                        let peer = peer.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let server_state = app_handle.state::<SharedServerState>();
+                            if let Err(e) = save_received_file(
+                                server_state,
+                                peer.held_file,
+                                data,
+                                Some(peer.name),
+                                Some(peer.id),
+                            )
+                            .await
+                            {
+                                eprintln!("[Discovery] Failed to save inline file: {}", e);
+                            }
+                        });
+                    }
+                }
+
+                if event == PeerEvent::GrabUpdate {
+                    if peer.is_holding {
+                        notifications::peer_holding(&app_handle_clone, &peer);
+                    }
+                    let _ = app_handle_clone.emit("grab-update", &peer);
+                } else {
+                    let _ = app_handle_clone.emit("peer-discovered", &peer);
+                }
             });
 
-            let app_handle_clone = app_handle.clone();
+            let app_handle_for_port = app_handle.clone();
+            let on_port_changed = Arc::new(move |port: u16| {
+                let _ = app_handle_for_port.emit("discovery-port-changed", port);
+            });
+
+            let registry: SharedDiscoveryRegistry =
+                Arc::new(DiscoveryRegistry::new(discovery_state.clone(), on_peer));
+            let enabled = &server_state.config.discovery_backends;
+            let backends: [Arc<dyn DiscoveryBackend>; 3] = [
+                Arc::new(UdpBroadcastBackend::new(on_port_changed)),
+                Arc::new(MdnsBackend),
+                Arc::new(TcpFallbackBackend),
+            ];
+            for backend in backends {
+                let is_enabled = enabled.iter().any(|name| name == backend.name());
+                registry.register(backend, is_enabled);
+            }
+            app.manage(registry);
+
+            let mut server_events = server_state.events.subscribe();
+            let app_handle_for_server = app_handle.clone();
             tauri::async_runtime::spawn(async move {
-                start_listener(discovery_for_listener, move |peer: Peer, is_grab_update: bool| {
-                    if is_grab_update {
-                        let _ = app_handle_clone.emit("grab-update", &peer);
-                    } else {
-                        let _ = app_handle_clone.emit("peer-discovered", &peer);
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match server_events.recv().await {
+                        Ok(event) => {
+                            if event.name == "file-received" {
+                                notifications::file_received(&app_handle_for_server, &event.payload);
+                            }
+                            let _ = app_handle_for_server.emit(event.name, event.payload);
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            eprintln!("[Server] Dropped {} events", missed);
+                        }
+                        Err(RecvError::Closed) => break,
                     }
-                })
-                .await;
+                }
             });
 
+            let server_for_ipc = server_state.clone();
+            if server_state.config.use_quic {
+                let server_for_quic = server_state.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = server::start_quic_server(server_for_quic, server::QUIC_PORT).await {
+                        eprintln!("[QUIC] {}", e);
+                    }
+                });
+            }
             tauri::async_runtime::spawn(async move {
                 start_server(server_state).await;
             });
 
+            tauri::async_runtime::spawn(async move {
+                ipc::start_ipc_server(server_for_ipc, &ipc::default_socket_path()).await;
+            });
+
+            let discovery_for_transfers = discovery_state.clone();
+            tauri::async_runtime::spawn(async move {
+                transfers::run_transfer_worker(transfer_queue, discovery_for_transfers).await;
+            });
+
+            // Favorites are connected by IP, as discovery may never see them
+            let discovery_for_favorites = discovery_state.clone();
+            let app_handle_for_favorites = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                for favorite in favorites::load() {
+                    if let Err(e) = connect_manual_peer(
+                        &discovery_for_favorites,
+                        &app_handle_for_favorites,
+                        &favorite.ip,
+                        Some(&favorite.name),
+                    )
+                    .await
+                    {
+                        eprintln!("[Favorites] Skipped {}: {}", favorite.ip, e);
+                    }
+                }
+            });
+
             println!("[AirShare] Phantom UI engine started!");
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             set_grab,
             clear_grab,
+            set_grab_text,
+            send_text,
+            get_received_text,
             download_file,
+            transfers::download_file_priority,
+            transfers::get_transfer_queue_ordered,
+            transfers::cancel_transfer,
+            transfer_requests::accept_transfer,
+            transfer_requests::reject_transfer,
+            check_peer_has_file,
+            check_peer_file_hash,
+            upload_file_to_peer,
+            patch_file_range,
+            get_file_metadata,
+            copy_shared_file,
+            set_file_acl,
+            clear_file_acl,
+            list_files_recursive,
+            benchmark_zstd_vs_plain,
             get_device_info,
+            get_device_name,
+            set_device_name,
+            get_discovery_port,
+            stop_discovery,
+            reset_device_id,
+            discovery_backends::register_discovery_backend,
             manual_connect,
+            favorites::add_favorite,
+            favorites::remove_favorite,
+            favorites::get_favorites,
+            get_peers,
+            list_network_interfaces,
+            set_preferred_interface,
+            remove_peer,
+            ping_peer_udp,
+            measure_bandwidth_to_peer,
+            set_pin,
+            connections::get_server_connections,
+            config::get_config,
+            config::set_config,
             set_click_through,
             enter_phantom_mode,
+            set_phantom_hotkey,
             exit_phantom_mode,
+            open_settings_window,
+            close_settings_window,
+            toggle_settings_window,
+            snap_window,
+            snap_to_monitor,
+            set_window_opacity,
+            get_window_opacity,
             simulate_click,
+            simulate_click_at,
+            simulate_right_click_at,
+            simulate_double_click_at,
+            simulate_long_press,
+            is_pressing,
             simulate_scroll,
+            simulate_scroll_horizontal,
+            simulate_keypress,
+            set_gesture_sensitivity,
+            get_gesture_sensitivity,
+            simulate_inertia_scroll,
+            stop_inertia_scroll,
+            simulate_zoom,
+            simulate_browser_zoom,
             simulate_media_toggle,
+            audio::list_audio_devices,
+            audio::set_default_audio_device,
+            simulate_switch_virtual_desktop,
+            simulate_expose,
+            simulate_task_view,
+            simulate_show_desktop,
             simulate_mouse_move,
+            simulate_mouse_move_relative,
+            simulate_mouse_down,
+            simulate_mouse_up,
             get_screen_size,
+            get_primary_screen_size,
             get_airshare_downloads,
+            list_airshare_downloads,
             save_received_file,
+            set_max_upload_size,
+            set_upload_allow_list,
+            get_upload_allow_list,
             read_file_bytes,
             smart_drop::simulate_copy,
-            smart_drop::simulate_paste
+            smart_drop::simulate_paste,
+            smart_drop::simulate_cut,
+            smart_drop::get_clipboard_files_full_paths,
+            get_clipboard_image,
+            smart_drop::enable_clipboard_auto_upload,
+            smart_drop::disable_clipboard_auto_upload,
+            smart_drop::clear_clipboard,
+            smart_drop::stage_file_for_sharing,
+            smart_drop::simulate_print_screen,
+            smart_drop::simulate_snipping_tool,
+            history::add_transfer_tag,
+            history::get_transfers_by_tag,
+            history::get_transfer_history,
+            history::clear_transfer_history,
+            archive::set_grab_folder,
+            archive::extract_zip,
+            mobile::generate_connection_qr,
+            notifications::set_notifications_enabled,
+            ipc::transfer_via_ipc,
+            transfer_via_quic,
+            rooms::create_room,
+            rooms::join_room,
+            rooms::close_room,
+            #[cfg(feature = "webrtc")]
+            rtc_transfer::initiate_webrtc_transfer
         ])
+        .on_window_event(|window, event| {
+            // Let background tasks finish before the process exits
+            if matches!(event, tauri::WindowEvent::Destroyed) && window.label() == MAIN_WINDOW {
+                let state = window.state::<SharedDiscoveryState>().inner().clone();
+                if let Some(registry) = window.try_state::<SharedDiscoveryRegistry>() {
+                    tauri::async_runtime::block_on(shutdown_discovery(&state, &registry));
+                }
+                // Don't leave a held folder's zip behind in the shared folder
+                tauri::async_runtime::block_on(state.write()).discard_held_archive();
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
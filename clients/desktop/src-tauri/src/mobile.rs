@@ -0,0 +1,65 @@
+// Mobile web UI served by the HTTP server
+//
+// Assets are compiled into the binary so the page works regardless of the
+// working directory the app was launched from.
+
+use crate::discovery::SharedDiscoveryState;
+use crate::server::SharedServerState;
+use base64::Engine;
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+
+const MOBILE_HTML: &str = include_str!("../mobile-web/index.html");
+const MOBILE_CSS: &str = include_str!("../mobile-web/mobile.css");
+const ICON_PNG: &[u8] = include_bytes!("../icons/128x128.png");
+
+/// Mobile UI routes, merged into the main server router
+pub fn routes() -> Router<SharedServerState> {
+    Router::new()
+        .route("/mobile", get(handle_mobile_ui))
+        .route("/mobile.css", get(handle_mobile_css))
+        .route("/icon.png", get(handle_icon))
+}
+
+/// GET /mobile
+async fn handle_mobile_ui() -> Html<&'static str> {
+    Html(MOBILE_HTML)
+}
+
+/// GET /mobile.css
+async fn handle_mobile_css() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css")], MOBILE_CSS)
+}
+
+/// GET /icon.png
+async fn handle_icon() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "image/png")], ICON_PNG)
+}
+
+/// Tauri command to get a QR code of the mobile UI URL, as a PNG data URL
+///
+/// Built from the current local IP on every call, so it follows interface changes.
+#[tauri::command]
+pub async fn generate_connection_qr(state: tauri::State<'_, SharedDiscoveryState>) -> Result<String, String> {
+    let url = {
+        let state = state.read().await;
+        format!("{}/mobile", crate::server::peer_base_url(&state.local_ip, state.server_port))
+    };
+
+    let code = qrcode::QrCode::new(url.as_bytes()).map_err(|e| format!("Failed to encode QR: {}", e))?;
+    let image = code.render::<image::Luma<u8>>().min_dimensions(256, 256).build();
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    Ok(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png.into_inner())
+    ))
+}
@@ -0,0 +1,56 @@
+// OS notifications for peers grabbing files and files arriving
+//
+// Shown through tauri-plugin-notification, so the user hears about them while
+// another app has focus. `set_notifications_enabled` turns them all off.
+
+use crate::config::AirShareConfig;
+use crate::discovery::Peer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+/// Whether notifications are shown (on until loaded from config)
+static NOTIFICATIONS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Apply the saved preference at startup
+pub fn init(enabled: bool) {
+    NOTIFICATIONS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn show(app_handle: &AppHandle, title: &str, body: &str) {
+    if !NOTIFICATIONS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Err(e) = app_handle.notification().builder().title(title).body(body).show() {
+        eprintln!("[Notify] Failed to show notification: {}", e);
+    }
+}
+
+/// Tell the user a peer has grabbed a file and is ready to drop it
+pub fn peer_holding(app_handle: &AppHandle, peer: &Peer) {
+    show(
+        app_handle,
+        "File ready to drop",
+        &format!("{} is holding {}", peer.name, peer.held_file),
+    );
+}
+
+/// Tell the user a file was saved, from a `file-received` event payload
+pub fn file_received(app_handle: &AppHandle, payload: &serde_json::Value) {
+    let filename = payload.get("filename").and_then(|v| v.as_str()).unwrap_or("A file");
+    let body = match payload.get("sender_name").and_then(|v| v.as_str()) {
+        Some(sender) => format!("{} from {}", filename, sender),
+        None => filename.to_string(),
+    };
+    show(app_handle, "File received", &body);
+}
+
+/// Tauri command to turn OS notifications on or off, persisted in the config
+#[tauri::command]
+pub fn set_notifications_enabled(enabled: bool) -> Result<(), String> {
+    AirShareConfig::update(|config| config.notifications_enabled = enabled)?;
+    NOTIFICATIONS_ENABLED.store(enabled, Ordering::Relaxed);
+
+    println!("[Notify] Notifications {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
@@ -0,0 +1,117 @@
+// Post-upload processing hooks (logging, virus scanning, ...)
+//
+// Every processor registered in `ServerState::processors` runs on each file
+// accepted by `/upload`. A single `Quarantine` verdict moves the file into
+// `<shared_dir>/.quarantine/` so peers can no longer fetch it.
+
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+/// Directory under the shared folder holding quarantined uploads
+pub const QUARANTINE_DIR: &str = ".quarantine";
+
+/// Verdict of a post-upload processor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessResult {
+    Allow,
+    /// Move the file out of the shared folder, with the reason shown to the user
+    Quarantine(String),
+}
+
+/// Hook run on every uploaded file after it has been written to disk
+#[async_trait]
+pub trait PostUploadProcessor: Send + Sync {
+    async fn process(&self, filename: &str, path: &Path) -> Result<ProcessResult, String>;
+}
+
+/// Built-in processor that only logs each upload
+pub struct LoggingProcessor;
+
+#[async_trait]
+impl PostUploadProcessor for LoggingProcessor {
+    async fn process(&self, filename: &str, path: &Path) -> Result<ProcessResult, String> {
+        let size = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        println!("[Processors] Upload {} ({} bytes)", filename, size);
+        Ok(ProcessResult::Allow)
+    }
+}
+
+/// Scans uploads with the `clamscan` command-line scanner
+#[cfg(feature = "clamav")]
+pub struct ClamAvProcessor;
+
+#[cfg(feature = "clamav")]
+#[async_trait]
+impl PostUploadProcessor for ClamAvProcessor {
+    async fn process(&self, filename: &str, path: &Path) -> Result<ProcessResult, String> {
+        let output = tokio::process::Command::new("clamscan")
+            .arg("--no-summary")
+            .arg(path)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run clamscan: {}", e))?;
+
+        // clamscan exits 0 when clean, 1 when a virus was found, 2 on error
+        match output.status.code() {
+            Some(0) => Ok(ProcessResult::Allow),
+            Some(1) => {
+                let report = String::from_utf8_lossy(&output.stdout);
+                let reason = report
+                    .lines()
+                    .find(|line| line.ends_with("FOUND"))
+                    .and_then(|line| line.rsplit_once(": "))
+                    .map(|(_, signature)| signature.trim_end_matches(" FOUND").to_string())
+                    .unwrap_or_else(|| "Virus found".to_string());
+                println!("[Processors] ClamAV flagged {}: {}", filename, reason);
+                Ok(ProcessResult::Quarantine(reason))
+            }
+            _ => Err(format!(
+                "clamscan failed on {}: {}",
+                filename,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        }
+    }
+}
+
+/// Processors enabled in this build, in the order they run
+pub fn default_processors() -> Vec<Box<dyn PostUploadProcessor>> {
+    #[allow(unused_mut)]
+    let mut processors: Vec<Box<dyn PostUploadProcessor>> = vec![Box::new(LoggingProcessor)];
+    #[cfg(feature = "clamav")]
+    processors.push(Box::new(ClamAvProcessor));
+    processors
+}
+
+/// Run every processor on an upload, stopping at the first quarantine verdict
+///
+/// A processor that errors is logged and skipped, so a missing scanner does
+/// not block every upload.
+pub async fn run_processors(
+    processors: &[Box<dyn PostUploadProcessor>],
+    filename: &str,
+    path: &Path,
+) -> ProcessResult {
+    for processor in processors {
+        match processor.process(filename, path).await {
+            Ok(ProcessResult::Allow) => {}
+            Ok(verdict) => return verdict,
+            Err(e) => eprintln!("[Processors] {}", e),
+        }
+    }
+    ProcessResult::Allow
+}
+
+/// Move an upload into the quarantine directory, returning its new path
+pub async fn quarantine(shared_dir: &Path, filename: &str) -> Result<PathBuf, String> {
+    let dir = shared_dir.join(QUARANTINE_DIR);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create quarantine directory: {}", e))?;
+
+    let dest = dir.join(filename);
+    tokio::fs::rename(shared_dir.join(filename), &dest)
+        .await
+        .map_err(|e| format!("Failed to quarantine {}: {}", filename, e))?;
+    Ok(dest)
+}
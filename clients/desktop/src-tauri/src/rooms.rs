@@ -0,0 +1,359 @@
+// Rooms: isolated, ephemeral file spaces for shared machines
+//
+// Each room gets its own directory under the OS temp dir. Rooms live only
+// in memory and are lost on restart; a room's directory is deleted when it
+// is closed, expires after `ROOM_IDLE_EXPIRY` without requests, or the
+// server shuts down. Only peers whose `X-AirShare-Peer-ID` is in the room's
+// members can use it, and once a PIN is set only with the PIN's token.
+
+use crate::discovery::SharedDiscoveryState;
+use crate::acl::PEER_ID_HEADER;
+use crate::server::{
+    has_path_syntax, has_valid_token, is_plain_filename, list_dir_files, save_field, shared_file_path,
+    SharedServerState, UPLOAD_TOO_LARGE,
+};
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::fs;
+
+/// Rooms with no requests for this long are closed
+const ROOM_IDLE_EXPIRY: Duration = Duration::from_secs(12 * 60 * 60);
+/// How often the server checks for expired rooms
+pub const ROOM_SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// An isolated file space
+#[derive(Debug, Clone, Serialize)]
+pub struct Room {
+    pub id: String,
+    pub name: String,
+    pub shared_dir: PathBuf,
+    /// Device IDs that have joined the room
+    pub members: HashSet<String>,
+    /// Last time a member used the room, for `ROOM_IDLE_EXPIRY`
+    #[serde(skip)]
+    pub last_active: Instant,
+}
+
+/// Body of `POST /rooms`
+#[derive(Debug, Deserialize)]
+struct CreateRoomRequest {
+    name: String,
+}
+
+/// Room routes, merged into the main server router
+pub fn routes() -> Router<SharedServerState> {
+    Router::new()
+        .route("/rooms", post(handle_create_room))
+        .route("/rooms/{id}", axum::routing::delete(handle_close_room))
+        .route("/rooms/{id}/join", post(handle_join_room))
+        .route("/rooms/{id}/files", get(handle_list_files))
+        .route(
+            "/rooms/{id}/upload",
+            post(handle_upload).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/rooms/{id}/file/{filename}", get(handle_serve_file))
+}
+
+/// Create a room and its directory, returning the new room ID
+///
+/// `creator` becomes the room's first member.
+pub async fn create(state: &SharedServerState, name: &str, creator: Option<&str>) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let shared_dir = std::env::temp_dir().join("airshare-rooms").join(&id);
+
+    fs::create_dir_all(&shared_dir)
+        .await
+        .map_err(|e| format!("Failed to create room directory: {}", e))?;
+
+    let room = Room {
+        id: id.clone(),
+        name: name.to_string(),
+        shared_dir,
+        members: creator.map(str::to_string).into_iter().collect(),
+        last_active: Instant::now(),
+    };
+    state.rooms.write().await.insert(id.clone(), room);
+
+    println!("[Rooms] Created room '{}' ({})", name, id);
+    Ok(id)
+}
+
+/// Remove a room and delete its directory
+pub async fn close(state: &SharedServerState, id: &str) -> Result<(), String> {
+    let room = state
+        .rooms
+        .write()
+        .await
+        .remove(id)
+        .ok_or_else(|| format!("Room not found: {}", id))?;
+    remove_room_dir(&room).await;
+    Ok(())
+}
+
+/// Close every room, deleting their directories (rooms don't outlive the server)
+pub async fn close_all(state: &SharedServerState) {
+    let rooms: Vec<Room> = state.rooms.write().await.drain().map(|(_, room)| room).collect();
+    for room in &rooms {
+        remove_room_dir(room).await;
+    }
+}
+
+/// Close rooms nobody has used within `ROOM_IDLE_EXPIRY`
+pub async fn sweep_expired_rooms(state: &SharedServerState) {
+    let expired: Vec<Room> = {
+        let mut rooms = state.rooms.write().await;
+        let ids: Vec<String> = rooms
+            .iter()
+            .filter(|(_, room)| room.last_active.elapsed() >= ROOM_IDLE_EXPIRY)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter().filter_map(|id| rooms.remove(id)).collect()
+    };
+    for room in &expired {
+        println!("[Rooms] Room '{}' ({}) expired", room.name, room.id);
+        remove_room_dir(room).await;
+    }
+}
+
+async fn remove_room_dir(room: &Room) {
+    match fs::remove_dir_all(&room.shared_dir).await {
+        Ok(()) => println!("[Rooms] Closed room '{}' ({})", room.name, room.id),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("[Rooms] Failed to delete {:?}: {}", room.shared_dir, e),
+    }
+}
+
+/// Device ID the request names in `X-AirShare-Peer-ID`
+fn requesting_peer(headers: &HeaderMap) -> Option<&str> {
+    headers.get(PEER_ID_HEADER).and_then(|v| v.to_str().ok())
+}
+
+/// Look up a room's directory for a request, refusing peers that aren't members
+///
+/// Device IDs are broadcast in beacons, so once a PIN is set the request must
+/// also carry the PIN's token.
+async fn member_room_dir(state: &SharedServerState, id: &str, headers: &HeaderMap) -> Result<PathBuf, Response> {
+    if !has_valid_token(state, headers).await {
+        return Err((StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response());
+    }
+    let mut rooms = state.rooms.write().await;
+    let Some(room) = rooms.get_mut(id) else {
+        return Err((StatusCode::NOT_FOUND, format!("Room not found: {}", id)).into_response());
+    };
+    if !requesting_peer(headers).is_some_and(|peer| room.members.contains(peer)) {
+        println!("[Rooms] Rejected request to room {}: not a member", id);
+        return Err((StatusCode::FORBIDDEN, "Peer is not a member of this room").into_response());
+    }
+    room.last_active = Instant::now();
+    Ok(room.shared_dir.clone())
+}
+
+/// POST /rooms
+async fn handle_create_room(
+    State(state): State<SharedServerState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateRoomRequest>,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    let Some(creator) = requesting_peer(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing X-AirShare-Peer-ID").into_response();
+    };
+
+    match create(&state, &request.name, Some(creator)).await {
+        Ok(id) => (StatusCode::CREATED, Json(serde_json::json!({ "id": id }))).into_response(),
+        Err(e) => {
+            eprintln!("[Rooms] {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+/// DELETE /rooms/{id}
+async fn handle_close_room(
+    State(state): State<SharedServerState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(rejection) = member_room_dir(&state, &id, &headers).await {
+        return rejection;
+    }
+    match close(&state, &id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+/// POST /rooms/{id}/join
+///
+/// Adds the requesting peer to the room. Once a PIN is set, joining needs
+/// the PIN's `X-AirShare-Token`.
+async fn handle_join_room(
+    State(state): State<SharedServerState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    let Some(peer) = requesting_peer(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing X-AirShare-Peer-ID").into_response();
+    };
+
+    let mut rooms = state.rooms.write().await;
+    let Some(room) = rooms.get_mut(&id) else {
+        return (StatusCode::NOT_FOUND, format!("Room not found: {}", id)).into_response();
+    };
+    room.members.insert(peer.to_string());
+    room.last_active = Instant::now();
+
+    println!("[Rooms] Peer {} joined room {}", peer, id);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// GET /rooms/{id}/files
+async fn handle_list_files(
+    State(state): State<SharedServerState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let dir = match member_room_dir(&state, &id, &headers).await {
+        Ok(dir) => dir,
+        Err(rejection) => return rejection,
+    };
+
+    match list_dir_files(&dir, false).await {
+        Ok(files) => Json(files).into_response(),
+        Err(e) => {
+            eprintln!("[Rooms] Failed to list room {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list files").into_response()
+        }
+    }
+}
+
+/// POST /rooms/{id}/upload (multipart, one or more file fields)
+async fn handle_upload(
+    State(state): State<SharedServerState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let dir = match member_room_dir(&state, &id, &headers).await {
+        Ok(dir) => dir,
+        Err(rejection) => return rejection,
+    };
+
+    let max_size = state.max_upload_size_bytes.load(Ordering::Relaxed);
+    let mut received = 0u64;
+    let mut saved = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+
+        let Some(filename) = field.file_name().map(|n| n.to_string()) else {
+            continue;
+        };
+        if !is_plain_filename(&filename) || has_path_syntax(&filename) {
+            return (StatusCode::BAD_REQUEST, format!("Invalid filename: {}", filename))
+                .into_response();
+        }
+        if !state.accepts_upload(&filename).await {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("File type not allowed: {}", filename),
+            )
+                .into_response();
+        }
+
+        let limit = max_size.saturating_sub(received);
+        match save_field(field, &dir.join(&filename), limit, |_| {}).await {
+            Ok(size) => received += size,
+            Err(e) => {
+                eprintln!("[Rooms] Upload of {} failed: {}", filename, e);
+                if e == UPLOAD_TOO_LARGE {
+                    return (StatusCode::PAYLOAD_TOO_LARGE, e).into_response();
+                }
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        }
+
+        println!("[Rooms] Uploaded {} to room {}", filename, id);
+        saved.push(filename);
+    }
+
+    Json(saved).into_response()
+}
+
+/// GET /rooms/{id}/file/{filename}
+async fn handle_serve_file(
+    State(state): State<SharedServerState>,
+    Path((id, filename)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let dir = match member_room_dir(&state, &id, &headers).await {
+        Ok(dir) => dir,
+        Err(rejection) => return rejection,
+    };
+    let path = match shared_file_path(&dir, &filename) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    match fs::read(&path).await {
+        Ok(contents) => {
+            println!("[Rooms] Serving {} from room {}", filename, id);
+            (StatusCode::OK, contents).into_response()
+        }
+        Err(_) => (StatusCode::NOT_FOUND, format!("File not found: {}", filename)).into_response(),
+    }
+}
+
+/// Tauri command to create a room on the local server, joining it as its first member
+#[tauri::command]
+pub async fn create_room(
+    server: tauri::State<'_, SharedServerState>,
+    discovery: tauri::State<'_, SharedDiscoveryState>,
+    name: String,
+) -> Result<String, String> {
+    let device_id = discovery.read().await.device_id.clone();
+    create(&server, &name, Some(&device_id)).await
+}
+
+/// Tauri command to join an existing room on the local server
+#[tauri::command]
+pub async fn join_room(
+    server: tauri::State<'_, SharedServerState>,
+    discovery: tauri::State<'_, SharedDiscoveryState>,
+    room_id: String,
+) -> Result<(), String> {
+    let device_id = discovery.read().await.device_id.clone();
+
+    let mut rooms = server.rooms.write().await;
+    let room = rooms
+        .get_mut(&room_id)
+        .ok_or_else(|| format!("Room not found: {}", room_id))?;
+    room.members.insert(device_id);
+
+    println!("[Rooms] Joined room {}", room_id);
+    Ok(())
+}
+
+/// Tauri command to close a room on the local server, deleting its files
+#[tauri::command]
+pub async fn close_room(server: tauri::State<'_, SharedServerState>, room_id: String) -> Result<(), String> {
+    close(&server, &room_id).await
+}
@@ -0,0 +1,417 @@
+// WebRTC data channel transfer (optional, `webrtc` feature)
+//
+// Signaling runs over the existing HTTP servers: the sender POSTs an SDP offer
+// to the receiver's `/webrtc/offer`, the receiver POSTs its SDP answer back to
+// the sender's `/webrtc/answer`, then the file bytes stream over a data
+// channel. ICE candidates are gathered up front and carried in the SDP.
+
+use crate::auth::PeerCredentials;
+use crate::discovery::SharedDiscoveryState;
+use crate::server::{
+    has_path_syntax, has_valid_token, is_plain_filename, peer_base_url, resolve_collision,
+    SharedServerState, DEFAULT_SERVER_PORT, META_SUFFIX,
+};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+
+const DATA_CHANNEL_LABEL: &str = "airshare-file";
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Pause sending while more than this many bytes are queued on the channel
+const MAX_BUFFERED_BYTES: usize = 1024 * 1024;
+const SIGNALING_TIMEOUT_SECS: u64 = 10;
+const TRANSFER_TIMEOUT_SECS: u64 = 600;
+
+/// Body of `POST /webrtc/offer`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRtcOffer {
+    pub session_id: String,
+    /// IP whose `/webrtc/answer` receives the answer
+    pub sender_ip: String,
+    /// HTTP port of the sender's server
+    #[serde(default = "default_sender_port")]
+    pub sender_port: u16,
+    pub filename: String,
+    pub size: u64,
+    pub sdp: RTCSessionDescription,
+}
+
+fn default_sender_port() -> u16 {
+    DEFAULT_SERVER_PORT
+}
+
+/// Body of `POST /webrtc/answer`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRtcAnswer {
+    pub session_id: String,
+    pub sdp: RTCSessionDescription,
+}
+
+/// Outgoing transfers waiting for the receiver's answer, keyed by session ID
+static PENDING_ANSWERS: LazyLock<Mutex<HashMap<String, oneshot::Sender<RTCSessionDescription>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// WebRTC signaling routes, merged into the main server router
+pub fn routes() -> Router<SharedServerState> {
+    Router::new()
+        .route("/webrtc/offer", post(handle_offer))
+        .route("/webrtc/answer", post(handle_answer))
+}
+
+/// Create a peer connection with the default (host candidate) ICE config
+async fn new_peer_connection() -> Result<Arc<RTCPeerConnection>, String> {
+    let api = APIBuilder::new().build();
+    api.new_peer_connection(RTCConfiguration::default())
+        .await
+        .map(Arc::new)
+        .map_err(|e| format!("Failed to create peer connection: {}", e))
+}
+
+/// Apply a local description and wait for ICE gathering so the SDP is complete
+async fn set_local_and_gather(
+    pc: &RTCPeerConnection,
+    desc: RTCSessionDescription,
+) -> Result<RTCSessionDescription, String> {
+    let mut gathered = pc.gathering_complete_promise().await;
+    pc.set_local_description(desc)
+        .await
+        .map_err(|e| format!("Failed to set local description: {}", e))?;
+    let _ = gathered.recv().await;
+
+    pc.local_description()
+        .await
+        .ok_or_else(|| "Missing local description".to_string())
+}
+
+/// POST /webrtc/offer - accept an incoming transfer into the shared directory
+///
+/// The file lands where `/upload` puts files, so the same name, type, size
+/// and collision rules apply, and the PIN token is required.
+async fn handle_offer(
+    State(state): State<SharedServerState>,
+    headers: HeaderMap,
+    Json(offer): Json<WebRtcOffer>,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        println!("[WebRTC] Rejected offer for {} from {}: bad token", offer.filename, offer.sender_ip);
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !is_plain_filename(&offer.filename)
+        || has_path_syntax(&offer.filename)
+        || offer.filename.ends_with(META_SUFFIX)
+    {
+        return (StatusCode::BAD_REQUEST, format!("Invalid filename: {}", offer.filename))
+            .into_response();
+    }
+    if !state.accepts_upload(&offer.filename).await {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("File type not allowed: {}", offer.filename),
+        )
+            .into_response();
+    }
+    let max_size = state.max_upload_size_bytes.load(Ordering::Relaxed);
+    if offer.size > max_size {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Upload is larger than {} bytes", max_size),
+        )
+            .into_response();
+    }
+    let Some(dest) =
+        resolve_collision(&state.shared_dir, &offer.filename, state.config.file_collision_strategy)
+    else {
+        return (StatusCode::CONFLICT, format!("File already exists: {}", offer.filename))
+            .into_response();
+    };
+
+    println!(
+        "[WebRTC] Offer for {} ({} bytes) from {}",
+        offer.filename, offer.size, offer.sender_ip
+    );
+
+    tokio::spawn(async move {
+        if let Err(e) = receive_transfer(offer, dest).await {
+            eprintln!("[WebRTC] Receive failed: {}", e);
+        }
+    });
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// POST /webrtc/answer - hand the answer to the waiting sender
+async fn handle_answer(Json(answer): Json<WebRtcAnswer>) -> impl IntoResponse {
+    let waiter = PENDING_ANSWERS.lock().unwrap().remove(&answer.session_id);
+    match waiter {
+        Some(tx) => {
+            let _ = tx.send(answer.sdp);
+            StatusCode::OK.into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "Unknown session").into_response(),
+    }
+}
+
+/// Answer an offer, then write the incoming data channel bytes to `dest`
+async fn receive_transfer(offer: WebRtcOffer, dest: PathBuf) -> Result<(), String> {
+    let pc = new_peer_connection().await?;
+    let file = fs::File::create(&dest)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    let (done_tx, done_rx) = oneshot::channel::<Result<u64, String>>();
+    let sink = Arc::new(tokio::sync::Mutex::new(ReceiveSink {
+        file,
+        received: 0,
+        expected: offer.size,
+        done: Some(done_tx),
+    }));
+
+    pc.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+        let sink = sink.clone();
+        Box::pin(async move {
+            channel.on_message(Box::new(move |msg: DataChannelMessage| {
+                let sink = sink.clone();
+                Box::pin(async move {
+                    sink.lock().await.write(&msg.data).await;
+                })
+            }));
+        })
+    }));
+
+    pc.set_remote_description(offer.sdp)
+        .await
+        .map_err(|e| format!("Failed to set remote description: {}", e))?;
+    let answer = pc
+        .create_answer(None)
+        .await
+        .map_err(|e| format!("Failed to create answer: {}", e))?;
+    let answer = set_local_and_gather(&pc, answer).await?;
+
+    let url = format!("{}/webrtc/answer", peer_base_url(&offer.sender_ip, offer.sender_port));
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&WebRtcAnswer {
+            session_id: offer.session_id,
+            sdp: answer,
+        })
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send answer: {}", e))?;
+    if !response.status().is_success() {
+        let _ = pc.close().await;
+        return Err(format!("Sender rejected answer: {}", response.status()));
+    }
+
+    let result = tokio::time::timeout(Duration::from_secs(TRANSFER_TIMEOUT_SECS), done_rx).await;
+    let _ = pc.close().await;
+
+    match result {
+        Ok(Ok(Ok(bytes))) => {
+            println!("[WebRTC] Received {} ({} bytes)", offer.filename, bytes);
+            Ok(())
+        }
+        Ok(Ok(Err(e))) => Err(e),
+        Ok(Err(_)) => Err("Data channel closed early".to_string()),
+        Err(_) => Err("Transfer timed out".to_string()),
+    }
+}
+
+/// Destination file for an incoming data channel
+struct ReceiveSink {
+    file: fs::File,
+    received: u64,
+    expected: u64,
+    done: Option<oneshot::Sender<Result<u64, String>>>,
+}
+
+impl ReceiveSink {
+    async fn write(&mut self, data: &Bytes) {
+        if self.done.is_none() {
+            return;
+        }
+
+        let result = self.file.write_all(data).await;
+        self.received += data.len() as u64;
+
+        let outcome = match result {
+            Err(e) => Some(Err(format!("Failed to write file: {}", e))),
+            Ok(()) if self.received >= self.expected => Some(
+                self.file
+                    .flush()
+                    .await
+                    .map(|_| self.received)
+                    .map_err(|e| format!("Failed to write file: {}", e)),
+            ),
+            Ok(()) => None,
+        };
+
+        if let Some(outcome) = outcome {
+            if let Some(done) = self.done.take() {
+                let _ = done.send(outcome);
+            }
+        }
+    }
+}
+
+/// Send `path` to the peer at `peer_ip` over a WebRTC data channel
+async fn send_transfer(
+    (our_ip, our_port): (&str, u16),
+    (peer_ip, peer_port): (&str, u16),
+    path: PathBuf,
+    filename: &str,
+    credentials: &PeerCredentials,
+) -> Result<u64, String> {
+    let mut file = fs::File::open(&path)
+        .await
+        .map_err(|_| format!("File not found: {}", filename))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    // The receiver finishes once it has `size` bytes, so it would never see an empty file end
+    if size == 0 {
+        return Err(format!("Cannot send empty file: {}", filename));
+    }
+
+    let pc = new_peer_connection().await?;
+    let channel = pc
+        .create_data_channel(DATA_CHANNEL_LABEL, None)
+        .await
+        .map_err(|e| format!("Failed to create data channel: {}", e))?;
+
+    let (open_tx, open_rx) = oneshot::channel::<()>();
+    channel.on_open(Box::new(move || {
+        Box::pin(async move {
+            let _ = open_tx.send(());
+        })
+    }));
+
+    let offer = pc
+        .create_offer(None)
+        .await
+        .map_err(|e| format!("Failed to create offer: {}", e))?;
+    let offer = set_local_and_gather(&pc, offer).await?;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let (answer_tx, answer_rx) = oneshot::channel();
+    PENDING_ANSWERS
+        .lock()
+        .unwrap()
+        .insert(session_id.clone(), answer_tx);
+
+    let result = async {
+        let url = format!("{}/webrtc/offer", peer_base_url(peer_ip, peer_port));
+        let request = reqwest::Client::new().post(&url).json(&WebRtcOffer {
+            session_id: session_id.clone(),
+            sender_ip: our_ip.to_string(),
+            sender_port: our_port,
+            filename: filename.to_string(),
+            size,
+            sdp: offer,
+        });
+        let response = credentials
+            .apply(request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send offer: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Peer rejected offer: {}", response.status()));
+        }
+
+        let signaling_timeout = Duration::from_secs(SIGNALING_TIMEOUT_SECS);
+        let answer = tokio::time::timeout(signaling_timeout, answer_rx)
+            .await
+            .map_err(|_| "Timed out waiting for answer".to_string())?
+            .map_err(|_| "Answer channel closed".to_string())?;
+        pc.set_remote_description(answer)
+            .await
+            .map_err(|e| format!("Failed to set remote description: {}", e))?;
+
+        tokio::time::timeout(signaling_timeout, open_rx)
+            .await
+            .map_err(|_| "Timed out opening data channel".to_string())?
+            .map_err(|_| "Data channel closed".to_string())?;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut sent = 0u64;
+        loop {
+            let n = file
+                .read(&mut buf)
+                .await
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            while channel.buffered_amount().await > MAX_BUFFERED_BYTES {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            channel
+                .send(&Bytes::copy_from_slice(&buf[..n]))
+                .await
+                .map_err(|e| format!("Failed to send data: {}", e))?;
+            sent += n as u64;
+        }
+
+        // Let the channel drain before tearing the connection down
+        while channel.buffered_amount().await > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        Ok(sent)
+    }
+    .await;
+
+    PENDING_ANSWERS.lock().unwrap().remove(&session_id);
+    let _ = pc.close().await;
+    result
+}
+
+/// Tauri command to push a shared file to a peer over a WebRTC data channel
+#[tauri::command]
+pub async fn initiate_webrtc_transfer(
+    discovery: tauri::State<'_, SharedDiscoveryState>,
+    server: tauri::State<'_, SharedServerState>,
+    peer_id: String,
+    filename: String,
+) -> Result<String, String> {
+    if !is_plain_filename(&filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
+
+    let (our_ip, peer_ip, peer_port, credentials) = {
+        let state = discovery.read().await;
+        let peer = state
+            .peers
+            .get(&peer_id)
+            .ok_or_else(|| format!("Unknown peer: {}", peer_id))?;
+        (state.local_ip.clone(), peer.ip.clone(), peer.server_port, state.credentials())
+    };
+
+    println!("[WebRTC] Sending {} to {}", filename, peer_ip);
+    let path = server.shared_dir.join(&filename);
+    let our_port = server.port();
+    let bytes =
+        send_transfer((&our_ip, our_port), (&peer_ip, peer_port), path, &filename, &credentials).await?;
+    println!("[WebRTC] Sent {} ({} bytes)", filename, bytes);
+    Ok(filename)
+}
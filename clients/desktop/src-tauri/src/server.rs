@@ -1,27 +1,190 @@
 // Native Rust HTTP File Server (replaces Go server package)
 
+use crate::acl::{self, FileAcls};
+use crate::auth::{self, PeerCredentials};
+use crate::config::{AirShareConfig, CollisionStrategy};
+use crate::history;
+use crate::discovery::SharedDiscoveryState;
+use crate::ipc::{self, TransferResponse};
+use crate::processors::{self, PostUploadProcessor, ProcessResult};
+use crate::rooms::{self, Room};
+use crate::transfer_requests::{self, TransferRequests};
+use async_compression::tokio::bufread::{ZstdDecoder, ZstdEncoder};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::get,
-    Router,
+    body::Body,
+    extract::{
+        ConnectInfo, DefaultBodyLimit, Multipart, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Extension, Json, Router,
 };
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::Instrument;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
-use tower_http::cors::{Any, CorsLayer};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::sync::RwLock;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
-const SERVER_PORT: u16 = 8080;
+/// HTTP port used unless `server_port` is set in the config
+pub const DEFAULT_SERVER_PORT: u16 = 8080;
+/// Ports tried from `server_port` upwards when it is already taken
+const SERVER_PORT_ATTEMPTS: u16 = 10;
+/// Quiet time after a shared folder change before `files-changed` is emitted
+const FILES_CHANGED_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+/// UDP port of the experimental QUIC transport
+pub const QUIC_PORT: u16 = 8443;
+/// Response header carrying the hex SHA-256 of a file (HEAD requests only)
+const CONTENT_SHA256_HEADER: &str = "Content-SHA256";
+const REQUEST_ID_HEADER: &str = "X-Request-ID";
+/// Multipart field carrying JSON metadata for the uploaded file(s)
+pub const META_FIELD: &str = "X-AirShare-Meta";
+/// Suffix of the sidecar file storing an upload's metadata
+pub const META_SUFFIX: &str = ".meta.json";
+/// Chunk size used when streaming files from disk
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+/// Bytes received between `upload-progress` events
+const UPLOAD_PROGRESS_INTERVAL: u64 = 1024 * 1024;
+/// Upload size limit unless `max_upload_size_bytes` is set in the config
+pub const DEFAULT_MAX_UPLOAD_SIZE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// Error returned by `save_field` when an upload goes over its limit
+pub const UPLOAD_TOO_LARGE: &str = "Upload exceeds the size limit";
+/// Bytes sent each way by `measure_bandwidth`
+pub const BANDWIDTH_TEST_BYTES: u64 = 1024 * 1024;
+/// Largest transfer `/bandwidth` will generate or accept
+const MAX_BANDWIDTH_TEST_BYTES: u64 = 16 * 1024 * 1024;
+const BANDWIDTH_TEST_TIMEOUT_SECS: u64 = 30;
+/// Largest text accepted by `POST /text`
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+/// `Content-Encoding` token for zstd-compressed downloads
+const ZSTD_ENCODING: &str = "zstd";
+/// Extensions of formats that are already compressed and gain nothing from zstd
+const PRECOMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "apk", "avi", "br", "bz2", "docx", "flac", "gif", "gz", "heic", "jar", "jpeg",
+    "jpg", "m4a", "mkv", "mov", "mp3", "mp4", "ogg", "png", "pptx", "rar", "tgz", "webm", "webp",
+    "xlsx", "xz", "zip", "zst",
+];
+/// Deepest subdirectory level walked by recursive listings
+const MAX_LIST_DEPTH: u32 = 32;
+/// Number of requests kept for `/debug/requests`
+const RECENT_REQUESTS_CAPACITY: usize = 100;
+/// Comma-separated list of allowed CORS origins, overrides the config file
+const CORS_ORIGINS_ENV: &str = "AIRSHARE_CORS_ORIGINS";
+
+/// Unique ID assigned to each HTTP request by `request_id_middleware`
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Summary of a handled request, kept for debugging
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestLogEntry {
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+}
+
+/// A shared file as listed by `GET /files`
+#[derive(Debug, Clone, Serialize)]
+pub struct FileInfo {
+    pub name: String,
+    pub size_bytes: u64,
+    /// Last modification time (Unix seconds, 0 if unknown)
+    pub modified_unix: i64,
+    pub mime: String,
+}
+
+/// Counters reported by `GET /stats`
+pub struct ServerStats {
+    pub started_at: std::time::Instant,
+    pub files_served: AtomicU64,
+    pub bytes_served: AtomicU64,
+    pub uploads_accepted: AtomicU64,
+    pub bytes_uploaded: AtomicU64,
+    /// Requests currently being handled
+    pub active_connections: AtomicU64,
+}
+
+impl ServerStats {
+    fn new() -> Self {
+        Self {
+            started_at: std::time::Instant::now(),
+            files_served: AtomicU64::new(0),
+            bytes_served: AtomicU64::new(0),
+            uploads_accepted: AtomicU64::new(0),
+            bytes_uploaded: AtomicU64::new(0),
+            active_connections: AtomicU64::new(0),
+        }
+    }
+
+    fn record_served(&self, bytes: u64) {
+        self.files_served.fetch_add(1, Ordering::Relaxed);
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn record_upload(&self, bytes: u64) {
+        self.uploads_accepted.fetch_add(1, Ordering::Relaxed);
+        self.bytes_uploaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Notification from the server, forwarded to the frontend as a Tauri event
+#[derive(Debug, Clone)]
+pub struct ServerEvent {
+    pub name: &'static str,
+    pub payload: serde_json::Value,
+}
+
+/// Capacity of the server event channel; slow subscribers miss older events
+const SERVER_EVENT_CAPACITY: usize = 64;
 
 /// Server state
 pub struct ServerState {
     pub shared_dir: PathBuf,
+    pub config: AirShareConfig,
+    pub discovery: SharedDiscoveryState,
+    /// Ring buffer of the most recent requests
+    pub recent_requests: Mutex<VecDeque<RequestLogEntry>>,
+    /// Ephemeral per-session file spaces, keyed by room ID
+    pub rooms: RwLock<HashMap<String, Room>>,
+    /// Unix time (seconds) of the last `/heartbeat` hit from a mobile client
+    pub last_heartbeat: Arc<AtomicU64>,
+    pub stats: ServerStats,
+    /// Hooks run on every file accepted by `/upload`
+    pub processors: Vec<Box<dyn PostUploadProcessor>>,
+    pub events: tokio::sync::broadcast::Sender<ServerEvent>,
+    /// Per-file peer allow-lists, persisted to acls.json
+    pub file_acls: RwLock<FileAcls>,
+    /// Latest text a peer sent to `POST /text`
+    pub received_text: Arc<RwLock<Option<String>>>,
+    /// Largest upload accepted in one request (from config, changed by `set_max_upload_size`)
+    pub max_upload_size_bytes: AtomicU64,
+    /// Extensions uploads may have, from `normalize_extensions` (empty = any)
+    pub allowed_extensions: RwLock<Vec<String>>,
+    /// Port the HTTP server is listening on; `server_port` until it has bound
+    port: AtomicU16,
+    /// Incoming transfers waiting for the user to accept or reject them
+    pub transfer_requests: TransferRequests,
 }
 
 impl ServerState {
-    pub fn new() -> Self {
+    pub fn new(config: AirShareConfig, discovery: SharedDiscoveryState) -> Self {
         // Use a "shared" folder in the current directory
         let shared_dir = std::env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("."))
@@ -32,107 +195,2926 @@ impl ServerState {
             let _ = std::fs::create_dir_all(&shared_dir);
         }
 
-        // Create a demo file
-        let demo_path = shared_dir.join("demo.txt");
-        if !demo_path.exists() {
-            let _ = std::fs::write(&demo_path, "Hello from AirShare!\nThis is a demo file.");
-            println!("[Server] Created demo.txt in shared folder");
+        // Create the configured demo file, never replacing an existing one
+        if !config.skip_demo_file {
+            if let (Some(name), Some(content)) = (&config.demo_file_name, &config.demo_file_content) {
+                let demo_path = shared_dir.join(name);
+                if !is_plain_filename(name) {
+                    eprintln!("[Server] Invalid demo file name: {}", name);
+                } else if !demo_path.exists() {
+                    let _ = std::fs::write(&demo_path, content);
+                    println!("[Server] Created {} in shared folder", name);
+                }
+            }
         }
 
         println!("[Server] Shared directory: {:?}", shared_dir);
 
-        Self { shared_dir }
+        Self {
+            max_upload_size_bytes: AtomicU64::new(config.max_upload_size_bytes),
+            port: AtomicU16::new(config.server_port),
+            transfer_requests: TransferRequests::new(),
+            allowed_extensions: RwLock::new(normalize_extensions(&config.allowed_upload_extensions)),
+            shared_dir,
+            config,
+            discovery,
+            recent_requests: Mutex::new(VecDeque::with_capacity(RECENT_REQUESTS_CAPACITY)),
+            rooms: RwLock::new(HashMap::new()),
+            last_heartbeat: Arc::new(AtomicU64::new(0)),
+            stats: ServerStats::new(),
+            processors: processors::default_processors(),
+            events: tokio::sync::broadcast::channel(SERVER_EVENT_CAPACITY).0,
+            file_acls: RwLock::new(acl::load()),
+            received_text: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Send an event to the frontend, if the app is listening
+    pub fn emit(&self, name: &'static str, payload: serde_json::Value) {
+        let _ = self.events.send(ServerEvent { name, payload });
+    }
+
+    /// Port the HTTP server is listening on
+    pub fn port(&self) -> u16 {
+        self.port.load(Ordering::Relaxed)
     }
 
     pub fn get_shared_dir(&self) -> &PathBuf {
         &self.shared_dir
     }
+
+    /// Whether the upload allow-list lets `filename` in
+    pub async fn accepts_upload(&self, filename: &str) -> bool {
+        let allowed = self.allowed_extensions.read().await;
+        if allowed.is_empty() {
+            return true;
+        }
+        std::path::Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| allowed.contains(&ext.to_ascii_lowercase()))
+    }
+}
+
+/// Lowercase, strip leading dots and drop blanks and duplicates from an allow-list
+pub fn normalize_extensions(extensions: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = extensions
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+/// Extra check on upload names: not `..` and no separator of any platform
+///
+/// `is_plain_filename` already covers this for the current platform; this
+/// also catches `\\` on Unix, where it is a legal filename character.
+/// Dots inside a name (`v1..2.zip`) are fine.
+pub fn has_path_syntax(filename: &str) -> bool {
+    filename == ".." || filename.contains(['/', '\\'])
 }
 
 pub type SharedServerState = Arc<ServerState>;
 
+/// Bind the IPv6 wildcard address on `port` next to the IPv4 listener
+///
+/// The socket is IPv6-only, so IPv4 clients keep arriving on the IPv4
+/// listener with plain IPv4 addresses. `None` when IPv6 is unavailable.
+fn bind_ipv6_server_port(port: u16) -> Option<tokio::net::TcpListener> {
+    let bind = || -> std::io::Result<tokio::net::TcpListener> {
+        let socket = socket2::Socket::new(socket2::Domain::IPV6, socket2::Type::STREAM, None)?;
+        socket.set_only_v6(true)?;
+        #[cfg(not(windows))]
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, port)).into())?;
+        socket.listen(1024)?;
+        tokio::net::TcpListener::from_std(socket.into())
+    };
+    match bind() {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            eprintln!("[Server] IPv6 unavailable on port {}: {}", port, e);
+            None
+        }
+    }
+}
+
+/// Bind the first free port from `first_port` upwards
+async fn bind_server_port(first_port: u16) -> Result<tokio::net::TcpListener, String> {
+    let mut last_error = None;
+    for port in (0..SERVER_PORT_ATTEMPTS).filter_map(|offset| first_port.checked_add(offset)) {
+        match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => {
+                eprintln!("[Server] Port {} unavailable: {}", port, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(format!(
+        "No free port between {} and {}: {}",
+        first_port,
+        first_port.saturating_add(SERVER_PORT_ATTEMPTS - 1),
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
 /// Start the HTTP file server
+///
+/// Falls back to the next free port when `server_port` is taken, advertises
+/// the port it got in beacons and emits `server-started` with it, or
+/// `server-error` if no port could be bound.
 pub async fn start_server(state: SharedServerState) {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let listener = match bind_server_port(state.config.server_port).await {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("[Server] Failed to start: {}", e);
+            state.emit("server-error", serde_json::json!({ "error": e }));
+            return;
+        }
+    };
+    let port = match listener.local_addr() {
+        Ok(addr) => addr.port(),
+        Err(e) => {
+            eprintln!("[Server] Failed to read bound address: {}", e);
+            return;
+        }
+    };
+    // Peers on IPv6-only networks connect on the same port
+    let listener_v6 = bind_ipv6_server_port(port);
+    state.port.store(port, Ordering::Relaxed);
+    state.discovery.write().await.server_port = port;
+    println!("[Server] Starting HTTP server on port {}", port);
+    state.emit("server-started", serde_json::json!({ "port": port }));
+    tokio::spawn(watch_shared_dir(state.clone()));
+    tokio::spawn(sweep_rooms(state.clone()));
+
+    let cors = cors_layer(&state.config.allowed_origins);
 
-    let app = Router::new()
-        .route("/file/{filename}", get(serve_file))
+    let mut router = Router::new()
+        .route("/file/{filename}", get(serve_file).patch(patch_file))
+        .route("/file/{filename}/meta", get(serve_file_meta))
+        .route("/file/{filename}/stream", get(stream_file))
+        .route("/file/{filename}/copy", post(copy_file))
+        .route("/checksum/{filename}", get(serve_checksum))
+        .route("/files", get(list_files))
+        .route(
+            "/upload",
+            post(handle_upload).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/text",
+            get(serve_text)
+                .post(receive_text)
+                .layer(DefaultBodyLimit::max(MAX_TEXT_BYTES)),
+        )
+        .route("/events", get(server_events))
         .route("/health", get(health_check))
+        .route("/bandwidth", get(bandwidth_download).post(bandwidth_upload))
+        .route("/heartbeat", get(heartbeat))
+        .route("/stats", get(stats))
+        .merge(rooms::routes())
+        .merge(transfer_requests::routes())
+        .merge(crate::mobile::routes());
+
+    #[cfg(feature = "webrtc")]
+    {
+        router = router.merge(crate::rtc_transfer::routes());
+    }
+
+    if state.config.debug_mode {
+        router = router.route("/debug/requests", get(debug_requests));
+    }
+
+    let app = router
+        .layer(middleware::from_fn_with_state(state.clone(), request_id_middleware))
         .with_state(state)
         .layer(cors);
 
-    let addr = format!("0.0.0.0:{}", SERVER_PORT);
-    println!("[Server] Starting HTTP server on port {}", SERVER_PORT);
+    // Client addresses let serve_file check the requesting peer's capabilities
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let serve_v6 = async {
+        if let Some(listener) = listener_v6 {
+            if let Err(e) = axum::serve(listener, service.clone()).await {
+                eprintln!("[Server] IPv6 server error: {}", e);
+            }
+        }
+    };
+    let serve_v4 = async {
+        if let Err(e) = axum::serve(listener, service.clone()).await {
+            eprintln!("[Server] Server error: {}", e);
+        }
+    };
+    tokio::join!(serve_v4, serve_v6);
+}
+
+/// Emit `files-changed` whenever files appear in or leave the shared folder
+///
+/// The OS watcher runs on a blocking thread and forwards the names of
+/// changed files; bursts (e.g. a multi-file drop in Explorer) are collected
+/// until `FILES_CHANGED_DEBOUNCE` passes without a change, then sent as one
+/// event with `{ files }`.
+async fn watch_shared_dir(state: SharedServerState) {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let dir = state.shared_dir.clone();
+    tokio::task::spawn_blocking(move || {
+        use notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher};
 
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            eprintln!("[Server] Failed to bind to {}: {}", addr, e);
+        let (events_tx, events) = std::sync::mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(events_tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("[Server] Failed to create folder watcher: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            eprintln!("[Server] Failed to watch {:?}: {}", dir, e);
             return;
         }
+        println!("[Server] Watching {:?} for changes", dir);
+
+        for event in events {
+            let event: notify::Event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("[Server] Folder watcher error: {}", e);
+                    continue;
+                }
+            };
+            // Moving a file in or out shows up as a rename
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+            ) {
+                continue;
+            }
+            for path in event.paths {
+                let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                    continue;
+                };
+                if tx.send(name).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    while let Some(first) = rx.recv().await {
+        let mut changed = std::collections::BTreeSet::from([first]);
+        while let Ok(Some(name)) = tokio::time::timeout(FILES_CHANGED_DEBOUNCE, rx.recv()).await {
+            changed.insert(name);
+        }
+        state.emit("files-changed", serde_json::json!({ "files": changed }));
+    }
+}
+
+/// Close rooms that have gone unused, every `ROOM_SWEEP_INTERVAL`
+async fn sweep_rooms(state: SharedServerState) {
+    let mut sweep = tokio::time::interval(rooms::ROOM_SWEEP_INTERVAL);
+    loop {
+        sweep.tick().await;
+        rooms::sweep_expired_rooms(&state).await;
+    }
+}
+
+/// Announce a clean shutdown to peers before the process exits
+pub async fn shutdown_server(state: &SharedServerState) {
+    println!("[Server] Shutting down");
+    crate::discovery::send_leaving_beacon(&state.discovery).await;
+    // A held folder's zip sits in the shared folder, where peers could keep fetching it
+    state.discovery.write().await.discard_held_archive();
+    rooms::close_all(state).await;
+}
+
+/// Tag each request with a UUID, echo it in `X-Request-ID` and record the outcome
+async fn request_id_middleware(
+    State(state): State<SharedServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let id = uuid::Uuid::new_v4().to_string();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id, %method, %path);
+    state.stats.active_connections.fetch_add(1, Ordering::Relaxed);
+    let mut response = next.run(request).instrument(span.clone()).await;
+    state.stats.active_connections.fetch_sub(1, Ordering::Relaxed);
+    span.in_scope(|| tracing::debug!(status = response.status().as_u16(), "Request finished"));
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let entry = RequestLogEntry {
+        id,
+        method,
+        path,
+        status: response.status().as_u16(),
+    };
+    if let Ok(mut recent) = state.recent_requests.lock() {
+        if recent.len() == RECENT_REQUESTS_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    response
+}
+
+/// List recent request IDs, paths and status codes (debug mode only)
+async fn debug_requests(State(state): State<SharedServerState>) -> Json<Vec<RequestLogEntry>> {
+    let recent = state
+        .recent_requests
+        .lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default();
+    Json(recent)
+}
+
+/// Build the CORS layer from `AIRSHARE_CORS_ORIGINS` or the configured origins
+fn cors_layer(configured: &[String]) -> CorsLayer {
+    let origins: Vec<String> = match std::env::var(CORS_ORIGINS_ENV) {
+        Ok(value) => value
+            .split(',')
+            .map(|origin| origin.trim().to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect(),
+        Err(_) => configured.to_vec(),
     };
 
-    if let Err(e) = axum::serve(listener, app).await {
-        eprintln!("[Server] Server error: {}", e);
+    let cors = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+
+    if origins.is_empty() {
+        return cors.allow_origin(Any);
     }
+
+    let allowed: Vec<HeaderValue> = origins
+        .iter()
+        .filter_map(|origin| match origin.parse::<HeaderValue>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                eprintln!("[Server] Ignoring invalid CORS origin: {}", origin);
+                None
+            }
+        })
+        .collect();
+
+    println!("[Server] CORS allowed origins: {:?}", origins);
+    cors.allow_origin(AllowOrigin::list(allowed))
+}
+
+/// Whether the request may download files: always without a PIN, otherwise
+/// only with the PIN's request token
+pub(crate) async fn has_valid_token(state: &ServerState, headers: &HeaderMap) -> bool {
+    let Some(expected) = state.discovery.read().await.request_token() else {
+        return true;
+    };
+    headers
+        .get(auth::TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|token| auth::tokens_match(&expected, token))
+}
+
+/// Whether the peer named in `X-AirShare-Peer-ID` may download `filename`
+async fn peer_allowed(state: &ServerState, filename: &str, headers: &HeaderMap) -> bool {
+    let peer_id = headers.get(acl::PEER_ID_HEADER).and_then(|v| v.to_str().ok());
+    acl::is_allowed(&*state.file_acls.read().await, filename, peer_id)
 }
 
 /// Serve a file from the shared directory
+///
+/// HEAD requests get a `Content-SHA256` header so peers can compare files
+/// without downloading them. Whole-file downloads are zstd-compressed when
+/// the request accepts it and the requesting peer's beacon advertised
+/// `supports_zstd`. Once a PIN is set, requests must carry the matching
+/// `X-AirShare-Token`, and files with an ACL are refused to peers not on it.
 async fn serve_file(
     State(state): State<SharedServerState>,
     Path(filename): Path<String>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    method: Method,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let file_path = state.shared_dir.join(&filename);
+    if !has_valid_token(&state, &headers).await {
+        println!("[Server] [{}] Rejected {} from {}: bad token", request_id, filename, remote.ip());
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        println!("[Server] [{}] Rejected {} from {}: not in ACL", request_id, filename, remote.ip());
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+
+    let file_path = match shared_file_path(&state.shared_dir, &filename) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
 
     if !file_path.exists() {
         return (StatusCode::NOT_FOUND, format!("File not found: {}", filename)).into_response();
     }
+    if let Err(rejection) =
+        check_servable_path(&state.shared_dir, &file_path, state.config.follow_symlinks).await
+    {
+        return rejection.into_response();
+    }
 
-    match fs::read(&file_path).await {
-        Ok(contents) => {
-            println!("[Server] Serving file: {}", filename);
-            (StatusCode::OK, contents).into_response()
-        }
+    if method == Method::HEAD {
+        return match sha256_file(&file_path).await {
+            Ok(hash) => (StatusCode::OK, [(CONTENT_SHA256_HEADER, hash)]).into_response(),
+            Err(e) => {
+                eprintln!("[Server] [{}] Failed to hash file {}: {}", request_id, filename, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+    }
+
+    let mime_type = mime_guess::from_path(&file_path).first_or_octet_stream().to_string();
+    let disposition = attachment_disposition(&filename);
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| v.trim_start().starts_with("bytes="));
+    if let Some(range) = range {
+        return match read_range(&file_path, range).await {
+            Ok(RangeRead::Partial { start, end, total, body }) => {
+                println!(
+                    "[Server] [{}] Serving file: {} (bytes {}-{}/{})",
+                    request_id, filename, start, end, total
+                );
+                state.stats.record_served(end - start + 1);
+                (
+                    StatusCode::PARTIAL_CONTENT,
+                    [
+                        (header::CONTENT_TYPE, mime_type),
+                        (header::CONTENT_DISPOSITION, disposition),
+                        (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total)),
+                        (header::CONTENT_LENGTH, (end - start + 1).to_string()),
+                        (header::ACCEPT_RANGES, "bytes".to_string()),
+                    ],
+                    body,
+                )
+                    .into_response()
+            }
+            Ok(RangeRead::Unsatisfiable { total }) => (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", total))],
+            )
+                .into_response(),
+            Err(e) => {
+                eprintln!("[Server] [{}] Failed to read file {}: {}", request_id, filename, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response()
+            }
+        };
+    }
+
+    let (file, size) = match fs::File::open(&file_path).await {
+        Ok(file) => match file.metadata().await {
+            Ok(meta) => (file, meta.len()),
+            Err(e) => {
+                eprintln!("[Server] [{}] Failed to read file {}: {}", request_id, filename, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response();
+            }
+        },
         Err(e) => {
-            eprintln!("[Server] Failed to read file {}: {}", filename, e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response()
+            eprintln!("[Server] [{}] Failed to read file {}: {}", request_id, filename, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response();
         }
+    };
+
+    if accepts_zstd(&headers)
+        && !is_precompressed(&file_path, &mime_type)
+        && peer_supports_zstd(&state, remote).await
+    {
+        println!("[Server] [{}] Serving file: {} (zstd)", request_id, filename);
+        state.stats.record_served(size);
+        let encoder = ZstdEncoder::new(BufReader::with_capacity(READ_CHUNK_SIZE, file));
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, mime_type),
+                (header::CONTENT_DISPOSITION, disposition),
+                (header::CONTENT_ENCODING, ZSTD_ENCODING.to_string()),
+                (header::VARY, header::ACCEPT_ENCODING.to_string()),
+            ],
+            Body::from_stream(ReaderStream::with_capacity(encoder, READ_CHUNK_SIZE)),
+        )
+            .into_response();
     }
+
+    println!("[Server] [{}] Serving file: {}", request_id, filename);
+    state.stats.record_served(size);
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, mime_type),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        Body::from_stream(ReaderStream::with_capacity(file, READ_CHUNK_SIZE)),
+    )
+        .into_response()
 }
 
-/// Health check endpoint
-async fn health_check() -> &'static str {
-    "AirShare Server OK"
+/// True if `Accept-Encoding` lists zstd without `q=0`
+fn accepts_zstd(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(|part| part.trim());
+            let name = parts.next().unwrap_or_default();
+            let disabled = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            name.eq_ignore_ascii_case(ZSTD_ENCODING) && !disabled
+        })
 }
 
-/// Download a file from a URL and save to destination
-pub async fn download_file(url: &str, dest_path: &str) -> Result<(), String> {
-    println!("[Server] Downloading: {} -> {}", url, dest_path);
+/// True for media and archive formats that zstd can't meaningfully shrink
+fn is_precompressed(path: &std::path::Path, mime_type: &str) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if PRECOMPRESSED_EXTENSIONS.contains(&extension.as_str()) {
+        return true;
+    }
+    mime_type.starts_with("audio/")
+        || mime_type.starts_with("video/")
+        || (mime_type.starts_with("image/") && mime_type != "image/svg+xml" && mime_type != "image/bmp")
+}
 
-    let response = reqwest::get(url)
+/// True if the peer at `remote` advertised `supports_zstd` in its beacon
+async fn peer_supports_zstd(state: &SharedServerState, remote: SocketAddr) -> bool {
+    let ip = remote.ip().to_string();
+    state
+        .discovery
+        .read()
         .await
-        .map_err(|e| format!("HTTP request failed: {}", e))?;
+        .peers
+        .values()
+        .any(|peer| peer.ip == ip && peer.supports_zstd)
+}
 
-    if !response.status().is_success() {
-        return Err(format!("HTTP error: {}", response.status()));
+/// Sizes and timings from `benchmark_zstd`
+#[derive(Debug, Clone, Serialize)]
+pub struct ZstdBenchmark {
+    pub filename: String,
+    pub plain_bytes: u64,
+    pub zstd_bytes: u64,
+    /// `zstd_bytes / plain_bytes`
+    pub ratio: f64,
+    pub plain_ms: u64,
+    pub zstd_ms: u64,
+    /// Whether `serve_file` would skip compression for this file
+    pub precompressed: bool,
+}
+
+/// Time reading a file as-is against reading it through the zstd encoder
+pub async fn benchmark_zstd(path: &std::path::Path) -> Result<ZstdBenchmark, String> {
+    let open = || async {
+        fs::File::open(path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+    };
+
+    let started = std::time::Instant::now();
+    let plain_bytes = tokio::io::copy(&mut open().await?, &mut tokio::io::sink())
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let plain_ms = started.elapsed().as_millis() as u64;
+
+    let started = std::time::Instant::now();
+    let mut encoder = ZstdEncoder::new(BufReader::with_capacity(READ_CHUNK_SIZE, open().await?));
+    let zstd_bytes = tokio::io::copy(&mut encoder, &mut tokio::io::sink())
+        .await
+        .map_err(|e| format!("Failed to compress file: {}", e))?;
+    let zstd_ms = started.elapsed().as_millis() as u64;
+
+    let mime_type = mime_guess::from_path(path).first_or_octet_stream().to_string();
+    Ok(ZstdBenchmark {
+        filename: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        plain_bytes,
+        zstd_bytes,
+        ratio: if plain_bytes == 0 { 1.0 } else { zstd_bytes as f64 / plain_bytes as f64 },
+        plain_ms,
+        zstd_ms,
+        precompressed: is_precompressed(path, &mime_type),
+    })
+}
+
+/// `Content-Disposition` value that makes browsers save the file as `filename`
+///
+/// Non-ASCII names also get an RFC 5987 `filename*` so they survive intact.
+fn attachment_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '_' })
+        .map(|c| if c == '"' || c == '\\' { '_' } else { c })
+        .collect();
+
+    if filename.is_ascii() {
+        return format!("attachment; filename=\"{}\"", fallback);
     }
 
-    let bytes = response
+    let encoded: String = filename
         .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
 
-    let mut file = fs::File::create(dest_path)
+/// GET /file/{filename}/stream - chunked response so media can start playing early
+async fn stream_file(
+    State(state): State<SharedServerState>,
+    Path(filename): Path<String>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Same file contents as `serve_file`, so the same PIN and ACL checks
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+    let file_path = match shared_file_path(&state.shared_dir, &filename) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if let Err(rejection) =
+        check_servable_path(&state.shared_dir, &file_path, state.config.follow_symlinks).await
+    {
+        return rejection.into_response();
+    }
+
+    let file = match fs::File::open(&file_path).await {
+        Ok(f) => f,
+        Err(_) => {
+            return (StatusCode::NOT_FOUND, format!("File not found: {}", filename)).into_response()
+        }
+    };
+
+    println!("[Server] [{}] Streaming file: {}", request_id, filename);
+    let size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    state.stats.record_served(size);
+    let stream = ReaderStream::with_capacity(file, READ_CHUNK_SIZE);
+    (
+        [
+            (header::CACHE_CONTROL, "no-cache"),
+            // Ask reverse proxies not to buffer the whole response
+            (HeaderName::from_static("x-accel-buffering"), "no"),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+/// PATCH /file/{filename} - overwrite the byte range given by `Content-Range`
+///
+/// Needs the same PIN token and ACL entry as downloading the file.
+async fn patch_file(
+    State(state): State<SharedServerState>,
+    Path(filename): Path<String>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        println!("[Server] [{}] Rejected patch of {} from {}: bad token", request_id, filename, remote.ip());
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        println!("[Server] [{}] Rejected patch of {} from {}: not in ACL", request_id, filename, remote.ip());
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+
+    let file_path = match shared_file_path(&state.shared_dir, &filename) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let Some((start, end)) = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing or invalid Content-Range").into_response();
+    };
+    if body.len() as u64 != end - start + 1 {
+        return (StatusCode::BAD_REQUEST, "Body length does not match Content-Range").into_response();
+    }
+
+    if let Err(rejection) =
+        check_servable_path(&state.shared_dir, &file_path, state.config.follow_symlinks).await
+    {
+        return rejection.into_response();
+    }
+    let mut file = match fs::OpenOptions::new().write(true).open(&file_path).await {
+        Ok(f) => f,
+        Err(_) => {
+            return (StatusCode::NOT_FOUND, format!("File not found: {}", filename)).into_response()
+        }
+    };
+
+    let size = match file.metadata().await {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            eprintln!("[Server] [{}] Failed to read metadata for {}: {}", request_id, filename, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if end >= size {
+        return (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+        )
+            .into_response();
+    }
+
+    let written = async {
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        file.write_all(&body).await?;
+        file.flush().await
+    }
+    .await;
+
+    match written {
+        Ok(()) => {
+            println!(
+                "[Server] [{}] Patched {} (bytes {}-{})",
+                request_id, filename, start, end
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => {
+            eprintln!("[Server] [{}] Failed to patch {}: {}", request_id, filename, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to write file").into_response()
+        }
+    }
+}
+
+/// Body of `POST /file/{filename}/copy`
+#[derive(Debug, Deserialize)]
+struct CopyFileRequest {
+    new_name: String,
+}
+
+/// Query string of `POST /file/{filename}/copy`
+#[derive(Debug, Deserialize)]
+struct CopyFileQuery {
+    /// Replace `new_name` if it already exists
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// POST /file/{filename}/copy - duplicate a shared file without a download/upload round trip
+///
+/// Needs the same PIN token and ACL entry as downloading the file.
+async fn copy_file(
+    State(state): State<SharedServerState>,
+    Path(filename): Path<String>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<CopyFileQuery>,
+    Json(request): Json<CopyFileRequest>,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        println!("[Server] [{}] Rejected copy of {} from {}: bad token", request_id, filename, remote.ip());
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        println!("[Server] [{}] Rejected copy of {} from {}: not in ACL", request_id, filename, remote.ip());
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+
+    let peer_id = headers.get(acl::PEER_ID_HEADER).and_then(|v| v.to_str().ok());
+    match copy_shared_file(&state, &filename, &request.new_name, query.overwrite, peer_id).await {
+        Ok(new_name) => {
+            println!("[Server] [{}] Copied {} to {}", request_id, filename, new_name);
+            Json(serde_json::json!({ "copied_to": new_name })).into_response()
+        }
+        Err((status, message)) => (status, message).into_response(),
+    }
+}
+
+/// Copy `filename` to a sanitized `new_name` inside the shared folder,
+/// returning the name actually used
+///
+/// Without `overwrite` an existing destination is a `409 Conflict`. With it,
+/// `peer_id` must be on the destination's ACL, if it has one.
+pub async fn copy_shared_file(
+    state: &ServerState,
+    filename: &str,
+    new_name: &str,
+    overwrite: bool,
+    peer_id: Option<&str>,
+) -> Result<String, (StatusCode, String)> {
+    let src = shared_file_path(&state.shared_dir, filename)?;
+    let new_name = sanitize_filename(new_name)
+        .filter(|name| !name.ends_with(META_SUFFIX))
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Invalid new name: {}", new_name)))?;
+    // Copying a file onto itself would truncate it
+    if new_name == filename {
+        return Err((StatusCode::CONFLICT, format!("{} already exists", new_name)));
+    }
+
+    check_servable_path(&state.shared_dir, &src, state.config.follow_symlinks)
         .await
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+        .map_err(|(status, message)| (status, message.to_string()))?;
+
+    let dest = state.shared_dir.join(&new_name);
+    if overwrite {
+        // Being allowed to read the source says nothing about the destination
+        if !acl::is_allowed(&*state.file_acls.read().await, &new_name, peer_id) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Peer is not allowed to overwrite {}", new_name),
+            ));
+        }
+        // Never write through a link pointing out of the shared folder
+        if fs::symlink_metadata(&dest).await.is_ok() {
+            check_servable_path(&state.shared_dir, &dest, state.config.follow_symlinks)
+                .await
+                .map_err(|(status, message)| (status, message.to_string()))?;
+        }
+    } else {
+        // Claim the name first so a concurrent copy or upload can't be clobbered
+        match fs::OpenOptions::new().write(true).create_new(true).open(&dest).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err((StatusCode::CONFLICT, format!("{} already exists", new_name)));
+            }
+            Err(e) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to create {}: {}", new_name, e),
+                ));
+            }
+        }
+    }
+
+    if let Err(e) = fs::copy(&src, &dest).await {
+        if !overwrite {
+            let _ = fs::remove_file(&dest).await;
+        }
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to copy {}: {}", filename, e),
+        ));
+    }
+    Ok(new_name)
+}
+
+/// Parse `bytes <start>-<end>/<total>` (total may be `*`) into inclusive offsets
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, _total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end): (u64, u64) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start <= end).then_some((start, end))
+}
+
+/// Result of reading a `Range` request
+enum RangeRead {
+    Partial {
+        start: u64,
+        end: u64,
+        total: u64,
+        body: Body,
+    },
+    Unsatisfiable {
+        total: u64,
+    },
+}
+
+/// Parse a single `bytes=<start>-<end>` range (either bound may be omitted)
+/// into inclusive offsets within a file of `total` bytes
+fn parse_byte_range(range: &str, total: u64) -> Option<(u64, u64)> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let (start, end) = if start.is_empty() {
+        // `bytes=-N` is the last N bytes
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+        (total.saturating_sub(suffix), total.checked_sub(1)?)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() {
+            total.checked_sub(1)?
+        } else {
+            end.parse::<u64>().ok()?.min(total.checked_sub(1)?)
+        };
+        (start, end)
+    };
+
+    (start <= end && end < total).then_some((start, end))
+}
+
+/// Read the requested byte range of a file
+async fn read_range(path: &std::path::Path, range: &str) -> std::io::Result<RangeRead> {
+    let mut file = fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+
+    let Some((start, end)) = parse_byte_range(range, total) else {
+        return Ok(RangeRead::Unsatisfiable { total });
+    };
+
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let stream = ReaderStream::with_capacity(file.take(end - start + 1), READ_CHUNK_SIZE);
+
+    Ok(RangeRead::Partial {
+        start,
+        end,
+        total,
+        body: Body::from_stream(stream),
+    })
+}
+
+/// GET /checksum/{filename} - SHA-256 and size, for verifying a finished download
+async fn serve_checksum(
+    State(state): State<SharedServerState>,
+    Path(filename): Path<String>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Describes the file's contents, so the same PIN and ACL checks as `serve_file`
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+    let file_path = match shared_file_path(&state.shared_dir, &filename) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if let Err(rejection) =
+        check_servable_path(&state.shared_dir, &file_path, state.config.follow_symlinks).await
+    {
+        return rejection.into_response();
+    }
+
+    let size = match fs::metadata(&file_path).await {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => return (StatusCode::NOT_FOUND, format!("File not found: {}", filename)).into_response(),
+    };
+    match sha256_file(&file_path).await {
+        Ok(sha256) => Json(serde_json::json!({ "sha256": sha256, "size": size })).into_response(),
+        Err(e) => {
+            eprintln!("[Server] [{}] Failed to hash file {}: {}", request_id, filename, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash file").into_response()
+        }
+    }
+}
+
+/// Serve the metadata stored alongside an uploaded file
+async fn serve_file_meta(
+    State(state): State<SharedServerState>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !peer_allowed(&state, &filename, &headers).await {
+        return (StatusCode::FORBIDDEN, "Peer is not allowed to access this file").into_response();
+    }
+    if let Err(rejection) = shared_file_path(&state.shared_dir, &filename) {
+        return rejection.into_response();
+    }
+    match read_file_meta(&state.shared_dir, &filename).await {
+        Ok(meta) => Json(meta).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+/// Read `<filename>.meta.json` from `dir`
+pub async fn read_file_meta(
+    dir: &std::path::Path,
+    filename: &str,
+) -> Result<serde_json::Value, String> {
+    if !is_plain_filename(filename) {
+        return Err(format!("Invalid filename: {}", filename));
+    }
 
-    file.write_all(&bytes)
+    let json = fs::read_to_string(dir.join(format!("{}{}", filename, META_SUFFIX)))
         .await
-        .map_err(|e| format!("Failed to write file: {}", e))?;
+        .map_err(|_| format!("No metadata for {}", filename))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid metadata for {}: {}", filename, e))
+}
 
-    println!("[Server] Download complete: {}", dest_path);
-    Ok(())
+/// Progress of an incoming upload, sent as the `upload-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub filename: String,
+    pub bytes_received: u64,
+    /// `Content-Length` of the whole multipart request, if the client sent one
+    pub total_bytes: Option<u64>,
+}
+
+/// A file saved by `/upload`
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedUpload {
+    pub path: String,
+    pub size: u64,
+}
+
+/// GET /events - server events such as `upload-progress`, as Server-Sent Events
+///
+/// Each event's name is the SSE event type and its payload the JSON data.
+/// Clients too slow to keep up skip the events they missed.
+async fn server_events(
+    State(state): State<SharedServerState>,
+) -> Sse<impl futures_util::Stream<Item = Result<SseEvent, std::convert::Infallible>>> {
+    let receiver = state.events.subscribe();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = SseEvent::default()
+                        .event(event.name)
+                        .data(event.payload.to_string());
+                    return Some((Ok(sse), receiver));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Accept a multipart upload into the shared directory
+///
+/// Files are streamed to disk, with `upload-progress` events every
+/// `UPLOAD_PROGRESS_INTERVAL` bytes. An optional `X-AirShare-Meta` field
+/// holds a JSON object that is stored as `<filename>.meta.json` next to
+/// each uploaded file. Responds with `{ path, size }` for a single file, or
+/// an array of them when several were uploaded. Once a PIN is set, uploads
+/// need the matching `X-AirShare-Token` like downloads do. Each file must
+/// match a transfer request the user accepted, named in
+/// `X-AirShare-Transfer-Request`, down to its size.
+async fn handle_upload(
+    State(state): State<SharedServerState>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        println!("[Server] [{}] Rejected upload from {}: bad token", request_id, remote.ip());
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    let total_bytes = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let max_size = state.max_upload_size_bytes.load(Ordering::Relaxed);
+    if total_bytes.is_some_and(|total| total > max_size) {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Upload is larger than {} bytes", max_size),
+        )
+            .into_response();
+    }
+    let peer_id = headers
+        .get(acl::PEER_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let transfer_request = headers
+        .get(transfer_requests::TRANSFER_REQUEST_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let mut saved = Vec::new();
+    let mut meta: Option<serde_json::Value> = None;
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+
+        if field.name() == Some(META_FIELD) {
+            let text = match field.text().await {
+                Ok(text) => text,
+                Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            };
+            match serde_json::from_str::<serde_json::Value>(&text) {
+                Ok(value) if value.is_object() => meta = Some(value),
+                _ => {
+                    return (StatusCode::BAD_REQUEST, "Metadata must be a JSON object")
+                        .into_response()
+                }
+            }
+            continue;
+        }
+
+        let Some(filename) = field.file_name().map(|n| n.to_string()) else {
+            continue;
+        };
+        let accepted_size = match check_incoming_file(&state, &filename, transfer_request).await {
+            Ok(size) => size,
+            Err((status, message)) => {
+                println!("[Server] [{}] Rejected upload from {}: {}", request_id, remote.ip(), message);
+                return (status, message).into_response();
+            }
+        };
+
+        let Some(path) =
+            resolve_collision(&state.shared_dir, &filename, state.config.file_collision_strategy)
+        else {
+            return (StatusCode::CONFLICT, format!("File already exists: {}", filename))
+                .into_response();
+        };
+        // Renaming may have picked a different name
+        let filename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(filename);
+        let emit_progress = |bytes_received: u64| {
+            let progress = UploadProgress {
+                filename: filename.clone(),
+                bytes_received,
+                total_bytes,
+            };
+            state.emit("upload-progress", serde_json::to_value(progress).unwrap_or_default());
+        };
+        let mut last_reported = 0;
+        let on_progress = |bytes_received: u64| {
+            // Per-chunk events would overflow the event channel on fast links
+            if bytes_received - last_reported >= UPLOAD_PROGRESS_INTERVAL {
+                last_reported = bytes_received;
+                emit_progress(bytes_received);
+            }
+        };
+        let started_at = history::now_ms();
+        // The limit covers the whole request, not each file
+        let received: u64 = saved.iter().map(|(_, size, _)| *size).sum();
+        let limit = max_size.saturating_sub(received).min(accepted_size);
+        match save_field(field, &path, limit, on_progress).await {
+            Ok(size) if size != accepted_size => {
+                let _ = fs::remove_file(&path).await;
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("{} is {} bytes, but {} were accepted", filename, size, accepted_size),
+                )
+                    .into_response();
+            }
+            Ok(size) => {
+                emit_progress(size);
+                println!("[Server] [{}] Received upload: {} ({} bytes)", request_id, filename, size);
+                state.stats.record_upload(size);
+
+                let log = format!("[Server] [{}]", request_id);
+                if !process_received_file(&state, &log, &filename, &path).await {
+                    continue;
+                }
+
+                saved.push((filename, size, started_at));
+            }
+            Err(e) => {
+                eprintln!("[Server] [{}] Upload of {} failed: {}", request_id, filename, e);
+                let mut record = received_record(&filename, meta.as_ref(), peer_id.clone(), started_at);
+                record.status = history::TransferStatus::Failed;
+                if let Err(e) = history::record(record) {
+                    eprintln!("[Server] [{}] Failed to record {} in history: {}", request_id, filename, e);
+                }
+                if e == UPLOAD_TOO_LARGE {
+                    return (StatusCode::PAYLOAD_TOO_LARGE, e).into_response();
+                }
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        }
+    }
+
+    for (filename, size, started_at) in &saved {
+        let mut record = received_record(filename, meta.as_ref(), peer_id.clone(), *started_at);
+        record.size_bytes = *size;
+        state.emit(
+            "file-received",
+            serde_json::json!({
+                "filename": filename,
+                "size": size,
+                "sender_name": record.peer_name,
+            }),
+        );
+        if let Err(e) = history::record(record) {
+            eprintln!("[Server] [{}] Failed to record {} in history: {}", request_id, filename, e);
+        }
+    }
+
+    if let Some(meta) = meta {
+        for (filename, _, _) in &saved {
+            let meta_path = state.shared_dir.join(format!("{}{}", filename, META_SUFFIX));
+            let json = serde_json::to_string_pretty(&meta).unwrap_or_default();
+            if let Err(e) = fs::write(&meta_path, json).await {
+                eprintln!("[Server] [{}] Failed to write metadata for {}: {}", request_id, filename, e);
+            }
+        }
+    }
+
+    let mut saved: Vec<SavedUpload> = saved
+        .into_iter()
+        .map(|(filename, size, _)| SavedUpload {
+            path: state.shared_dir.join(filename).to_string_lossy().to_string(),
+            size,
+        })
+        .collect();
+    if saved.len() == 1 {
+        return Json(saved.remove(0)).into_response();
+    }
+    Json(saved).into_response()
+}
+
+/// Checks `/upload` runs on each incoming file before writing it: a plain
+/// name, an allowed type and a transfer request the user accepted
+///
+/// Returns the size the accepted request announced, or the status and message
+/// to refuse the file with.
+async fn check_incoming_file(
+    state: &ServerState,
+    filename: &str,
+    transfer_request: Option<&str>,
+) -> Result<u64, (StatusCode, String)> {
+    if !is_plain_filename(filename) || has_path_syntax(filename) || filename.ends_with(META_SUFFIX) {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid filename: {}", filename)));
+    }
+    if !state.accepts_upload(filename).await {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("File type not allowed: {}", filename),
+        ));
+    }
+    // Only files the user accepted through `/request-transfer` get in
+    transfer_request
+        .and_then(|id| state.transfer_requests.take_accepted(id, filename))
+        .ok_or_else(|| (StatusCode::FORBIDDEN, format!("Transfer of {} was not accepted", filename)))
+}
+
+/// Run the post-upload processors on a received file, quarantining it if one
+/// flags it
+///
+/// Returns false when the file is no longer in the shared folder.
+async fn process_received_file(
+    state: &ServerState,
+    log: &str,
+    filename: &str,
+    path: &std::path::Path,
+) -> bool {
+    let ProcessResult::Quarantine(reason) =
+        processors::run_processors(&state.processors, filename, path).await
+    else {
+        return true;
+    };
+
+    match processors::quarantine(&state.shared_dir, filename).await {
+        Ok(_) => {
+            println!("{} Quarantined {}: {}", log, filename, reason);
+            state.emit(
+                "file-quarantined",
+                serde_json::json!({ "filename": filename, "reason": reason }),
+            );
+        }
+        Err(e) => {
+            // Never leave a flagged file where peers can fetch it
+            eprintln!("{} {}", log, e);
+            let _ = fs::remove_file(path).await;
+        }
+    }
+    false
+}
+
+/// History entry for a file received through `/upload`, before its outcome is known
+///
+/// The sender's name and tags come from the upload metadata, if it was sent first.
+fn received_record(
+    filename: &str,
+    meta: Option<&serde_json::Value>,
+    peer_id: Option<String>,
+    started_at: u64,
+) -> history::TransferRecord {
+    history::TransferRecord {
+        filename: filename.to_string(),
+        peer_name: meta
+            .and_then(|meta| meta.get("sender_name"))
+            .and_then(|name| name.as_str())
+            .map(|name| name.to_string()),
+        peer_id,
+        direction: history::Direction::Received,
+        started_at,
+        tags: meta.map(meta_tags).unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Stream a multipart field (or any other chunk stream) to `path` chunk by
+/// chunk, returning the bytes written
+///
+/// `on_progress` gets the running total after every chunk. Past `limit`
+/// bytes the partial file is deleted and `UPLOAD_TOO_LARGE` returned.
+pub async fn save_field<S, E, F>(
+    mut field: S,
+    path: &std::path::Path,
+    limit: u64,
+    mut on_progress: F,
+) -> Result<u64, String>
+where
+    S: futures_util::Stream<Item = Result<axum::body::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+    F: FnMut(u64),
+{
+    let mut file = fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    let mut written = 0u64;
+    while let Some(chunk) = field
+        .next()
+        .await
+        .transpose()
+        .map_err(|e| format!("Failed to read upload: {}", e))?
+    {
+        written += chunk.len() as u64;
+        if written > limit {
+            drop(file);
+            let _ = fs::remove_file(path).await;
+            return Err(UPLOAD_TOO_LARGE.to_string());
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        on_progress(written);
+    }
+
+    Ok(written)
+}
+
+/// Query string of `GET /files`
+#[derive(Debug, Deserialize)]
+struct ListFilesQuery {
+    /// Only list files whose metadata carries this tag
+    tag: Option<String>,
+    /// Walk subdirectories and return entry objects instead of names
+    #[serde(default)]
+    recursive: bool,
+    /// Maximum subdirectory depth when `recursive` is set
+    depth: Option<u32>,
+    /// Only return directories (recursive listings only)
+    #[serde(default)]
+    dirs_only: bool,
+}
+
+/// Tags listed under `"tags"` in an upload's metadata
+pub fn meta_tags(meta: &serde_json::Value) -> Vec<String> {
+    meta.get("tags")
+        .and_then(|tags| tags.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str().map(|t| t.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// List the files in the shared directory as `FileInfo` objects, optionally
+/// filtered by `?tag=`
+///
+/// `?recursive=true` walks subdirectories (up to `?depth=`) and returns
+/// entry objects; `?dirs_only=true` limits that walk to directories.
+async fn list_files(
+    State(state): State<SharedServerState>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Query(query): Query<ListFilesQuery>,
+) -> impl IntoResponse {
+    if query.recursive {
+        let depth = query.depth.unwrap_or(MAX_LIST_DEPTH);
+        return match list_dir_recursive(&state.shared_dir, depth, query.dirs_only, state.config.follow_symlinks).await {
+            Ok(entries) => Json(entries).into_response(),
+            Err(e) => {
+                eprintln!("[Server] [{}] {}", request_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list files").into_response()
+            }
+        };
+    }
+
+    match list_dir_files(&state.shared_dir, state.config.follow_symlinks).await {
+        Ok(files) => {
+            let Some(tag) = query.tag else {
+                return Json(files).into_response();
+            };
+
+            let mut tagged = Vec::new();
+            for file in files {
+                if let Ok(meta) = read_file_meta(&state.shared_dir, &file.name).await {
+                    if meta_tags(&meta).contains(&tag) {
+                        tagged.push(file);
+                    }
+                }
+            }
+            Json(tagged).into_response()
+        }
+        Err(e) => {
+            eprintln!("[Server] [{}] Failed to list shared directory: {}", request_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list files").into_response()
+        }
+    }
+}
+
+/// The regular files directly inside `dir`, sorted by name
+///
+/// Symlinks are listed only with `follow_symlinks`, and only when they point
+/// at a file inside `dir`; their size and date are the target's.
+pub async fn list_dir_files(
+    dir: &std::path::Path,
+    follow_symlinks: bool,
+) -> std::io::Result<Vec<FileInfo>> {
+    let mut entries = fs::read_dir(dir).await?;
+
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let is_file = match entry.file_type().await {
+            Ok(t) if t.is_symlink() => {
+                follow_symlinks
+                    && resolves_within(dir, &entry.path()).await
+                    && fs::metadata(entry.path()).await.map(|m| m.is_file()).unwrap_or(false)
+            }
+            Ok(t) => t.is_file(),
+            Err(_) => false,
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Metadata sidecars are an implementation detail, not shared files
+        if !is_file || name.ends_with(META_SUFFIX) {
+            continue;
+        }
+
+        let Ok(meta) = fs::metadata(entry.path()).await else {
+            continue;
+        };
+        let modified_unix = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        files.push(FileInfo {
+            mime: mime_guess::from_path(&name).first_or_octet_stream().to_string(),
+            name,
+            size_bytes: meta.len(),
+            modified_unix,
+        });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(files)
+}
+
+/// True if `path` canonicalizes to somewhere inside `root`
+pub async fn resolves_within(root: &std::path::Path, path: &std::path::Path) -> bool {
+    match (fs::canonicalize(root).await, fs::canonicalize(path).await) {
+        (Ok(root), Ok(resolved)) => resolved.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Path of `filename` inside `root`, refusing names that could leave it
+///
+/// Every route taking a filename from the URL goes through this before
+/// touching the file system, so `..` and separators never reach `join`.
+/// `check_servable_path` then catches symlinks that escape.
+pub fn shared_file_path(root: &std::path::Path, filename: &str) -> Result<PathBuf, (StatusCode, String)> {
+    if !is_plain_filename(filename) {
+        return Err((StatusCode::FORBIDDEN, format!("Invalid filename: {}", filename)));
+    }
+    Ok(root.join(filename))
+}
+
+/// Check that a path about to be served stays inside `root`
+///
+/// Symlinks are refused unless `follow_symlinks` is set, and targets outside
+/// `root` are refused regardless of config.
+pub async fn check_servable_path(
+    root: &std::path::Path,
+    path: &std::path::Path,
+    follow_symlinks: bool,
+) -> Result<(), (StatusCode, &'static str)> {
+    let meta = fs::symlink_metadata(path)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "File not found"))?;
+    if meta.file_type().is_symlink() && !follow_symlinks {
+        return Err((StatusCode::NOT_FOUND, "File not found"));
+    }
+    if !resolves_within(root, path).await {
+        return Err((StatusCode::FORBIDDEN, "File is outside the shared folder"));
+    }
+    Ok(())
+}
+
+/// Entries under `root` up to `depth` levels deep, as
+/// `{ name, relative_path, size_bytes, mime_type, is_dir, is_symlink }` objects
+///
+/// With `follow_symlinks`, links are walked into but anything resolving
+/// outside `root` is skipped. Without it, links are listed as
+/// `is_symlink: true` with `target_accessible` telling whether they point
+/// inside `root`. Metadata sidecars and the quarantine directory are hidden.
+pub async fn list_dir_recursive(
+    root: &std::path::Path,
+    depth: u32,
+    dirs_only: bool,
+    follow_symlinks: bool,
+) -> Result<Vec<serde_json::Value>, String> {
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || walk_dir_entries(&root, depth, dirs_only, follow_symlinks))
+        .await
+        .map_err(|e| format!("Directory walk failed: {}", e))?
+}
+
+fn walk_dir_entries(
+    root: &std::path::Path,
+    depth: u32,
+    dirs_only: bool,
+    follow_symlinks: bool,
+) -> Result<Vec<serde_json::Value>, String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve shared directory: {}", e))?;
+    let within_root = |path: &std::path::Path| {
+        path.canonicalize()
+            .map(|resolved| resolved.starts_with(&root))
+            .unwrap_or(false)
+    };
+
+    let walker = walkdir::WalkDir::new(&root)
+        .min_depth(1)
+        .max_depth(depth.min(MAX_LIST_DEPTH) as usize)
+        .follow_links(follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        // Prune followed links that escape the shared folder before descending
+        .filter_entry(|entry| {
+            entry.file_name() != processors::QUARANTINE_DIR
+                && !(follow_symlinks && entry.path_is_symlink() && !within_root(entry.path()))
+        });
+
+    let mut entries = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("[Server] Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+
+        let is_dir = entry.file_type().is_dir();
+        let is_symlink = entry.path_is_symlink();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if (dirs_only && !is_dir) || name.ends_with(META_SUFFIX) {
+            continue;
+        }
+        let target_accessible = within_root(entry.path());
+        if !is_symlink && !target_accessible {
+            continue;
+        }
+
+        let Ok(relative) = entry.path().strip_prefix(&root) else {
+            continue;
+        };
+        let relative_path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let size_bytes = if is_dir || (is_symlink && !follow_symlinks) {
+            0
+        } else {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        };
+        let mime_type = (!is_dir)
+            .then(|| mime_guess::from_path(entry.path()).first_or_octet_stream().to_string());
+
+        let mut value = serde_json::json!({
+            "name": name,
+            "relative_path": relative_path,
+            "size_bytes": size_bytes,
+            "mime_type": mime_type,
+            "is_dir": is_dir,
+            "is_symlink": is_symlink,
+        });
+        if is_symlink {
+            value["target_accessible"] = serde_json::Value::Bool(target_accessible);
+        }
+        entries.push(value);
+    }
+
+    Ok(entries)
+}
+
+/// True if `name` is a single path component (no separators, `..` or root)
+pub fn is_plain_filename(name: &str) -> bool {
+    std::path::Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name)
+}
+
+/// Reduce an untrusted name to a file name that is safe to create in the
+/// shared folder, or `None` if nothing usable is left
+///
+/// Directory parts are dropped, control characters and characters Windows
+/// forbids become `_`, and surrounding spaces and trailing dots are trimmed.
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"|?*".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']);
+    (!cleaned.is_empty()).then(|| cleaned.to_string())
+}
+
+/// First free path for `filename` in `dir`, adding ` (1)`, ` (2)`, ... before
+/// the extension the way Finder and Explorer name copies
+pub fn resolve_unique_path(dir: &std::path::Path, filename: &str) -> PathBuf {
+    // symlink_metadata also sees dangling symlinks, which File::create would follow
+    let taken = |path: &std::path::Path| path.symlink_metadata().is_ok();
+    let path = dir.join(filename);
+    if !taken(&path) {
+        return path;
+    }
+
+    let original = std::path::Path::new(filename);
+    let stem = original.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let extension = original
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1u32..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or(path)
+}
+
+/// Where to save an incoming file called `name` in `dir` under `strategy`
+///
+/// `Rename` picks a free name with `resolve_unique_path`; `Reject` returns
+/// `None` if `name` is already taken. Both claim the returned path by
+/// creating it empty, so concurrent uploads of one name never share a file.
+pub fn resolve_collision(dir: &std::path::Path, name: &str, strategy: CollisionStrategy) -> Option<PathBuf> {
+    let taken = |path: &std::path::Path| {
+        let claimed = std::fs::OpenOptions::new().write(true).create_new(true).open(path);
+        matches!(claimed, Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+    };
+    match strategy {
+        CollisionStrategy::Overwrite => Some(dir.join(name)),
+        // Another upload may claim the free name first; then look again
+        CollisionStrategy::Rename => loop {
+            let path = resolve_unique_path(dir, name);
+            if !taken(&path) {
+                return Some(path);
+            }
+        },
+        CollisionStrategy::Reject => {
+            let path = dir.join(name);
+            (!taken(&path)).then_some(path)
+        }
+    }
+}
+
+/// Compute the hex SHA-256 of a file without loading it into memory
+pub async fn sha256_file(path: &std::path::Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Health check endpoint
+async fn health_check() -> &'static str {
+    "AirShare Server OK"
+}
+
+/// POST /text - store a plain-text snippet from a peer and emit `text-received`
+///
+/// Needs the PIN token once a PIN is set, like `GET /text`.
+async fn receive_text(
+    State(state): State<SharedServerState>,
+    headers: HeaderMap,
+    text: String,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Invalid or missing token").into_response();
+    }
+    let is_plain_text = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/plain"));
+    if !is_plain_text {
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Expected text/plain").into_response();
+    }
+
+    let sender_id = headers
+        .get(acl::PEER_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    println!("[Server] Received text ({} bytes)", text.len());
+    *state.received_text.write().await = Some(text.clone());
+    state.emit(
+        "text-received",
+        serde_json::json!({ "text": text, "sender_id": sender_id }),
+    );
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// GET /text - the latest text received, or 204 if none has arrived
+///
+/// Like file downloads, needs the PIN token once a PIN is set.
+async fn serve_text(State(state): State<SharedServerState>, headers: HeaderMap) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        return (StatusCode::UNAUTHORIZED, "Invalid or missing token").into_response();
+    }
+    match state.received_text.read().await.clone() {
+        Some(text) => ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Query string of `GET /bandwidth`
+#[derive(Debug, Deserialize)]
+struct BandwidthQuery {
+    bytes: Option<u64>,
+}
+
+/// GET /bandwidth?bytes=N - stream N generated bytes for download speed tests
+async fn bandwidth_download(Query(query): Query<BandwidthQuery>) -> impl IntoResponse {
+    let total = query
+        .bytes
+        .unwrap_or(BANDWIDTH_TEST_BYTES)
+        .min(MAX_BANDWIDTH_TEST_BYTES);
+    let chunk = axum::body::Bytes::from(vec![0u8; READ_CHUNK_SIZE]);
+    let chunks = (0..total).step_by(READ_CHUNK_SIZE).map(move |offset| {
+        let len = (total - offset).min(READ_CHUNK_SIZE as u64) as usize;
+        Ok::<_, std::io::Error>(chunk.slice(..len))
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_LENGTH, total.to_string()),
+        ],
+        Body::from_stream(futures_util::stream::iter(chunks)),
+    )
+}
+
+/// POST /bandwidth - read and discard the body for upload speed tests
+async fn bandwidth_upload(body: Body) -> impl IntoResponse {
+    let mut stream = body.into_data_stream();
+    let mut received = 0u64;
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => received += chunk.len() as u64,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+        if received > MAX_BANDWIDTH_TEST_BYTES {
+            return (StatusCode::PAYLOAD_TOO_LARGE, "Bandwidth test too large").into_response();
+        }
+    }
+    Json(serde_json::json!({ "bytes_received": received })).into_response()
+}
+
+/// Lightweight liveness probe polled by mobile web clients
+async fn heartbeat(State(state): State<SharedServerState>) -> Json<serde_json::Value> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    state.last_heartbeat.store(timestamp, Ordering::Relaxed);
+
+    let peer_count = state.discovery.read().await.peers.len() as u32;
+
+    Json(serde_json::json!({
+        "timestamp": timestamp,
+        "peer_count": peer_count
+    }))
+}
+
+/// GET /stats - server counters for external monitoring
+async fn stats(State(state): State<SharedServerState>) -> impl IntoResponse {
+    let peer_count = state.discovery.read().await.peers.len();
+
+    let mut dir_size = 0u64;
+    let mut file_count = 0usize;
+    if let Ok(files) = list_dir_files(&state.shared_dir, state.config.follow_symlinks).await {
+        dir_size = files.iter().map(|file| file.size_bytes).sum();
+        file_count = files.len();
+    }
+
+    let stats = &state.stats;
+    let body = serde_json::json!({
+        "uptime_secs": stats.started_at.elapsed().as_secs(),
+        "files_served": stats.files_served.load(Ordering::Relaxed),
+        "bytes_served": stats.bytes_served.load(Ordering::Relaxed),
+        "uploads_accepted": stats.uploads_accepted.load(Ordering::Relaxed),
+        "bytes_uploaded": stats.bytes_uploaded.load(Ordering::Relaxed),
+        "active_connections": stats.active_connections.load(Ordering::Relaxed),
+        "peer_count": peer_count,
+        "shared_dir_size_bytes": dir_size,
+        "shared_dir_file_count": file_count,
+    });
+    ([(header::CACHE_CONTROL, "no-cache")], Json(body))
+}
+
+/// Error returned by `download_file` when its token is cancelled
+pub const DOWNLOAD_CANCELLED: &str = "Download cancelled";
+
+/// Whether `ip` is on the local network: RFC 1918 private or RFC 3927
+/// link-local IPv4, or IPv6 unique-local (fc00::/7) or link-local (fe80::/10)
+fn is_local_network_ip(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => ip.is_private() || ip.is_link_local(),
+        std::net::IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => ip.is_private() || ip.is_link_local(),
+            None => (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80,
+        },
+    }
+}
+
+/// Parse a download URL, refusing anything but http(s) on the local network
+///
+/// Downloads come from peers, so this keeps the renderer from reading local
+/// files (`file://`) or reaching hosts on the internet. Host names must
+/// resolve only to local network addresses.
+pub async fn validate_download_url(url: &str) -> Result<reqwest::Url, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Only http/https URLs are allowed".to_string());
+    }
+
+    let host = parsed.host_str().unwrap_or_default();
+    // IPv6 hosts keep their brackets in URLs
+    let addrs: Vec<std::net::IpAddr> = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => vec![ip],
+        Err(_) if host.is_empty() => Vec::new(),
+        Err(_) => {
+            let port = parsed.port_or_known_default().unwrap_or(80);
+            tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+                .map(|addr| addr.ip())
+                .collect()
+        }
+    };
+    if addrs.is_empty() || !addrs.iter().all(|ip| is_local_network_ip(*ip)) {
+        return Err(format!("Only local network hosts are allowed: {}", url));
+    }
+    Ok(parsed)
+}
+
+/// Progress of an incoming download
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub bytes_downloaded: u64,
+    /// From `Content-Length`, if the server sent one
+    pub total_bytes: Option<u64>,
+    pub metrics: TransferMetrics,
+}
+
+/// Speed and timing of a running transfer
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TransferMetrics {
+    /// Average rate over the last `METRICS_WINDOW`
+    pub bytes_per_second: f64,
+    /// Seconds since the transfer started
+    pub elapsed_secs: f64,
+    /// Seconds left at the current rate; `None` when the total size is unknown
+    pub eta_secs: Option<f64>,
+}
+
+/// Span of recent chunks `TransferMetrics::bytes_per_second` averages over
+const METRICS_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Rolling byte counts behind `TransferMetrics`
+struct TransferMeter {
+    started_at: std::time::Instant,
+    /// (time, bytes so far) after each chunk; the front is the window's baseline
+    samples: VecDeque<(std::time::Instant, u64)>,
+}
+
+impl TransferMeter {
+    fn start() -> Self {
+        let started_at = std::time::Instant::now();
+        Self {
+            started_at,
+            samples: VecDeque::from([(started_at, 0)]),
+        }
+    }
+
+    /// Record that `bytes_done` bytes have been written and measure the rate
+    fn record(&mut self, bytes_done: u64, total_bytes: Option<u64>) -> TransferMetrics {
+        let now = std::time::Instant::now();
+        self.samples.push_back((now, bytes_done));
+        // Keep one sample at or before the window start as the baseline
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= METRICS_WINDOW {
+            self.samples.pop_front();
+        }
+
+        let (since, bytes_before) = self.samples[0];
+        let window_secs = now.duration_since(since).as_secs_f64().max(f64::EPSILON);
+        let bytes_per_second = bytes_done.saturating_sub(bytes_before) as f64 / window_secs;
+        let eta_secs = total_bytes
+            .map(|total| total.saturating_sub(bytes_done) as f64 / bytes_per_second)
+            .filter(|eta| eta.is_finite());
+        TransferMetrics {
+            bytes_per_second,
+            elapsed_secs: now.duration_since(self.started_at).as_secs_f64(),
+            eta_secs,
+        }
+    }
+}
+
+/// Download a file from a URL and save to destination,
+/// writing it chunk by chunk and reporting progress after every chunk
+///
+/// zstd is always offered; a `Content-Encoding: zstd` response is decoded
+/// before writing, so progress counts decompressed bytes. With
+/// `expected_sha256`, the saved file is hashed afterwards and deleted if it
+/// does not match. Cancelling `cancel` stops the download and deletes the
+/// partial file. Only URLs passing `validate_download_url` are fetched.
+pub async fn download_file<F>(
+    url: &str,
+    dest_path: &str,
+    expected_sha256: Option<&str>,
+    credentials: Option<&PeerCredentials>,
+    cancel: Option<&CancellationToken>,
+    on_progress: F,
+) -> Result<(), String>
+where
+    F: Fn(DownloadProgress),
+{
+    validate_download_url(url).await?;
+    println!("[Server] Downloading: {} -> {}", url, dest_path);
+
+    let cancelled = || async {
+        match cancel {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    // A redirect could lead past the checks above
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("HTTP client error: {}", e))?;
+    let mut request = client
+        .get(url)
+        .header(reqwest::header::ACCEPT_ENCODING, ZSTD_ENCODING);
+    if let Some(credentials) = credentials {
+        request = credentials.apply(request);
+    }
+    let request = request.send();
+    let response = tokio::select! {
+        response = request => response.map_err(|e| format!("HTTP request failed: {}", e))?,
+        _ = cancelled() => return Err(DOWNLOAD_CANCELLED.to_string()),
+    };
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    let compressed = response
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case(ZSTD_ENCODING));
+    // A compressed body's Content-Length says nothing about the final size
+    let total_bytes = if compressed { None } else { response.content_length() };
+    let mut file = fs::File::create(dest_path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    let body = StreamReader::new(
+        response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(std::io::Error::other)),
+    );
+    let mut reader: Pin<Box<dyn AsyncRead + Send>> = if compressed {
+        Box::pin(ZstdDecoder::new(body))
+    } else {
+        Box::pin(body)
+    };
+
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+    let mut bytes_downloaded = 0u64;
+    let mut meter = TransferMeter::start();
+    loop {
+        let n = tokio::select! {
+            read = reader.read(&mut buf) => read.map_err(|e| format!("Failed to read response: {}", e))?,
+            _ = cancelled() => {
+                drop(file);
+                let _ = fs::remove_file(dest_path).await;
+                println!("[Server] Download cancelled: {}", dest_path);
+                return Err(DOWNLOAD_CANCELLED.to_string());
+            }
+        };
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+
+        bytes_downloaded += n as u64;
+        on_progress(DownloadProgress {
+            url: url.to_string(),
+            bytes_downloaded,
+            total_bytes,
+            metrics: meter.record(bytes_downloaded, total_bytes),
+        });
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    drop(file);
+
+    if let Some(expected) = expected_sha256 {
+        let actual = sha256_file(std::path::Path::new(dest_path)).await?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            let _ = fs::remove_file(dest_path).await;
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                dest_path, expected, actual
+            ));
+        }
+    }
+
+    println!("[Server] Download complete: {}", dest_path);
+    Ok(())
+}
+
+/// `http://ip:port` of a peer's server, with IPv6 addresses in brackets
+pub fn peer_base_url(ip: &str, port: u16) -> String {
+    match ip.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V6(ip)) => format!("http://[{}]:{}", ip, port),
+        _ => format!("http://{}:{}", ip, port),
+    }
+}
+
+/// `/file/{filename}` on a peer's server, with `filename` percent-encoded
+pub fn peer_file_url(ip: &str, port: u16, filename: &str) -> Result<reqwest::Url, String> {
+    let base = format!("{}/file/", peer_base_url(ip, port));
+    let mut url = reqwest::Url::parse(&base).map_err(|e| format!("Invalid URL {}: {}", base, e))?;
+    url.path_segments_mut()
+        .map_err(|_| format!("Invalid URL {}", base))?
+        .pop_if_empty()
+        .push(filename);
+    Ok(url)
+}
+
+/// Ask a peer's server whether it is sharing `filename`
+pub async fn peer_has_file(peer_ip: &str, port: u16, filename: &str) -> Result<bool, String> {
+    let url = format!("{}/files", peer_base_url(peer_ip, port));
+
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    let files: Vec<serde_json::Value> = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid file list: {}", e))?;
+
+    // Older peers list bare names, newer ones `FileInfo` objects
+    Ok(files.iter().any(|f| {
+        f.as_str().or_else(|| f.get("name").and_then(|n| n.as_str())) == Some(filename)
+    }))
+}
+
+/// Fetch a peer's SHA-256 for `filename` via HEAD, `None` if it doesn't have it
+pub async fn peer_file_hash(
+    peer_ip: &str,
+    port: u16,
+    filename: &str,
+    credentials: &PeerCredentials,
+) -> Result<Option<String>, String> {
+    let url = peer_file_url(peer_ip, port, filename)?;
+
+    let response = credentials
+        .apply(reqwest::Client::new().head(url))
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    Ok(response
+        .headers()
+        .get(CONTENT_SHA256_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string()))
+}
+
+/// Send a text snippet to a peer's `POST /text`
+pub async fn send_text(
+    peer_ip: &str,
+    port: u16,
+    text: &str,
+    credentials: &PeerCredentials,
+) -> Result<(), String> {
+    let url = format!("{}/text", peer_base_url(peer_ip, port));
+    let request = reqwest::Client::new()
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(text.to_string());
+
+    let response = credentials
+        .apply(request)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Result of `measure_bandwidth`
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthResult {
+    pub upload_kbps: f64,
+    pub download_kbps: f64,
+    /// HTTP round trip on an open connection
+    pub latency_ms: u64,
+}
+
+/// Kilobits per second for `bytes` moved in `elapsed`
+fn kbps(bytes: u64, elapsed: std::time::Duration) -> f64 {
+    bytes as f64 * 8.0 / 1000.0 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+/// Time a `BANDWIDTH_TEST_BYTES` upload and download against a peer's
+/// `/bandwidth` endpoint, plus the round trip of a `/health` request
+///
+/// Nothing is written to the peer's shared folder.
+pub async fn measure_bandwidth(peer_ip: &str, port: u16) -> Result<BandwidthResult, String> {
+    let base = peer_base_url(peer_ip, port);
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(BANDWIDTH_TEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let check = |response: reqwest::Response| {
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(format!("HTTP error: {}", response.status()))
+        }
+    };
+
+    // The first request opens the connection, so only the second is timed
+    client
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))
+        .and_then(check)?;
+    let started = std::time::Instant::now();
+    client
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))
+        .and_then(check)?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let payload = vec![0u8; BANDWIDTH_TEST_BYTES as usize];
+    let started = std::time::Instant::now();
+    client
+        .post(format!("{}/bandwidth", base))
+        .body(payload)
+        .send()
+        .await
+        .map_err(|e| format!("Upload test failed: {}", e))
+        .and_then(check)?;
+    let upload_kbps = kbps(BANDWIDTH_TEST_BYTES, started.elapsed());
+
+    let started = std::time::Instant::now();
+    let response = client
+        .get(format!("{}/bandwidth?bytes={}", base, BANDWIDTH_TEST_BYTES))
+        .send()
+        .await
+        .map_err(|e| format!("Download test failed: {}", e))
+        .and_then(check)?;
+    let downloaded = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Download test failed: {}", e))?
+        .len() as u64;
+    let download_kbps = kbps(downloaded, started.elapsed());
+
+    Ok(BandwidthResult {
+        upload_kbps,
+        download_kbps,
+        latency_ms,
+    })
+}
+
+/// Progress of an outgoing upload
+#[derive(Debug, Clone, Serialize)]
+pub struct SendProgress {
+    pub filename: String,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+}
+
+/// Upload a local file to a peer's `/upload` endpoint with JSON metadata attached,
+/// streaming it from disk and reporting progress after every chunk
+///
+/// `transfer_request` is the ID `request_transfer` returned once the peer accepted.
+pub async fn upload_file<F>(
+    peer_ip: &str,
+    port: u16,
+    local_path: &str,
+    meta: serde_json::Value,
+    credentials: &PeerCredentials,
+    transfer_request: Option<&str>,
+    on_progress: F,
+) -> Result<(), String>
+where
+    F: Fn(SendProgress) + Send + Sync + 'static,
+{
+    let path = std::path::Path::new(local_path);
+    let filename = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Invalid file path")?
+        .to_string();
+
+    let file = fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let total_bytes = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+
+    let on_progress = Arc::new(on_progress);
+    let progress_name = filename.clone();
+    let chunks = futures_util::stream::try_unfold((file, 0u64), move |(mut file, sent)| {
+        let on_progress = on_progress.clone();
+        let filename = progress_name.clone();
+        async move {
+            let mut buf = vec![0u8; READ_CHUNK_SIZE];
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                return Ok::<_, std::io::Error>(None);
+            }
+            buf.truncate(n);
+
+            let bytes_sent = sent + n as u64;
+            on_progress(SendProgress {
+                filename,
+                bytes_sent,
+                total_bytes,
+            });
+            Ok(Some((buf, (file, bytes_sent))))
+        }
+    });
+
+    let part = reqwest::multipart::Part::stream_with_length(
+        reqwest::Body::wrap_stream(chunks),
+        total_bytes,
+    )
+    .file_name(filename.clone());
+    let form = reqwest::multipart::Form::new()
+        .text(META_FIELD, meta.to_string())
+        .part("file", part);
+
+    let url = format!("{}/upload", peer_base_url(peer_ip, port));
+    println!("[Server] Uploading: {} -> {}", local_path, url);
+
+    let mut request = reqwest::Client::new().post(&url).multipart(form);
+    if let Some(id) = transfer_request {
+        request = request.header(transfer_requests::TRANSFER_REQUEST_HEADER, id);
+    }
+    let response = credentials
+        .apply(request)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    println!("[Server] Upload complete: {}", filename);
+    Ok(())
+}
+
+/// Overwrite `data.len()` bytes of a peer's file starting at `offset`
+pub async fn patch_file_range(
+    peer_ip: &str,
+    port: u16,
+    filename: &str,
+    offset: u64,
+    data: Vec<u8>,
+    credentials: &PeerCredentials,
+) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("No data to write".to_string());
+    }
+
+    let url = peer_file_url(peer_ip, port, filename)?;
+    let end = offset + data.len() as u64 - 1;
+
+    let request = reqwest::Client::new()
+        .patch(url)
+        .header(reqwest::header::CONTENT_RANGE, format!("bytes {}-{}/*", offset, end))
+        .body(data);
+    let response = credentials
+        .apply(request)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    match response.status() {
+        reqwest::StatusCode::NOT_FOUND => Err(format!("File not found: {}", filename)),
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE => {
+            Err(format!("Range {}-{} exceeds the size of {}", offset, end, filename))
+        }
+        status if !status.is_success() => Err(format!("HTTP error: {}", status)),
+        _ => Ok(()),
+    }
+}
+
+/// Request frame opening each QUIC stream, followed by the file bytes for uploads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuicRequest {
+    #[serde(flatten)]
+    pub method: QuicMethod,
+    /// Stands in for `X-AirShare-Peer-ID`
+    #[serde(default)]
+    pub peer_id: String,
+    /// Stands in for `X-AirShare-Token`
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum QuicMethod {
+    Get {
+        filename: String,
+    },
+    Upload {
+        filename: String,
+        /// Accepted `/request-transfer` ID, as in `X-AirShare-Transfer-Request`
+        #[serde(default)]
+        transfer_request: Option<String>,
+    },
+}
+
+impl QuicRequest {
+    fn new(method: QuicMethod, credentials: &PeerCredentials) -> Self {
+        Self {
+            method,
+            peer_id: credentials.device_id.clone(),
+            token: credentials.token.clone(),
+        }
+    }
+
+    /// The identity headers an HTTP request would carry, for the HTTP checks
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&self.peer_id) {
+            headers.insert(acl::PEER_ID_HEADER, value);
+        }
+        if let Some(Ok(value)) = self.token.as_deref().map(HeaderValue::from_str) {
+            headers.insert(auth::TOKEN_HEADER, value);
+        }
+        headers
+    }
+}
+
+/// Bind a UDP socket on `port` that accepts both IPv4 and IPv6
+fn bind_dual_stack_udp(port: u16) -> std::io::Result<std::net::UdpSocket> {
+    let socket = socket2::Socket::new(socket2::Domain::IPV6, socket2::Type::DGRAM, None)?;
+    socket.set_only_v6(false)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, port)).into())?;
+    Ok(socket.into())
+}
+
+/// Start the experimental QUIC server with a fresh self-signed certificate
+///
+/// Each bidirectional stream carries one `QuicRequest`; the reply is an IPC
+/// `TransferResponse` frame, followed by the file bytes for `get`.
+pub async fn start_quic_server(state: SharedServerState, port: u16) -> Result<(), String> {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+        .map_err(|e| format!("Failed to generate certificate: {}", e))?;
+    let cert_der = cert
+        .serialize_der()
+        .map_err(|e| format!("Failed to generate certificate: {}", e))?;
+    let key_der = cert.serialize_private_key_der();
+
+    let config = quinn::ServerConfig::with_single_cert(
+        vec![rustls::Certificate(cert_der)],
+        rustls::PrivateKey(key_der),
+    )
+    .map_err(|e| format!("Invalid QUIC certificate: {}", e))?;
+    let endpoint = match bind_dual_stack_udp(port) {
+        // One socket serves both IPv4 and IPv6 peers
+        Ok(socket) => quinn::Endpoint::new(
+            quinn::EndpointConfig::default(),
+            Some(config),
+            socket,
+            Arc::new(quinn::TokioRuntime),
+        ),
+        Err(e) => {
+            eprintln!("[QUIC] IPv6 unavailable, listening on IPv4 only: {}", e);
+            quinn::Endpoint::server(config, SocketAddr::from(([0, 0, 0, 0], port)))
+        }
+    }
+    .map_err(|e| format!("Failed to bind QUIC port {}: {}", port, e))?;
+
+    println!("[QUIC] Listening on UDP port {}", port);
+
+    while let Some(incoming) = endpoint.accept().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let connection = match incoming.await {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("[QUIC] Handshake failed: {}", e);
+                    return;
+                }
+            };
+            while let Ok((send, recv)) = connection.accept_bi().await {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_quic_stream(state, send, recv).await {
+                        eprintln!("[QUIC] {}", e);
+                    }
+                });
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Serve one `QuicRequest` on a bidirectional stream
+async fn handle_quic_stream(
+    state: SharedServerState,
+    mut send: quinn::SendStream,
+    recv: quinn::RecvStream,
+) -> Result<(), String> {
+    let mut reader = tokio::io::BufReader::new(recv);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+
+    let request = serde_json::from_str::<QuicRequest>(line.trim());
+    let result = match request.as_ref().map(|request| (request, &request.method)) {
+        Ok((request, QuicMethod::Get { filename })) => {
+            match open_quic_file(&state, request, filename).await {
+                Ok((mut file, size)) => {
+                    write_quic_response(&mut send, Ok(size)).await?;
+                    tokio::io::copy(&mut file, &mut send)
+                        .await
+                        .map_err(|e| format!("Transfer failed: {}", e))?;
+                    println!("[QUIC] Sent {} ({} bytes)", filename, size);
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Ok((request, QuicMethod::Upload { filename, transfer_request })) => {
+            match receive_quic_upload(&state, request, filename, transfer_request.as_deref(), &mut reader).await {
+                Ok(size) => {
+                    println!("[QUIC] Received {} ({} bytes)", filename, size);
+                    write_quic_response(&mut send, Ok(size)).await
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Err(e) => Err(format!("Invalid request: {}", e)),
+    };
+
+    if let Err(error) = result {
+        write_quic_response(&mut send, Err(error.clone())).await?;
+        let _ = send.finish().await;
+        return Err(error);
+    }
+
+    // Waits for the peer to acknowledge everything before the stream is dropped
+    send.finish()
+        .await
+        .map_err(|e| format!("Failed to finish stream: {}", e))
+}
+
+/// Open a shared file for a `get`, with the PIN, ACL and symlink checks of `serve_file`
+async fn open_quic_file(
+    state: &ServerState,
+    request: &QuicRequest,
+    filename: &str,
+) -> Result<(fs::File, u64), String> {
+    let headers = request.headers();
+    if !has_valid_token(state, &headers).await {
+        return Err("Missing or invalid X-AirShare-Token".to_string());
+    }
+    if !peer_allowed(state, filename, &headers).await {
+        return Err("Peer is not allowed to access this file".to_string());
+    }
+
+    let path = shared_file_path(&state.shared_dir, filename).map_err(|(_, message)| message)?;
+    if !path.exists() {
+        return Err(format!("File not found: {}", filename));
+    }
+    check_servable_path(&state.shared_dir, &path, state.config.follow_symlinks)
+        .await
+        .map_err(|(_, message)| message.to_string())?;
+
+    let file = fs::File::open(&path)
+        .await
+        .map_err(|_| format!("File not found: {}", filename))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    Ok((file, size))
+}
+
+/// Save the upload's remaining stream bytes into the shared directory
+///
+/// Goes through the same steps as `/upload`: PIN token, `check_incoming_file`,
+/// the collision strategy, the size limit, the processors, a `file-received`
+/// event and a history entry.
+async fn receive_quic_upload<R>(
+    state: &ServerState,
+    request: &QuicRequest,
+    filename: &str,
+    transfer_request: Option<&str>,
+    reader: &mut R,
+) -> Result<u64, String>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    if !has_valid_token(state, &request.headers()).await {
+        return Err("Missing or invalid X-AirShare-Token".to_string());
+    }
+    let accepted_size = check_incoming_file(state, filename, transfer_request)
+        .await
+        .map_err(|(_, message)| message)?;
+
+    let path = resolve_collision(&state.shared_dir, filename, state.config.file_collision_strategy)
+        .ok_or_else(|| format!("File already exists: {}", filename))?;
+    // Renaming may have picked a different name
+    let filename = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| filename.to_string());
+    let peer_id = Some(request.peer_id.clone()).filter(|id| !id.is_empty());
+    let started_at = history::now_ms();
+
+    let limit = state.max_upload_size_bytes.load(Ordering::Relaxed).min(accepted_size);
+    let size = match save_field(tokio_util::io::ReaderStream::new(reader), &path, limit, |_| {}).await {
+        Ok(size) => size,
+        Err(e) => {
+            let mut record = received_record(&filename, None, peer_id, started_at);
+            record.status = history::TransferStatus::Failed;
+            if let Err(e) = history::record(record) {
+                eprintln!("[QUIC] Failed to record {} in history: {}", filename, e);
+            }
+            return Err(e);
+        }
+    };
+    if size != accepted_size {
+        let _ = fs::remove_file(&path).await;
+        return Err(format!("{} is {} bytes, but {} were accepted", filename, size, accepted_size));
+    }
+    state.stats.record_upload(size);
+
+    if !process_received_file(state, "[QUIC]", &filename, &path).await {
+        return Err(format!("{} was quarantined", filename));
+    }
+
+    let mut record = received_record(&filename, None, peer_id, started_at);
+    record.size_bytes = size;
+    state.emit(
+        "file-received",
+        serde_json::json!({
+            "filename": filename,
+            "size": size,
+            "sender_name": record.peer_name,
+        }),
+    );
+    if let Err(e) = history::record(record) {
+        eprintln!("[QUIC] Failed to record {} in history: {}", filename, e);
+    }
+    Ok(size)
+}
+
+async fn write_quic_response(
+    send: &mut quinn::SendStream,
+    result: Result<u64, String>,
+) -> Result<(), String> {
+    let response = match result {
+        Ok(size) => TransferResponse {
+            ok: true,
+            size,
+            error: String::new(),
+        },
+        Err(error) => TransferResponse {
+            ok: false,
+            size: 0,
+            error,
+        },
+    };
+    ipc::write_frame(send, &response)
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+/// Download `filename` from a peer's QUIC server into `dest`
+pub async fn quic_download(
+    peer_ip: &str,
+    port: u16,
+    filename: &str,
+    dest: &std::path::Path,
+    credentials: &PeerCredentials,
+) -> Result<u64, String> {
+    let ip: std::net::IpAddr = peer_ip
+        .parse()
+        .map_err(|_| format!("Invalid peer IP: {}", peer_ip))?;
+
+    let unspecified = match ip {
+        std::net::IpAddr::V4(_) => std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        std::net::IpAddr::V6(_) => std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+    };
+    let mut endpoint = quinn::Endpoint::client(SocketAddr::new(unspecified, 0))
+        .map_err(|e| format!("Failed to bind QUIC client: {}", e))?;
+    endpoint.set_default_client_config(quic_client_config());
+
+    let connection = endpoint
+        .connect(SocketAddr::new(ip, port), "localhost")
+        .map_err(|e| format!("Failed to connect: {}", e))?
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+
+    let (mut send, recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| format!("Failed to open stream: {}", e))?;
+
+    let request = QuicRequest::new(
+        QuicMethod::Get {
+            filename: filename.to_string(),
+        },
+        credentials,
+    );
+    ipc::write_frame(&mut send, &request)
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+    send.finish()
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut reader = tokio::io::BufReader::new(recv);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let response: TransferResponse =
+        serde_json::from_str(line.trim()).map_err(|e| format!("Invalid response: {}", e))?;
+    if !response.ok {
+        return Err(response.error);
+    }
+
+    let mut file = fs::File::create(dest)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let bytes = tokio::io::copy(&mut reader, &mut file)
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    connection.close(0u32.into(), b"done");
+    endpoint.wait_idle().await;
+
+    if bytes != response.size {
+        return Err(format!(
+            "Transfer truncated: got {} of {} bytes",
+            bytes, response.size
+        ));
+    }
+    Ok(bytes)
+}
+
+/// Client config trusting any certificate; peers use throwaway self-signed certs
+fn quic_client_config() -> quinn::ClientConfig {
+    let crypto = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+        .with_no_client_auth();
+    quinn::ClientConfig::new(Arc::new(crypto))
+}
+
+/// Certificate verifier that skips identity checks (signatures are still checked)
+struct AcceptAnyCert;
+
+impl rustls::client::ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh empty directory under the system temp dir
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("airshare-{}-{}", label, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn peer_file_url_encodes_filename() {
+        let url = peer_file_url("192.168.1.5", 8080, "a b#1?.txt").unwrap();
+        assert_eq!(url.as_str(), "http://192.168.1.5:8080/file/a%20b%231%3F.txt");
+
+        let url = peer_file_url("fe80::1", 8080, "../x").unwrap();
+        assert_eq!(url.as_str(), "http://[fe80::1]:8080/file/..%2Fx");
+    }
+
+    #[test]
+    fn path_syntax_allows_inner_dots() {
+        for name in ["v1..2.zip", "notes...txt", "..hidden"] {
+            assert!(!has_path_syntax(name), "{:?}", name);
+        }
+        for name in ["..", "../x", "a/b", "a\\b"] {
+            assert!(has_path_syntax(name), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn resolve_collision_claims_the_name() {
+        let dir = temp_dir("collision");
+
+        let first = resolve_collision(&dir, "a.txt", CollisionStrategy::Reject).unwrap();
+        assert!(first.exists());
+        assert_eq!(resolve_collision(&dir, "a.txt", CollisionStrategy::Reject), None);
+
+        let renamed = resolve_collision(&dir, "a.txt", CollisionStrategy::Rename).unwrap();
+        let renamed_again = resolve_collision(&dir, "a.txt", CollisionStrategy::Rename).unwrap();
+        assert_eq!(renamed, dir.join("a (1).txt"));
+        assert_eq!(renamed_again, dir.join("a (2).txt"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn shared_file_path_forbids_traversal() {
+        let root = std::path::Path::new("/srv/shared");
+        for name in ["../secret", "..", "sub/../../secret", "/etc/passwd", "sub/file", ""] {
+            let rejection = shared_file_path(root, name).unwrap_err();
+            assert_eq!(rejection.0, StatusCode::FORBIDDEN, "{:?}", name);
+        }
+        assert_eq!(shared_file_path(root, "notes.txt").unwrap(), root.join("notes.txt"));
+    }
+
+    #[tokio::test]
+    async fn encoded_traversal_is_forbidden() {
+        // Path params arrive percent-decoded, so `..%2F` reaches the check as `../`
+        async fn lookup(Path(filename): Path<String>) -> StatusCode {
+            match shared_file_path(std::path::Path::new("/srv/shared"), &filename) {
+                Ok(_) => StatusCode::OK,
+                Err((status, _)) => status,
+            }
+        }
+        let app = Router::new().route("/file/{filename}", get(lookup));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        for path in ["..%2Fsecret", "..%2F..%2Fetc%2Fpasswd", "%2Fetc%2Fpasswd"] {
+            let status = reqwest::get(format!("{}/file/{}", base, path)).await.unwrap().status();
+            assert_eq!(status, reqwest::StatusCode::FORBIDDEN, "{}", path);
+        }
+        let status = reqwest::get(format!("{}/file/notes.txt", base)).await.unwrap().status();
+        assert_eq!(status, reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn cors_preflight_allows_only_listed_origins() {
+        // The only test reading this variable, so setting it can't race another test
+        std::env::set_var(CORS_ORIGINS_ENV, "http://localhost:3000");
+        let app = Router::new()
+            .route("/files", get(|| async { "[]" }))
+            .layer(cors_layer(&[]));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/files", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let preflight = |origin: &'static str| {
+            reqwest::Client::new()
+                .request(reqwest::Method::OPTIONS, &url)
+                .header("Origin", origin)
+                .header("Access-Control-Request-Method", "GET")
+                .send()
+        };
+        let allowed = preflight("http://localhost:3000").await.unwrap();
+        assert_eq!(
+            allowed.headers().get("access-control-allow-origin").and_then(|v| v.to_str().ok()),
+            Some("http://localhost:3000")
+        );
+        let refused = preflight("http://evil.com").await.unwrap();
+        assert!(refused.headers().get("access-control-allow-origin").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn check_servable_path_forbids_escaping_symlink() {
+        let root = temp_dir("shared");
+        let outside = temp_dir("outside");
+        std::fs::write(outside.join("secret.txt"), b"secret").unwrap();
+        std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.txt"), root.join("link.txt")).unwrap();
+
+        let link = shared_file_path(&root, "link.txt").unwrap();
+        let rejection = check_servable_path(&root, &link, true).await.unwrap_err();
+        assert_eq!(rejection.0, StatusCode::FORBIDDEN);
+        // Without symlink support the link is hidden instead
+        let rejection = check_servable_path(&root, &link, false).await.unwrap_err();
+        assert_eq!(rejection.0, StatusCode::NOT_FOUND);
+
+        let notes = shared_file_path(&root, "notes.txt").unwrap();
+        assert!(check_servable_path(&root, &notes, false).await.is_ok());
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&outside);
+    }
 }
@@ -1,53 +1,341 @@
 //! Smart Drop Module
 //! 
 //! Implements gesture-based copy/paste:
-//! - Closed Fist = Ctrl+C (copy selected item)
-//! - Open Palm = Ctrl+V (paste)
+//! - Closed Fist = Ctrl+C / Cmd+C (copy selected item)
+//! - Open Palm = Ctrl+V / Cmd+V (paste)
+//! - Print Screen = screenshot straight into the shared folder
+//! - Clipboard auto-upload = files copied on this device are sent to a peer
 
+use crate::clipboard;
+use crate::discovery::SharedDiscoveryState;
+use crate::server::{is_plain_filename, SharedServerState};
+use crate::transfers;
 use enigo::{Enigo, Key, Keyboard, Settings};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
 
-/// Simulate Ctrl+C (copy)
+/// Time the OS needs to place a screenshot on the clipboard
+const SCREENSHOT_CLIPBOARD_DELAY_MS: u64 = 200;
+
+/// How often the auto-upload watcher checks the clipboard for new files
+const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modifier of the copy/paste shortcuts: Cmd on macOS, Ctrl elsewhere
+pub fn platform_modifier() -> Key {
+    #[cfg(target_os = "macos")]
+    return Key::Meta;
+    #[cfg(not(target_os = "macos"))]
+    return Key::Control;
+}
+
+/// Press `letter` while holding the platform modifier
+fn send_shortcut(letter: char) -> Result<(), String> {
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to create enigo: {}", e))?;
+    let modifier = platform_modifier();
+
+    enigo.key(modifier, enigo::Direction::Press)
+        .map_err(|e| format!("Failed to press modifier: {}", e))?;
+
+    // Always release the modifier, even if the letter fails, so it doesn't stick
+    let result = enigo.key(Key::Unicode(letter), enigo::Direction::Click)
+        .map_err(|e| format!("Failed to press {}: {}", letter.to_ascii_uppercase(), e));
+
+    enigo.key(modifier, enigo::Direction::Release)
+        .map_err(|e| format!("Failed to release modifier: {}", e))?;
+
+    result
+}
+
+/// Simulate Ctrl+C / Cmd+C (copy)
 /// Called when user makes a closed fist gesture
 #[tauri::command]
 pub fn simulate_copy() -> Result<String, String> {
-    println!("[SmartDrop] 🤜 Simulating Ctrl+C (COPY)");
-    
-    let mut enigo = Enigo::new(&Settings::default())
-        .map_err(|e| format!("Failed to create enigo: {}", e))?;
-    
-    // Press Ctrl+C
-    enigo.key(Key::Control, enigo::Direction::Press)
-        .map_err(|e| format!("Failed to press Ctrl: {}", e))?;
-    
-    enigo.key(Key::Unicode('c'), enigo::Direction::Click)
-        .map_err(|e| format!("Failed to press C: {}", e))?;
-    
-    enigo.key(Key::Control, enigo::Direction::Release)
-        .map_err(|e| format!("Failed to release Ctrl: {}", e))?;
-    
-    println!("[SmartDrop] ✅ Ctrl+C sent!");
+    println!("[SmartDrop] 🤜 Simulating COPY");
+    send_shortcut('c')?;
+    println!("[SmartDrop] ✅ Copy shortcut sent!");
     Ok("Copied!".to_string())
 }
 
-/// Simulate Ctrl+V (paste)
+/// Simulate Ctrl+X / Cmd+X (cut)
+/// Grabbing a file moves it, so gestures can use this instead of copy
+#[tauri::command]
+pub fn simulate_cut() -> Result<String, String> {
+    println!("[SmartDrop] ✂️ Simulating CUT");
+    send_shortcut('x')?;
+    println!("[SmartDrop] ✅ Cut shortcut sent!");
+    Ok("Cut!".to_string())
+}
+
+/// Simulate Ctrl+V / Cmd+V (paste)
 /// Called when user opens palm after fist
 #[tauri::command]
 pub fn simulate_paste() -> Result<String, String> {
-    println!("[SmartDrop] 🖐️ Simulating Ctrl+V (PASTE)");
-    
+    println!("[SmartDrop] 🖐️ Simulating PASTE");
+    send_shortcut('v')?;
+    println!("[SmartDrop] ✅ Paste shortcut sent!");
+    Ok("Pasted!".to_string())
+}
+
+/// Absolute paths of the files on the clipboard
+fn clipboard_file_paths() -> Result<Vec<String>, String> {
+    let files = clipboard::platform_backend().get_files()?;
+
+    Ok(files
+        .into_iter()
+        .filter(|path| Path::new(path).is_absolute())
+        .collect())
+}
+
+/// Get absolute paths of files copied to the clipboard (e.g. from Explorer/Finder)
+#[tauri::command]
+pub fn get_clipboard_files_full_paths() -> Result<Vec<String>, String> {
+    let paths = clipboard_file_paths()?;
+    println!("[SmartDrop] 📋 {} file(s) on clipboard", paths.len());
+    Ok(paths)
+}
+
+/// The running clipboard auto-upload watcher, if any
+#[derive(Default)]
+pub struct ClipboardAutoUpload {
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Files on the clipboard right now; an empty or unreadable clipboard has none
+async fn poll_clipboard_files() -> Vec<String> {
+    tokio::task::spawn_blocking(clipboard_file_paths)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+}
+
+/// Upload files newly copied to the clipboard to `peer_ip` until aborted
+///
+/// Files already on the clipboard when the watcher starts are not sent, and
+/// a file is only sent again after something else has been copied.
+async fn watch_clipboard(app_handle: tauri::AppHandle, peer_ip: String) {
+    let discovery = app_handle.state::<SharedDiscoveryState>().inner().clone();
+    let mut last_seen = poll_clipboard_files().await;
+
+    loop {
+        tokio::time::sleep(CLIPBOARD_POLL_INTERVAL).await;
+
+        let files = poll_clipboard_files().await;
+        if files == last_seen {
+            continue;
+        }
+        let new_files: Vec<String> = files
+            .iter()
+            .filter(|path| !last_seen.contains(path) && Path::new(path).is_file())
+            .cloned()
+            .collect();
+        last_seen = files;
+
+        for path in new_files {
+            let filename = Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match transfers::send_file_to_peer(&app_handle, &discovery, &peer_ip, &path, Vec::new()).await {
+                Ok(()) => {
+                    println!("[SmartDrop] 📤 Auto-uploaded {} to {}", filename, peer_ip);
+                    let _ = app_handle.emit(
+                        "clipboard-auto-uploaded",
+                        serde_json::json!({ "filename": filename, "peer_ip": peer_ip }),
+                    );
+                }
+                Err(e) => eprintln!("[SmartDrop] Auto-upload of {} failed: {}", filename, e),
+            }
+        }
+    }
+}
+
+/// Tauri command to send every file copied to the clipboard to `peer_ip`
+/// Replaces a watcher already running for another peer.
+/// Emits `clipboard-auto-uploaded` with `{ filename, peer_ip }` per file sent.
+#[tauri::command]
+pub fn enable_clipboard_auto_upload(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, ClipboardAutoUpload>,
+    peer_ip: String,
+) -> Result<(), String> {
+    peer_ip
+        .parse::<std::net::IpAddr>()
+        .map_err(|_| format!("Invalid peer IP: {}", peer_ip))?;
+
+    let task = tauri::async_runtime::spawn(watch_clipboard(app_handle, peer_ip.clone()));
+    if let Some(previous) = state.task.lock().unwrap().replace(task) {
+        previous.abort();
+    }
+    println!("[SmartDrop] 📋 Auto-uploading clipboard files to {}", peer_ip);
+    Ok(())
+}
+
+/// Tauri command to stop sending clipboard files to a peer
+#[tauri::command]
+pub fn disable_clipboard_auto_upload(
+    state: tauri::State<'_, ClipboardAutoUpload>,
+) -> Result<(), String> {
+    if let Some(task) = state.task.lock().unwrap().take() {
+        task.abort();
+        println!("[SmartDrop] 📋 Clipboard auto-upload stopped");
+    }
+    Ok(())
+}
+
+/// Empty the clipboard, e.g. once copied files have been shared
+#[tauri::command]
+pub fn clear_clipboard() -> Result<(), String> {
+    clipboard::platform_backend().clear()?;
+    println!("[SmartDrop] 🧹 Clipboard cleared");
+    Ok(())
+}
+
+/// Copy a local file into the shared folder so peers can fetch it
+/// Returns the filename it is shared under
+#[tauri::command]
+pub async fn stage_file_for_sharing(
+    state: tauri::State<'_, SharedServerState>,
+    path: String,
+) -> Result<String, String> {
+    let source = std::path::Path::new(&path);
+    let filename = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| is_plain_filename(n))
+        .ok_or_else(|| format!("Invalid file path: {}", path))?
+        .to_string();
+
+    tokio::fs::copy(source, state.shared_dir.join(&filename))
+        .await
+        .map_err(|e| format!("Failed to stage {}: {}", path, e))?;
+
+    println!("[SmartDrop] 📦 Staged {} for sharing", filename);
+    Ok(filename)
+}
+
+/// Press the key combo that copies a full-screen screenshot to the clipboard
+fn send_print_screen() -> Result<(), String> {
     let mut enigo = Enigo::new(&Settings::default())
         .map_err(|e| format!("Failed to create enigo: {}", e))?;
-    
-    // Press Ctrl+V
-    enigo.key(Key::Control, enigo::Direction::Press)
-        .map_err(|e| format!("Failed to press Ctrl: {}", e))?;
-    
-    enigo.key(Key::Unicode('v'), enigo::Direction::Click)
-        .map_err(|e| format!("Failed to press V: {}", e))?;
-    
-    enigo.key(Key::Control, enigo::Direction::Release)
-        .map_err(|e| format!("Failed to release Ctrl: {}", e))?;
-    
-    println!("[SmartDrop] ✅ Ctrl+V sent!");
-    Ok("Pasted!".to_string())
+
+    // macOS has no Print Screen key; Cmd+Ctrl+Shift+3 captures to the clipboard
+    #[cfg(target_os = "macos")]
+    let (modifiers, key) = (vec![Key::Meta, Key::Control, Key::Shift], Key::Unicode('3'));
+    #[cfg(target_os = "windows")]
+    let (modifiers, key) = (Vec::new(), Key::Snapshot);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let (modifiers, key) = (Vec::new(), Key::Print);
+
+    for modifier in &modifiers {
+        enigo.key(*modifier, enigo::Direction::Press)
+            .map_err(|e| format!("Failed to press modifier: {}", e))?;
+    }
+    let result = enigo.key(key, enigo::Direction::Click)
+        .map_err(|e| format!("Failed to press Print Screen: {}", e));
+    for modifier in modifiers.iter().rev() {
+        let _ = enigo.key(*modifier, enigo::Direction::Release);
+    }
+    result
+}
+
+/// Save the image currently on the clipboard as a PNG
+///
+/// Returns false, writing nothing, if the clipboard holds no image.
+pub fn save_clipboard_image(dest: &Path) -> Result<bool, String> {
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| format!("Failed to open clipboard: {}", e))?;
+    let image = match clipboard.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(false),
+        Err(e) => return Err(format!("Failed to read clipboard image: {}", e)),
+    };
+
+    image::save_buffer(
+        dest,
+        &image.bytes,
+        image.width as u32,
+        image.height as u32,
+        image::ColorType::Rgba8,
+    )
+    .map_err(|e| format!("Failed to save clipboard image: {}", e))?;
+    Ok(true)
+}
+
+/// Take a full-screen screenshot and save it to the shared folder
+/// Returns the path of the saved PNG
+#[tauri::command]
+pub async fn simulate_print_screen(
+    state: tauri::State<'_, SharedServerState>,
+) -> Result<String, String> {
+    println!("[SmartDrop] 📸 Simulating Print Screen");
+    send_print_screen()?;
+
+    tokio::time::sleep(std::time::Duration::from_millis(SCREENSHOT_CLIPBOARD_DELAY_MS)).await;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = state.shared_dir.join(format!("screenshot_{}.png", timestamp));
+
+    let dest = path.clone();
+    let saved = tokio::task::spawn_blocking(move || save_clipboard_image(&dest))
+        .await
+        .map_err(|e| format!("Screenshot task failed: {}", e))??;
+    if !saved {
+        return Err("No screenshot on clipboard".to_string());
+    }
+
+    println!("[SmartDrop] ✅ Screenshot saved to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Open the OS region-snipping tool
+#[tauri::command]
+pub fn simulate_snipping_tool() -> Result<(), String> {
+    println!("[SmartDrop] ✂️ Opening snipping tool");
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        let mut enigo = Enigo::new(&Settings::default())
+            .map_err(|e| format!("Failed to create enigo: {}", e))?;
+
+        #[cfg(target_os = "windows")]
+        let (modifiers, key) = ([Key::Meta, Key::Shift], Key::Unicode('s'));
+        #[cfg(target_os = "macos")]
+        let (modifiers, key) = ([Key::Meta, Key::Shift], Key::Unicode('4'));
+
+        for modifier in &modifiers {
+            enigo.key(*modifier, enigo::Direction::Press)
+                .map_err(|e| format!("Failed to press modifier: {}", e))?;
+        }
+        let result = enigo.key(key, enigo::Direction::Click)
+            .map_err(|e| format!("Failed to press key: {}", e));
+        for modifier in modifiers.iter().rev() {
+            let _ = enigo.key(*modifier, enigo::Direction::Release);
+        }
+        result
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // Desktops without gnome-screenshot usually bind their own tool to PrtSc
+        if std::process::Command::new("gnome-screenshot")
+            .arg("--interactive")
+            .spawn()
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        let mut enigo = Enigo::new(&Settings::default())
+            .map_err(|e| format!("Failed to create enigo: {}", e))?;
+        enigo.key(Key::Print, enigo::Direction::Click)
+            .map_err(|e| format!("Failed to press Print Screen: {}", e))
+    }
 }
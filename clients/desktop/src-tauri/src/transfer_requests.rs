@@ -0,0 +1,315 @@
+// Accept/reject prompts for incoming transfers
+//
+// Before uploading, the sender POSTs the file's metadata to
+// `/request-transfer`. The receiver emits `transfer-request` so the frontend
+// can ask the user, and the sender polls `/transfer-response/{request_id}`
+// until `accept_transfer` or `reject_transfer` is called. The upload then
+// carries the accepted request ID in `X-AirShare-Transfer-Request`, and
+// `/upload` refuses files that weren't accepted. Peers without these routes
+// predate the prompt and are sent to directly.
+
+use crate::auth::PeerCredentials;
+use crate::server::{has_path_syntax, has_valid_token, is_plain_filename, peer_base_url, SharedServerState};
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Error returned by `request_transfer` when the receiver declines the file
+pub const TRANSFER_REJECTED: &str = "Transfer rejected";
+/// Header naming the accepted request an `/upload` belongs to
+pub const TRANSFER_REQUEST_HEADER: &str = "X-AirShare-Transfer-Request";
+
+/// Unanswered requests count as rejected after this long
+const TRANSFER_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest a `/transfer-response` call waits for a decision before answering `pending`
+const RESPONSE_WAIT: Duration = Duration::from_secs(10);
+/// Delay between `/transfer-response` polls when the peer answers immediately
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where a transfer request stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDecision {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// Body of `POST /request-transfer`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRequestBody {
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+    #[serde(default)]
+    pub sender_name: String,
+}
+
+/// Response of `POST /request-transfer`
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferRequestCreated {
+    request_id: String,
+}
+
+/// Response of `GET /transfer-response/{request_id}`
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferResponseBody {
+    status: TransferDecision,
+}
+
+struct PendingRequest {
+    decision: watch::Sender<TransferDecision>,
+    created_at: Instant,
+    filename: String,
+    size: u64,
+}
+
+/// Transfer requests received by this device, keyed by request ID
+#[derive(Default)]
+pub struct TransferRequests {
+    requests: Mutex<HashMap<String, PendingRequest>>,
+}
+
+impl TransferRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new pending request and return its ID
+    fn insert(&self, body: &TransferRequestBody) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut requests = self.requests.lock().unwrap();
+        // Senders stop polling after the timeout, so older entries are dead
+        requests.retain(|_, request| request.created_at.elapsed() < TRANSFER_REQUEST_TIMEOUT * 2);
+        requests.insert(
+            id.clone(),
+            PendingRequest {
+                decision: watch::Sender::new(TransferDecision::Pending),
+                created_at: Instant::now(),
+                filename: body.filename.clone(),
+                size: body.size,
+            },
+        );
+        id
+    }
+
+    /// Watch a request's decision; requests left pending too long are rejected
+    fn watch(&self, id: &str) -> Option<watch::Receiver<TransferDecision>> {
+        let requests = self.requests.lock().unwrap();
+        let request = requests.get(id)?;
+        if request.created_at.elapsed() >= TRANSFER_REQUEST_TIMEOUT {
+            request.decision.send_if_modified(|decision| {
+                let expired = *decision == TransferDecision::Pending;
+                if expired {
+                    *decision = TransferDecision::Rejected;
+                }
+                expired
+            });
+        }
+        Some(request.decision.subscribe())
+    }
+
+    /// Use up an accepted request for `filename`, returning the size it announced
+    ///
+    /// `None` if the request is unknown, not accepted or for another file.
+    /// Each request covers a single upload.
+    pub fn take_accepted(&self, id: &str, filename: &str) -> Option<u64> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = requests.get(id)?;
+        if *request.decision.borrow() != TransferDecision::Accepted || request.filename != filename {
+            return None;
+        }
+        requests.remove(id).map(|request| request.size)
+    }
+
+    /// Answer a pending request
+    pub fn decide(&self, id: &str, decision: TransferDecision) -> Result<(), String> {
+        let requests = self.requests.lock().unwrap();
+        let request = requests
+            .get(id)
+            .ok_or_else(|| format!("No transfer request: {}", id))?;
+        if *request.decision.borrow() != TransferDecision::Pending {
+            return Err(format!("Transfer request already answered: {}", id));
+        }
+        request.decision.send_replace(decision);
+        Ok(())
+    }
+}
+
+/// Transfer request routes, merged into the main server router
+pub fn routes() -> Router<SharedServerState> {
+    Router::new()
+        .route("/request-transfer", post(handle_request_transfer))
+        .route("/transfer-response/{request_id}", get(handle_transfer_response))
+}
+
+/// POST /request-transfer
+///
+/// Files the upload limits would refuse are turned down without asking, and
+/// once a PIN is set so is any sender without the PIN's token.
+async fn handle_request_transfer(
+    State(state): State<SharedServerState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<TransferRequestBody>,
+) -> impl IntoResponse {
+    if !has_valid_token(&state, &headers).await {
+        println!("[Transfers] Rejected request for {} from {}: bad token", body.filename, remote.ip());
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid X-AirShare-Token").into_response();
+    }
+    if !is_plain_filename(&body.filename) || has_path_syntax(&body.filename) {
+        return (StatusCode::BAD_REQUEST, format!("Invalid filename: {}", body.filename))
+            .into_response();
+    }
+    if !state.accepts_upload(&body.filename).await {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("File type not allowed: {}", body.filename),
+        )
+            .into_response();
+    }
+    if body.size > state.max_upload_size_bytes.load(Ordering::Relaxed) {
+        return (StatusCode::PAYLOAD_TOO_LARGE, crate::server::UPLOAD_TOO_LARGE.to_string())
+            .into_response();
+    }
+
+    let request_id = state.transfer_requests.insert(&body);
+    println!(
+        "[Transfers] {} at {} wants to send {} ({} bytes)",
+        body.sender_name,
+        remote.ip(),
+        body.filename,
+        body.size
+    );
+    state.emit(
+        "transfer-request",
+        serde_json::json!({
+            "request_id": request_id,
+            "filename": body.filename,
+            "size": body.size,
+            "sha256": body.sha256,
+            "sender_name": body.sender_name,
+            "sender_ip": remote.ip().to_string(),
+        }),
+    );
+    (StatusCode::ACCEPTED, Json(TransferRequestCreated { request_id })).into_response()
+}
+
+/// GET /transfer-response/{request_id}
+///
+/// Waits up to `RESPONSE_WAIT` for the user to decide, then answers with
+/// the current status.
+async fn handle_transfer_response(
+    State(state): State<SharedServerState>,
+    Path(request_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(mut decision) = state.transfer_requests.watch(&request_id) else {
+        return (StatusCode::NOT_FOUND, format!("No transfer request: {}", request_id))
+            .into_response();
+    };
+
+    let _ = tokio::time::timeout(
+        RESPONSE_WAIT,
+        decision.wait_for(|decision| *decision != TransferDecision::Pending),
+    )
+    .await;
+    let status = *decision.borrow();
+    Json(TransferResponseBody { status }).into_response()
+}
+
+/// Ask a peer to accept a file and wait until the user there decides
+///
+/// Returns the accepted request ID to send with the upload, or `None` for
+/// peers too old to prompt, which accept everything. Fails with
+/// `TRANSFER_REJECTED` if the user declines or does not answer in time.
+pub async fn request_transfer(
+    peer_ip: &str,
+    port: u16,
+    body: &TransferRequestBody,
+    credentials: &PeerCredentials,
+) -> Result<Option<String>, String> {
+    let client = reqwest::Client::new();
+    let response = credentials
+        .apply(client.post(format!("{}/request-transfer", peer_base_url(peer_ip, port))).json(body))
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        println!("[Transfers] {} does not prompt for transfers, sending directly", peer_ip);
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!("Peer refused {}: {} {}", body.filename, status, message));
+    }
+    let TransferRequestCreated { request_id } = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid transfer request response: {}", e))?;
+
+    println!("[Transfers] Waiting for {} to accept {}", peer_ip, body.filename);
+    let url = format!("{}/transfer-response/{}", peer_base_url(peer_ip, port), request_id);
+    let deadline = Instant::now() + TRANSFER_REQUEST_TIMEOUT + RESPONSE_WAIT;
+    while Instant::now() < deadline {
+        let polled_at = Instant::now();
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("HTTP request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()));
+        }
+        let TransferResponseBody { status } = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid transfer response: {}", e))?;
+
+        match status {
+            TransferDecision::Accepted => return Ok(Some(request_id)),
+            TransferDecision::Rejected => return Err(TRANSFER_REJECTED.to_string()),
+            TransferDecision::Pending => {
+                // Don't spin against a peer that answers without waiting
+                if polled_at.elapsed() < POLL_INTERVAL {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+    Err(TRANSFER_REJECTED.to_string())
+}
+
+/// Tauri command to accept a `transfer-request`
+#[tauri::command]
+pub fn accept_transfer(
+    state: tauri::State<'_, SharedServerState>,
+    request_id: String,
+) -> Result<(), String> {
+    state.transfer_requests.decide(&request_id, TransferDecision::Accepted)?;
+    println!("[Transfers] Accepted request {}", request_id);
+    Ok(())
+}
+
+/// Tauri command to reject a `transfer-request`
+#[tauri::command]
+pub fn reject_transfer(
+    state: tauri::State<'_, SharedServerState>,
+    request_id: String,
+) -> Result<(), String> {
+    state.transfer_requests.decide(&request_id, TransferDecision::Rejected)?;
+    println!("[Transfers] Rejected request {}", request_id);
+    Ok(())
+}
@@ -0,0 +1,340 @@
+// Prioritised download queue and cancellation of running downloads
+//
+// Downloads submitted through `download_file_priority` run one at a time,
+// highest priority first; equal priorities run in the order they were queued.
+// Downloads started with a transfer ID can be aborted through `TransferState`.
+// Uploads go out through `send_file_to_peer` once the peer accepts them.
+
+use crate::discovery::{Peer, SharedDiscoveryState};
+use crate::history;
+use crate::server;
+use crate::transfer_requests::{self, TransferRequestBody};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+use tokio::sync::{oneshot, Notify};
+use tokio_util::sync::CancellationToken;
+
+/// A queued download
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingTransfer {
+    pub id: String,
+    pub url: String,
+    pub dest: String,
+    /// 0-255, higher runs first
+    pub priority: u8,
+    /// Unix timestamp (ms) when the transfer was queued
+    pub enqueued_at: u64,
+    /// Enqueue order, breaks ties between equal priorities
+    #[serde(skip)]
+    sequence: u64,
+}
+
+impl Ord for PendingTransfer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            // Earlier transfers sort higher so the max-heap pops them first
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for PendingTransfer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PendingTransfer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PendingTransfer {}
+
+type TransferResult = Result<String, String>;
+
+/// Queue state shared between the Tauri commands and the worker
+#[derive(Default)]
+pub struct TransferQueue {
+    pending: Mutex<BinaryHeap<PendingTransfer>>,
+    /// Completion channels for callers awaiting a queued transfer
+    waiters: Mutex<HashMap<String, oneshot::Sender<TransferResult>>>,
+    next_sequence: AtomicU64,
+    wake: Notify,
+}
+
+pub type SharedTransferQueue = Arc<TransferQueue>;
+
+impl TransferQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a download and return a receiver for its result
+    fn push(&self, url: String, dest: String, priority: u8) -> oneshot::Receiver<TransferResult> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        let transfer = PendingTransfer {
+            id: id.clone(),
+            url,
+            dest,
+            priority,
+            enqueued_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            sequence: self.next_sequence.fetch_add(1, AtomicOrdering::Relaxed),
+        };
+
+        println!("[Transfers] Queued {} (priority {})", transfer.url, priority);
+        self.waiters.lock().unwrap().insert(id, tx);
+        self.pending.lock().unwrap().push(transfer);
+        self.wake.notify_one();
+        rx
+    }
+
+    /// Pending transfers in the order they will run
+    pub fn ordered(&self) -> Vec<PendingTransfer> {
+        self.pending.lock().unwrap().clone().into_sorted_vec().into_iter().rev().collect()
+    }
+
+    fn pop(&self) -> Option<PendingTransfer> {
+        self.pending.lock().unwrap().pop()
+    }
+}
+
+/// Payload of the `transfer-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub bytes_done: u64,
+    /// From `Content-Length`, if the peer sent one
+    pub bytes_total: Option<u64>,
+    pub metrics: server::TransferMetrics,
+}
+
+impl TransferProgress {
+    pub fn new(transfer_id: &str, progress: &server::DownloadProgress) -> Self {
+        Self {
+            transfer_id: transfer_id.to_string(),
+            bytes_done: progress.bytes_downloaded,
+            bytes_total: progress.total_bytes,
+            metrics: progress.metrics,
+        }
+    }
+}
+
+/// Cancellation tokens of running downloads, keyed by transfer ID
+#[derive(Default)]
+pub struct TransferState {
+    active: Mutex<HashMap<String, CancellationToken>>,
+}
+
+pub type SharedTransferState = Arc<TransferState>;
+
+impl TransferState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a running transfer and return the token it should watch
+    pub fn start(&self, transfer_id: &str) -> Result<CancellationToken, String> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(transfer_id) {
+            return Err(format!("Transfer already running: {}", transfer_id));
+        }
+        let token = CancellationToken::new();
+        active.insert(transfer_id.to_string(), token.clone());
+        Ok(token)
+    }
+
+    /// Forget a transfer once it has finished, failed or been cancelled
+    pub fn finish(&self, transfer_id: &str) {
+        self.active.lock().unwrap().remove(transfer_id);
+    }
+
+    /// Cancel a running transfer, returning false if no transfer has that ID
+    pub fn cancel(&self, transfer_id: &str) -> bool {
+        match self.active.lock().unwrap().remove(transfer_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Run queued downloads one at a time, forever
+///
+/// Downloads identify this device with the credentials from `discovery`.
+pub async fn run_transfer_worker(queue: SharedTransferQueue, discovery: SharedDiscoveryState) {
+    loop {
+        let Some(transfer) = queue.pop() else {
+            queue.wake.notified().await;
+            continue;
+        };
+
+        println!("[Transfers] Starting {} (priority {})", transfer.url, transfer.priority);
+        let (credentials, peer) = {
+            let discovery = discovery.read().await;
+            (discovery.credentials(), discovery.peer_for_url(&transfer.url).cloned())
+        };
+        let started_at = history::now_ms();
+        let result = server::download_file(
+            &transfer.url,
+            &transfer.dest,
+            None,
+            Some(&credentials),
+            None,
+            |_| {},
+        )
+        .await
+        .map(|_| transfer.dest.clone());
+
+        if let Err(e) = &result {
+            eprintln!("[Transfers] {} failed: {}", transfer.url, e);
+        }
+        record_download(&transfer, peer, started_at, &result).await;
+        if let Some(waiter) = queue.waiters.lock().unwrap().remove(&transfer.id) {
+            let _ = waiter.send(result);
+        }
+    }
+}
+
+/// Log a finished queued download in the transfer history
+async fn record_download(
+    transfer: &PendingTransfer,
+    peer: Option<Peer>,
+    started_at: u64,
+    result: &TransferResult,
+) {
+    let path = std::path::Path::new(&transfer.dest);
+    let record = history::TransferRecord {
+        filename: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        size_bytes: tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0),
+        peer_name: peer.as_ref().map(|p| p.name.clone()),
+        peer_id: peer.map(|p| p.id),
+        direction: history::Direction::Received,
+        started_at,
+        status: history::TransferStatus::of(result),
+        ..Default::default()
+    };
+    if let Err(e) = history::record(record) {
+        eprintln!("[History] {}", e);
+    }
+}
+
+/// Tauri command to download a file through the priority queue
+#[tauri::command]
+pub async fn download_file_priority(
+    queue: tauri::State<'_, SharedTransferQueue>,
+    url: String,
+    dest: String,
+    priority: u8,
+) -> Result<String, String> {
+    let done = queue.push(url, dest, priority);
+    done.await.map_err(|_| "Transfer queue stopped".to_string())?
+}
+
+/// Tauri command to abort a download started with `transfer_id`
+#[tauri::command]
+pub fn cancel_transfer(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SharedTransferState>,
+    transfer_id: String,
+) -> Result<(), String> {
+    if !state.cancel(&transfer_id) {
+        return Err(format!("No running transfer: {}", transfer_id));
+    }
+
+    println!("[Transfers] Cancelled {}", transfer_id);
+    let _ = app_handle.emit(
+        "transfer-cancelled",
+        serde_json::json!({ "transfer_id": transfer_id }),
+    );
+    Ok(())
+}
+
+/// Tauri command to list queued transfers, next to run first
+#[tauri::command]
+pub async fn get_transfer_queue_ordered(
+    queue: tauri::State<'_, SharedTransferQueue>,
+) -> Result<Vec<PendingTransfer>, String> {
+    Ok(queue.ordered())
+}
+
+/// Ask a peer to accept a local file, upload it tagged with sender metadata
+/// and record it in the history. Emits `transfer-send-progress` as the file
+/// is streamed.
+pub async fn send_file_to_peer(
+    app_handle: &tauri::AppHandle,
+    discovery: &SharedDiscoveryState,
+    peer_ip: &str,
+    local_path: &str,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let (sender_name, port, peer, credentials) = {
+        let state = discovery.read().await;
+        (
+            state.device_name.clone(),
+            state.peer_server_port(peer_ip),
+            state.peer_by_ip(peer_ip).cloned(),
+            state.credentials(),
+        )
+    };
+    let path = std::path::Path::new(local_path);
+    let tags = history::normalize_tags(tags);
+    let started_at = history::now_ms();
+
+    let upload = async {
+        let sha256 = server::sha256_file(path).await?;
+        let request = TransferRequestBody {
+            filename: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0),
+            sha256: sha256.clone(),
+            sender_name: sender_name.clone(),
+        };
+        let request_id = transfer_requests::request_transfer(peer_ip, port, &request, &credentials).await?;
+
+        let meta = serde_json::json!({
+            "sender_name": sender_name,
+            "app_version": env!("CARGO_PKG_VERSION"),
+            "sha256": sha256,
+            "tags": tags
+        });
+        let progress_handle = app_handle.clone();
+        server::upload_file(peer_ip, port, local_path, meta, &credentials, request_id.as_deref(), move |progress| {
+            let _ = progress_handle.emit("transfer-send-progress", &progress);
+        })
+        .await
+    };
+    let result = upload.await;
+
+    let filename = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let size = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+    let record = history::TransferRecord {
+        filename,
+        size_bytes: size,
+        peer_name: peer.as_ref().map(|p| p.name.clone()),
+        peer_id: peer.map(|p| p.id),
+        direction: history::Direction::Sent,
+        started_at,
+        status: history::TransferStatus::of(&result),
+        tags,
+        ..Default::default()
+    };
+    if let Err(e) = history::record(record) {
+        eprintln!("[History] {}", e);
+    }
+    result
+}